@@ -0,0 +1,234 @@
+// Command-line argument parsing
+//
+//   nn [--encoding NAME] [--line N [--col N]] [--new] [--] [PATH]
+//
+// Options also accept the `--option=value` form.
+
+use crate::file_io::FileEncoding;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandLine {
+    /// File to open (or, with `--new`, the name for the new document)
+    pub path: Option<PathBuf>,
+    pub encoding: FileEncoding,
+    /// 1-indexed caret line
+    pub line: Option<usize>,
+    /// 1-indexed caret column, only used together with `line`
+    pub col: Option<usize>,
+    /// Start with an empty document instead of reading `path`
+    pub new_file: bool,
+}
+
+impl Default for CommandLine {
+    fn default() -> Self {
+        CommandLine {
+            path: None,
+            encoding: FileEncoding::Auto,
+            line: None,
+            col: None,
+            new_file: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CliError {
+    UnknownOption(String),
+    MissingValue(String),
+    InvalidEncoding(String),
+    InvalidNumber { option: String, value: String },
+    TooManyPaths,
+}
+
+/// Look up an encoding by name, ignoring case and `-`/`_` differences
+pub fn parse_encoding(name: &str) -> Option<FileEncoding> {
+    let normalized: String = name
+        .chars()
+        .filter(|ch| *ch != '-' && *ch != '_')
+        .map(|ch| ch.to_ascii_lowercase())
+        .collect();
+
+    match normalized.as_str() {
+        "auto" => Some(FileEncoding::Auto),
+        "utf8" => Some(FileEncoding::Utf8),
+        "utf8bom" | "utf8sig" => Some(FileEncoding::Utf8Bom),
+        "utf16" | "utf16le" => Some(FileEncoding::Utf16Le),
+        "utf16be" => Some(FileEncoding::Utf16Be),
+        "utf32" | "utf32le" => Some(FileEncoding::Utf32Le),
+        "utf32be" => Some(FileEncoding::Utf32Be),
+        "sjis" | "shiftjis" | "cp932" | "windows31j" | "ms932" => Some(FileEncoding::ShiftJis),
+        "eucjp" | "cp51932" => Some(FileEncoding::EucJp),
+        "iso2022jp" | "jis" | "cp50221" => Some(FileEncoding::Iso2022Jp),
+        _ => None,
+    }
+}
+
+fn parse_number(option: &str, value: &str) -> Result<usize, CliError> {
+    match value.parse::<usize>() {
+        Ok(number) if number >= 1 => Ok(number),
+        _ => Err(CliError::InvalidNumber {
+            option: option.to_string(),
+            value: value.to_string(),
+        }),
+    }
+}
+
+/// Parse the arguments that follow the program name
+pub fn parse<I>(args: I) -> Result<CommandLine, CliError>
+where
+    I: IntoIterator<Item = OsString>,
+{
+    let mut command_line = CommandLine::default();
+    let mut args = args.into_iter();
+    let mut options_ended = false;
+
+    while let Some(arg) = args.next() {
+        let option = arg
+            .to_str()
+            .filter(|text| !options_ended && text.starts_with("--"));
+        let Some(option) = option else {
+            if command_line.path.is_some() {
+                return Err(CliError::TooManyPaths);
+            }
+            command_line.path = Some(PathBuf::from(arg));
+            continue;
+        };
+
+        if option == "--" {
+            options_ended = true;
+            continue;
+        }
+        if option == "--new" {
+            command_line.new_file = true;
+            continue;
+        }
+
+        // Options with a value: "--name value" or "--name=value"
+        let (name, inline_value) = match option.split_once('=') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (option, None),
+        };
+        if !matches!(name, "--encoding" | "--line" | "--col") {
+            return Err(CliError::UnknownOption(name.to_string()));
+        }
+        let value = match inline_value {
+            Some(value) => value,
+            None => args
+                .next()
+                .and_then(|value| value.into_string().ok())
+                .ok_or_else(|| CliError::MissingValue(name.to_string()))?,
+        };
+
+        match name {
+            "--encoding" => {
+                command_line.encoding =
+                    parse_encoding(&value).ok_or(CliError::InvalidEncoding(value))?;
+            }
+            "--line" => command_line.line = Some(parse_number(name, &value)?),
+            _ => command_line.col = Some(parse_number(name, &value)?),
+        }
+    }
+
+    Ok(command_line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_strs(args: &[&str]) -> Result<CommandLine, CliError> {
+        parse(args.iter().map(OsString::from))
+    }
+
+    #[test]
+    fn test_no_arguments() {
+        assert_eq!(parse_strs(&[]), Ok(CommandLine::default()));
+    }
+
+    #[test]
+    fn test_path_only() {
+        let command_line = parse_strs(&["C:\\notes\\todo.txt"]).unwrap();
+        assert_eq!(
+            command_line.path,
+            Some(PathBuf::from("C:\\notes\\todo.txt"))
+        );
+        assert_eq!(command_line.encoding, FileEncoding::Auto);
+        assert!(!command_line.new_file);
+    }
+
+    #[test]
+    fn test_all_options() {
+        let command_line = parse_strs(&[
+            "--encoding",
+            "cp932",
+            "--line",
+            "120",
+            "--col",
+            "5",
+            "a.txt",
+        ])
+        .unwrap();
+        assert_eq!(command_line.path, Some(PathBuf::from("a.txt")));
+        assert_eq!(command_line.encoding, FileEncoding::ShiftJis);
+        assert_eq!(command_line.line, Some(120));
+        assert_eq!(command_line.col, Some(5));
+    }
+
+    #[test]
+    fn test_inline_values() {
+        let command_line = parse_strs(&["a.txt", "--encoding=EUC-JP", "--line=3"]).unwrap();
+        assert_eq!(command_line.encoding, FileEncoding::EucJp);
+        assert_eq!(command_line.line, Some(3));
+        assert_eq!(command_line.col, None);
+    }
+
+    #[test]
+    fn test_new_file() {
+        let command_line = parse_strs(&["--new", "draft.txt"]).unwrap();
+        assert!(command_line.new_file);
+        assert_eq!(command_line.path, Some(PathBuf::from("draft.txt")));
+    }
+
+    #[test]
+    fn test_double_dash_ends_options() {
+        let command_line = parse_strs(&["--", "--new"]).unwrap();
+        assert!(!command_line.new_file);
+        assert_eq!(command_line.path, Some(PathBuf::from("--new")));
+    }
+
+    #[test]
+    fn test_encoding_names() {
+        assert_eq!(parse_encoding("Shift_JIS"), Some(FileEncoding::ShiftJis));
+        assert_eq!(parse_encoding("utf-8"), Some(FileEncoding::Utf8));
+        assert_eq!(parse_encoding("UTF-8-BOM"), Some(FileEncoding::Utf8Bom));
+        assert_eq!(parse_encoding("utf-16be"), Some(FileEncoding::Utf16Be));
+        assert_eq!(parse_encoding("iso-2022-jp"), Some(FileEncoding::Iso2022Jp));
+        assert_eq!(parse_encoding("latin1"), None);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            parse_strs(&["--bogus"]),
+            Err(CliError::UnknownOption("--bogus".to_string()))
+        );
+        assert_eq!(
+            parse_strs(&["--line"]),
+            Err(CliError::MissingValue("--line".to_string()))
+        );
+        assert_eq!(
+            parse_strs(&["--encoding", "latin1"]),
+            Err(CliError::InvalidEncoding("latin1".to_string()))
+        );
+        assert_eq!(
+            parse_strs(&["--col", "0"]),
+            Err(CliError::InvalidNumber {
+                option: "--col".to_string(),
+                value: "0".to_string()
+            })
+        );
+        assert_eq!(parse_strs(&["a.txt", "b.txt"]), Err(CliError::TooManyPaths));
+    }
+}
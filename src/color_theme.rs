@@ -0,0 +1,173 @@
+// Color themes: the built-in light and dark palettes, and user themes read from files
+//
+// A theme file is an INI file (see `ini`) with a [Theme] section. Base names the built-in
+// theme that supplies any color left out, and whether the title bar, scroll bars and popup
+// menus that Windows draws are dark. Colors are written as #RRGGBB.
+//
+//   [Theme]
+//   Base=Dark
+//   EditorBackground=#002B36
+//   EditorText=#839496
+
+use crate::ini;
+
+/// A color as 0x00BBGGRR, the layout of a Win32 COLORREF
+pub type Color = u32;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    /// Whether Windows draws the title bar, scroll bars and popup menus dark
+    pub dark: bool,
+    pub editor_bg: Color,
+    pub editor_text: Color,
+    pub menu_bg: Color,
+    pub menu_hover: Color,
+    pub menu_text: Color,
+    pub menu_text_disabled: Color,
+    /// The line between the menu bar and the editor
+    pub menu_border: Color,
+    pub statusbar_bg: Color,
+    pub statusbar_text: Color,
+    pub statusbar_hover: Color,
+    /// Lines between the editor and the status bar, and between status bar segments
+    pub separator: Color,
+    pub button_bg: Color,
+    pub button_pressed: Color,
+    pub button_border: Color,
+}
+
+const SECTION: &str = "Theme";
+
+impl Theme {
+    pub const LIGHT: Theme = Theme {
+        dark: false,
+        editor_bg: 0x00FFFFFF,
+        editor_text: 0x00000000,
+        menu_bg: 0x00F0F0F0,
+        menu_hover: 0x00DADADA,
+        menu_text: 0x00000000,
+        menu_text_disabled: 0x006D6D6D,
+        menu_border: 0x00D0D0D0,
+        statusbar_bg: 0x00F0F0F0,
+        statusbar_text: 0x00000000,
+        statusbar_hover: 0x00DADADA,
+        separator: 0x00D0D0D0,
+        button_bg: 0x00E1E1E1,
+        button_pressed: 0x00F7E4CC,
+        button_border: 0x00ADADAD,
+    };
+
+    pub const DARK: Theme = Theme {
+        dark: true,
+        editor_bg: 0x001E1E1E,
+        editor_text: 0x00E0E0E0,
+        menu_bg: 0x00202020,
+        menu_hover: 0x00404040,
+        menu_text: 0x00FFFFFF,
+        menu_text_disabled: 0x00808080,
+        menu_border: 0x00262624,
+        statusbar_bg: 0x00202020,
+        statusbar_text: 0x00E0E0E0,
+        statusbar_hover: 0x00383838,
+        separator: 0x00404040,
+        button_bg: 0x00333333,
+        button_pressed: 0x00454545,
+        button_border: 0x00606060,
+    };
+
+    /// Read a theme file. Unknown keys and colors that do not parse are ignored.
+    pub fn parse(text: &str) -> Theme {
+        let entries = ini::parse_section(text, SECTION);
+        let dark = entries
+            .iter()
+            .find(|(key, _)| key == "Base")
+            .is_some_and(|(_, base)| base.eq_ignore_ascii_case("Dark"));
+        let mut theme = if dark { Theme::DARK } else { Theme::LIGHT };
+
+        for (key, value) in &entries {
+            let Some(color) = parse_color(value) else {
+                continue;
+            };
+            let field = match key.as_str() {
+                "EditorBackground" => &mut theme.editor_bg,
+                "EditorText" => &mut theme.editor_text,
+                "MenuBackground" => &mut theme.menu_bg,
+                "MenuHover" => &mut theme.menu_hover,
+                "MenuText" => &mut theme.menu_text,
+                "MenuDisabledText" => &mut theme.menu_text_disabled,
+                "MenuBorder" => &mut theme.menu_border,
+                "StatusBarBackground" => &mut theme.statusbar_bg,
+                "StatusBarText" => &mut theme.statusbar_text,
+                "StatusBarHover" => &mut theme.statusbar_hover,
+                "Separator" => &mut theme.separator,
+                "ButtonBackground" => &mut theme.button_bg,
+                "ButtonPressed" => &mut theme.button_pressed,
+                "ButtonBorder" => &mut theme.button_border,
+                _ => continue,
+            };
+            *field = color;
+        }
+        theme
+    }
+}
+
+// #RRGGBB to 0x00BBGGRR
+fn parse_color(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    let rgb = u32::from_str_radix(hex, 16).ok()?;
+    let (r, g, b) = (rgb >> 16, (rgb >> 8) & 0xFF, rgb & 0xFF);
+    Some((b << 16) | (g << 8) | r)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_color() {
+        assert_eq!(parse_color("#002B36"), Some(0x00362B00));
+        assert_eq!(parse_color("#ffffff"), Some(0x00FFFFFF));
+        assert_eq!(parse_color("002B36"), None);
+        assert_eq!(parse_color("#02B36"), None);
+        assert_eq!(parse_color("#+02B36"), None);
+        assert_eq!(parse_color("#00 B36"), None);
+    }
+
+    #[test]
+    fn test_parse() {
+        let theme = Theme::parse(
+            "; Solarized Dark\r\n\
+             [Theme]\r\n\
+             Base = dark\r\n\
+             EditorBackground=#002B36\r\n\
+             EditorText=#839496\r\n\
+             MenuHover=blue\r\n\
+             Unknown=#FFFFFF\r\n\
+             [Other]\r\n\
+             Separator=#FFFFFF\r\n",
+        );
+        assert_eq!(
+            theme,
+            Theme {
+                editor_bg: 0x00362B00,
+                editor_text: 0x00969483,
+                ..Theme::DARK
+            }
+        );
+    }
+
+    #[test]
+    fn test_base_defaults_to_light() {
+        assert_eq!(Theme::parse(""), Theme::LIGHT);
+        assert_eq!(
+            Theme::parse("[Theme]\nBase=Sepia\nMenuBackground=#F4ECD8"),
+            Theme {
+                menu_bg: 0x00D8ECF4,
+                ..Theme::LIGHT
+            }
+        );
+    }
+}
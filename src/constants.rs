@@ -17,10 +17,16 @@ pub const EM_SETLANGOPTIONS: u32 = 0x0478;
 pub const EM_GETLANGOPTIONS: u32 = 0x0479;
 pub const EM_SETPARAFORMAT: u32 = 0x0447;
 pub const ES_MULTILINE: u32 = 0x0004;
+pub const ES_NOHIDESEL: u32 = 0x0100;
 pub const EC_TOPMARGIN: u32 = 0x0002;
 pub const IMF_AUTOFONT: u32 = 0x0002;
 pub const IMF_DUALFONT: u32 = 0x0080;
 
+// Editor zoom in percent: Zoom In and Zoom Out move in steps within these bounds
+pub const ZOOM_MIN: u32 = 10;
+pub const ZOOM_MAX: u32 = 500;
+pub const ZOOM_STEP: u32 = 10;
+
 // PARAFORMAT2 constants
 pub const PFM_LINESPACING: u32 = 0x00000100;
 pub const PFM_SPACEBEFORE: u32 = 0x00000040;
@@ -41,4 +47,36 @@ pub const ID_VIEW_WORDWRAP: i32 = 11;
 pub const ID_VIEW_STATUSBAR: i32 = 12;
 pub const ID_EDIT_SELECTALL: i32 = 13;
 pub const ID_EDIT_DELETE: i32 = 14;
-pub const ID_VIEW_DARKMODE: i32 = 15;
+pub const ID_EDIT_LINEENDING_CRLF: i32 = 16;
+pub const ID_EDIT_LINEENDING_LF: i32 = 17;
+pub const ID_EDIT_LINEENDING_CR: i32 = 18;
+pub const ID_FILE_BACKUP: i32 = 19;
+pub const ID_EDIT_GOTO: i32 = 20;
+pub const ID_EDIT_FIND: i32 = 21;
+pub const ID_EDIT_FIND_NEXT: i32 = 22;
+pub const ID_EDIT_FIND_PREV: i32 = 23;
+pub const ID_EDIT_REPLACE: i32 = 24;
+pub const ID_FORMAT_FONT: i32 = 25;
+pub const ID_VIEW_ZOOMIN: i32 = 26;
+pub const ID_VIEW_ZOOMOUT: i32 = 27;
+pub const ID_VIEW_ZOOMRESET: i32 = 28;
+pub const ID_VIEW_THEME_SYSTEM: i32 = 29;
+pub const ID_VIEW_THEME_LIGHT: i32 = 30;
+pub const ID_VIEW_THEME_DARK: i32 = 31;
+
+// Clickable status bar segments, sent to the main window as WM_COMMAND when clicked
+pub const ID_STATUS_POSITION: i32 = 40;
+pub const ID_STATUS_ENCODING: i32 = 41;
+pub const ID_STATUS_ZOOM: i32 = 42;
+pub const ID_STATUS_LINEENDING: i32 = 43;
+
+// Encoding menu: one command per entry in the menu's encoding list, counting up from these
+pub const ID_ENCODING_REOPEN_FIRST: i32 = 100;
+pub const ID_ENCODING_SAVE_FIRST: i32 = 120;
+
+// Theme menu: one command per user theme, counting up from this
+pub const ID_VIEW_THEME_USER_FIRST: i32 = 200;
+pub const MAX_USER_THEMES: usize = 100;
+
+// File > Recent Files: one command per entry in the recent files list, counting up from this
+pub const ID_FILE_RECENT_FIRST: i32 = 300;
@@ -0,0 +1,373 @@
+// CP932 (Microsoft's Shift_JIS) encoder and decoder
+//
+// Converts independently of the system ANSI code page, so "Shift-JIS" means the same
+// thing on every machine. Covers JIS X 0208, NEC special characters (row 13),
+// NEC-selected IBM extensions, IBM extensions and the user-defined area.
+
+mod table;
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use table::{DOUBLE_BYTE, TRAIL_COUNT};
+
+// Byte written in place of characters that CP932 cannot represent
+pub const REPLACEMENT_BYTE: u8 = b'?';
+
+// Unicode -> CP932 double-byte code (built once from the decoding table)
+static ENCODE_TABLE: OnceLock<HashMap<u16, u16>> = OnceLock::new();
+
+// Unicode -> NEC-selected IBM extension code (0xED/0xEE lead bytes only)
+static NEC_SELECTED_TABLE: OnceLock<HashMap<u16, u16>> = OnceLock::new();
+
+/// Returns true if the byte starts a double-byte sequence
+pub fn is_lead_byte(byte: u8) -> bool {
+    matches!(byte, 0x81..=0x9F | 0xE0..=0xFC)
+}
+
+// Index into the decoding table for a lead/trail byte pair
+fn table_index(lead: u8, trail: u8) -> Option<usize> {
+    let lead_index = match lead {
+        0x81..=0x9F => lead - 0x81,
+        0xE0..=0xFC => lead - 0xE0 + 31,
+        _ => return None,
+    } as usize;
+
+    if !(0x40..=0xFC).contains(&trail) || trail == 0x7F {
+        return None;
+    }
+
+    Some(lead_index * TRAIL_COUNT + (trail - 0x40) as usize)
+}
+
+/// Decode a double-byte sequence to its UTF-16 code unit
+pub fn decode_pair(lead: u8, trail: u8) -> Option<u16> {
+    let index = table_index(lead, trail)?;
+    match DOUBLE_BYTE[index] {
+        0 => None,
+        code => Some(code),
+    }
+}
+
+/// Decode CP932 bytes to a string
+///
+/// Invalid sequences are replaced with U+FFFD. An invalid double-byte sequence whose
+/// second byte is ASCII only consumes the lead byte, so the ASCII character survives.
+pub fn decode(bytes: &[u8]) -> String {
+    decode_with_errors(bytes).0
+}
+
+/// Decode CP932 bytes, also returning the byte offset of every invalid sequence
+pub fn decode_with_errors(bytes: &[u8]) -> (String, Vec<usize>) {
+    let mut result = String::with_capacity(bytes.len());
+    let mut errors = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let byte = bytes[i];
+        match byte {
+            0x00..=0x80 => {
+                result.push(byte as char);
+                i += 1;
+            }
+            0xA1..=0xDF => {
+                // Half-width katakana
+                let code = 0xFF61 + (byte - 0xA1) as u32;
+                result.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+                i += 1;
+            }
+            // Windows maps these single bytes to the private use area
+            0xA0 => {
+                result.push('\u{F8F0}');
+                i += 1;
+            }
+            0xFD..=0xFF => {
+                let code = 0xF8F1 + (byte - 0xFD) as u32;
+                result.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+                i += 1;
+            }
+            _ if is_lead_byte(byte) => match bytes.get(i + 1) {
+                Some(&trail) => {
+                    if let Some(code) = decode_pair(byte, trail) {
+                        result.push(char::from_u32(code as u32).unwrap_or('\u{FFFD}'));
+                        i += 2;
+                    } else {
+                        result.push('\u{FFFD}');
+                        errors.push(i);
+                        i += if trail < 0x80 { 1 } else { 2 };
+                    }
+                }
+                None => {
+                    result.push('\u{FFFD}');
+                    errors.push(i);
+                    i += 1;
+                }
+            },
+            _ => {
+                result.push('\u{FFFD}');
+                errors.push(i);
+                i += 1;
+            }
+        }
+    }
+
+    (result, errors)
+}
+
+// Build the reverse table. Where a character has several codes, the first one in
+// byte order wins, except that NEC-selected IBM extensions (0xED/0xEE) are only used
+// when nothing else maps to the character. This matches WideCharToMultiByte.
+fn encode_table() -> &'static HashMap<u16, u16> {
+    ENCODE_TABLE.get_or_init(|| {
+        let mut map = HashMap::with_capacity(DOUBLE_BYTE.len());
+        for nec_selected_pass in [false, true] {
+            for lead in (0x81..=0x9Fu8).chain(0xE0..=0xFC) {
+                if matches!(lead, 0xED | 0xEE) != nec_selected_pass {
+                    continue;
+                }
+                for trail in 0x40..=0xFCu8 {
+                    if let Some(code) = decode_pair(lead, trail) {
+                        map.entry(code)
+                            .or_insert(((lead as u16) << 8) | trail as u16);
+                    }
+                }
+            }
+        }
+        map
+    })
+}
+
+/// Encode a single character
+///
+/// Returns the single byte value (below 0x100) or the double-byte code
+/// (lead byte in the high 8 bits), or None if CP932 cannot represent the character.
+pub fn encode_char(ch: char) -> Option<u16> {
+    let code = ch as u32;
+    match code {
+        0x00..=0x80 => Some(code as u16),
+        0xFF61..=0xFF9F => Some((code - 0xFF61 + 0xA1) as u16),
+        0xF8F0 => Some(0xA0),
+        0xF8F1..=0xF8F3 => Some((code - 0xF8F1 + 0xFD) as u16),
+        0x81..=0xFFFF => encode_table().get(&(code as u16)).copied(),
+        _ => None,
+    }
+}
+
+// Build the reverse table for NEC-selected IBM extensions only
+fn nec_selected_table() -> &'static HashMap<u16, u16> {
+    NEC_SELECTED_TABLE.get_or_init(|| {
+        let mut map = HashMap::new();
+        for lead in [0xEDu8, 0xEE] {
+            for trail in 0x40..=0xFCu8 {
+                if let Some(code) = decode_pair(lead, trail) {
+                    map.entry(code)
+                        .or_insert(((lead as u16) << 8) | trail as u16);
+                }
+            }
+        }
+        map
+    })
+}
+
+/// Encode a character to a double-byte code within the 94 JIS rows (lead byte up to 0xEF)
+///
+/// IBM extensions are replaced by their NEC-selected equivalents, because EUC-JP and
+/// ISO-2022-JP have no room for the IBM rows. Single-byte characters return None.
+pub fn encode_char_jis_rows(ch: char) -> Option<u16> {
+    match encode_char(ch)? {
+        code @ 0x8140..=0xEFFC => Some(code),
+        code if code >= 0xFA40 => nec_selected_table().get(&(ch as u16)).copied(),
+        _ => None,
+    }
+}
+
+/// Convert a Shift_JIS double-byte code to a JIS row/cell pair (each 0x21..=0x7E)
+pub fn sjis_to_jis(code: u16) -> Option<(u8, u8)> {
+    let lead = (code >> 8) as u8;
+    let trail = (code & 0xFF) as u8;
+    if !matches!(lead, 0x81..=0x9F | 0xE0..=0xEF) || table_index(lead, trail).is_none() {
+        return None;
+    }
+
+    let lead_offset = if lead <= 0x9F { 0x70 } else { 0xB0 };
+    let mut row = (lead - lead_offset) * 2;
+    let cell = if trail >= 0x9F {
+        trail - 0x7E
+    } else {
+        row -= 1;
+        if trail >= 0x80 {
+            trail - 0x20
+        } else {
+            trail - 0x1F
+        }
+    };
+    Some((row, cell))
+}
+
+/// Convert a JIS row/cell pair (each 0x21..=0x7E) to a Shift_JIS double-byte code
+pub fn jis_to_sjis(row: u8, cell: u8) -> Option<u16> {
+    if !(0x21..=0x7E).contains(&row) || !(0x21..=0x7E).contains(&cell) {
+        return None;
+    }
+
+    let lead = if row <= 0x5E {
+        row.div_ceil(2) + 0x70
+    } else {
+        row.div_ceil(2) + 0xB0
+    };
+    let trail = if row.is_multiple_of(2) {
+        cell + 0x7E
+    } else if cell >= 0x60 {
+        cell + 0x20
+    } else {
+        cell + 0x1F
+    };
+    Some(((lead as u16) << 8) | trail as u16)
+}
+
+/// Returns true if CP932 can represent the character
+pub fn can_encode(ch: char) -> bool {
+    encode_char(ch).is_some()
+}
+
+/// Encode a string as CP932
+///
+/// Characters that CP932 cannot represent are written as `?`.
+pub fn encode(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    for ch in text.chars() {
+        match encode_char(ch) {
+            Some(code) if code > 0xFF => {
+                bytes.push((code >> 8) as u8);
+                bytes.push((code & 0xFF) as u8);
+            }
+            Some(code) => bytes.push(code as u8),
+            None => bytes.push(REPLACEMENT_BYTE),
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ascii_round_trip() {
+        let text = "hello, world\r\n~\\";
+        assert_eq!(encode(text), text.as_bytes());
+        assert_eq!(decode(text.as_bytes()), text);
+    }
+
+    #[test]
+    fn test_hiragana_and_kanji() {
+        assert_eq!(encode("あ漢字"), [0x82, 0xA0, 0x8A, 0xBF, 0x8E, 0x9A]);
+        assert_eq!(decode(&[0x82, 0xA0, 0x8A, 0xBF, 0x8E, 0x9A]), "あ漢字");
+    }
+
+    #[test]
+    fn test_half_width_katakana() {
+        assert_eq!(encode("ｱﾝ"), [0xB1, 0xDD]);
+        assert_eq!(decode(&[0xB1, 0xDD]), "ｱﾝ");
+    }
+
+    #[test]
+    fn test_nec_special_characters() {
+        assert_eq!(decode(&[0x87, 0x40]), "①");
+        assert_eq!(encode("①Ⅰ"), [0x87, 0x40, 0x87, 0x54]);
+    }
+
+    #[test]
+    fn test_ibm_extensions() {
+        assert_eq!(decode(&[0xFB, 0xFC]), "髙");
+        assert_eq!(encode("髙"), [0xFB, 0xFC]);
+    }
+
+    #[test]
+    fn test_nec_selected_ibm_extensions_encode_as_ibm() {
+        assert_eq!(decode(&[0xED, 0x40]), "纊");
+        assert_eq!(encode("纊"), [0xFA, 0x5C]);
+        assert_eq!(encode("ⅰ"), [0xFA, 0x40]);
+    }
+
+    #[test]
+    fn test_duplicates_prefer_jis_row_2() {
+        assert_eq!(encode("￢∵"), [0x81, 0xCA, 0x81, 0xE6]);
+    }
+
+    #[test]
+    fn test_microsoft_specific_mappings() {
+        assert_eq!(decode(&[0x81, 0x60, 0x81, 0x7C, 0x81, 0x5F]), "～－＼");
+        assert_eq!(encode("～－＼"), [0x81, 0x60, 0x81, 0x7C, 0x81, 0x5F]);
+    }
+
+    #[test]
+    fn test_user_defined_area() {
+        assert_eq!(decode(&[0xF0, 0x40]), "\u{E000}");
+        assert_eq!(encode("\u{E000}"), [0xF0, 0x40]);
+    }
+
+    #[test]
+    fn test_unmappable_characters() {
+        assert_eq!(encode("a😀b"), b"a?b");
+        assert_eq!(encode_char('😀'), None);
+    }
+
+    #[test]
+    fn test_jis_conversion() {
+        // あ is JIS 0x2422, Shift_JIS 0x82A0
+        assert_eq!(sjis_to_jis(0x82A0), Some((0x24, 0x22)));
+        assert_eq!(jis_to_sjis(0x24, 0x22), Some(0x82A0));
+        // 漢 is JIS 0x3441, Shift_JIS 0x8ABF
+        assert_eq!(sjis_to_jis(0x8ABF), Some((0x34, 0x41)));
+        assert_eq!(jis_to_sjis(0x34, 0x41), Some(0x8ABF));
+        // Last row
+        assert_eq!(sjis_to_jis(0xEFFC), Some((0x7E, 0x7E)));
+        assert_eq!(jis_to_sjis(0x7E, 0x7E), Some(0xEFFC));
+        // IBM extensions have no JIS row
+        assert_eq!(sjis_to_jis(0xFA40), None);
+    }
+
+    #[test]
+    fn test_jis_round_trip_all_rows() {
+        for row in 0x21..=0x7Eu8 {
+            for cell in 0x21..=0x7Eu8 {
+                let code = jis_to_sjis(row, cell).unwrap();
+                assert_eq!(sjis_to_jis(code), Some((row, cell)));
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_char_jis_rows() {
+        assert_eq!(encode_char_jis_rows('漢'), Some(0x8ABF));
+        assert_eq!(encode_char_jis_rows('髙'), Some(0xEEE0));
+        assert_eq!(encode_char_jis_rows('a'), None);
+        assert_eq!(encode_char_jis_rows('\u{E000}'), None);
+    }
+
+    #[test]
+    fn test_invalid_sequences() {
+        // Lead byte followed by ASCII keeps the ASCII character
+        assert_eq!(decode(&[0x82, 0x41]), "\u{FFFD}A");
+        // Truncated double-byte sequence
+        assert_eq!(decode(&[0x61, 0x82]), "a\u{FFFD}");
+    }
+
+    #[test]
+    fn test_single_bytes_like_windows() {
+        // MultiByteToWideChar maps these instead of rejecting them
+        let bytes = [0x80, 0xA0, 0xFD, 0xFE, 0xFF];
+        let (text, errors) = decode_with_errors(&bytes);
+        assert_eq!(text, "\u{80}\u{F8F0}\u{F8F1}\u{F8F2}\u{F8F3}");
+        assert!(errors.is_empty());
+        assert_eq!(encode(&text), bytes);
+    }
+
+    #[test]
+    fn test_error_offsets() {
+        let (text, errors) = decode_with_errors(&[0x82, 0xA0, 0x82, 0x41, 0xFC, 0x61, 0x82]);
+        assert_eq!(text, "あ\u{FFFD}A\u{FFFD}a\u{FFFD}");
+        assert_eq!(errors, vec![2, 4, 6]);
+        assert!(decode_with_errors(b"valid").1.is_empty());
+    }
+}
@@ -0,0 +1,367 @@
+// Encoding detection for FileEncoding::Auto
+//
+// BOMs and ISO-2022-JP escape sequences are decisive. NUL bytes point to UTF-16 or
+// UTF-32 without a BOM, since 8-bit text files practically never contain them.
+// Otherwise the bytes are decoded as UTF-8, CP932 and EUC-JP, and each result is
+// scored by how much it looks like real Japanese (or Western) text: hiragana and kanji
+// score well, decoding errors, control characters and private-use characters score
+// badly.
+
+use crate::cp932;
+use crate::euc_jp;
+use crate::file_io::FileEncoding;
+use crate::iso_2022_jp;
+
+/// Results below this confidence are reported as ambiguous
+pub const AMBIGUOUS_CONFIDENCE: f32 = 0.5;
+
+// Penalty for each invalid sequence
+const ERROR_WEIGHT: f32 = -10.0;
+
+// Number of scored characters needed before a result is trusted fully
+const MIN_EVIDENCE: f32 = 8.0;
+
+// Share of NUL bytes that must fall on one side of each UTF-16 code unit
+const UTF16_NUL_SHARE: f32 = 0.9;
+
+// Minimum NULs per UTF-16 code unit (about one ASCII character or line break in 20)
+const UTF16_MIN_NUL_RATIO: f32 = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Detection {
+    pub encoding: FileEncoding,
+    /// How sure the detector is, from 0.0 to 1.0
+    pub confidence: f32,
+    /// True if another encoding was nearly as likely
+    pub ambiguous: bool,
+}
+
+impl Detection {
+    fn certain(encoding: FileEncoding) -> Self {
+        Detection {
+            encoding,
+            confidence: 1.0,
+            ambiguous: false,
+        }
+    }
+}
+
+// Score of one candidate decoding
+#[derive(Debug, Clone, Copy)]
+struct Score {
+    encoding: FileEncoding,
+    total: f32,
+    chars: usize,
+}
+
+// How typical a non-ASCII character is in a text file
+fn char_weight(ch: char) -> f32 {
+    match ch as u32 {
+        0xFFFD => ERROR_WEIGHT,
+        0x0080..=0x009F => -5.0,  // C1 control characters
+        0x00A0..=0x024F => 1.0,   // Latin-1 and Latin Extended
+        0x0370..=0x04FF => 0.0,   // Greek and Cyrillic
+        0x2000..=0x206F => 1.0,   // General punctuation
+        0x2460..=0x24FF => 0.5,   // Enclosed alphanumerics
+        0x2500..=0x257F => 0.0,   // Box drawing
+        0x2100..=0x2BFF => -0.5,  // Arrows, math and misc symbols
+        0x3000..=0x303F => 2.0,   // CJK punctuation
+        0x3041..=0x309F => 3.0,   // Hiragana
+        0x30A0..=0x30FF => 2.0,   // Katakana
+        0x4E00..=0x9FFF => 1.0,   // CJK unified ideographs
+        0xE000..=0xF8FF => -3.0,  // Private use (user-defined characters)
+        0xF900..=0xFAFF => -0.5,  // CJK compatibility ideographs
+        0xFF01..=0xFF5E => 1.0,   // Full-width ASCII variants
+        0xFF61..=0xFF9F => 0.2,   // Half-width katakana
+        0x1F000..=0x1FAFF => 1.0, // Emoji (only reachable from UTF-8)
+        _ => -1.0,
+    }
+}
+
+// UTF-32 without a BOM: every code unit must be a valid, non-NUL character. This is
+// strict enough that UTF-16 and 8-bit text essentially never pass.
+fn detect_utf32(bytes: &[u8]) -> Option<FileEncoding> {
+    if bytes.is_empty() || !bytes.len().is_multiple_of(4) {
+        return None;
+    }
+
+    let all_valid = |from_bytes: fn([u8; 4]) -> u32| {
+        bytes
+            .chunks_exact(4)
+            .map(|chunk| from_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .all(|code| code != 0 && char::from_u32(code).is_some())
+    };
+
+    if all_valid(u32::from_le_bytes) {
+        Some(FileEncoding::Utf32Le)
+    } else if all_valid(u32::from_be_bytes) {
+        Some(FileEncoding::Utf32Be)
+    } else {
+        None
+    }
+}
+
+// UTF-16 without a BOM: ASCII characters (including line breaks) leave a NUL in the
+// high byte, so the NULs pile up on the odd offsets for LE and the even ones for BE
+fn detect_utf16(bytes: &[u8]) -> Option<Detection> {
+    let mut even_nuls = 0usize;
+    let mut odd_nuls = 0usize;
+    for (i, &byte) in bytes.iter().enumerate() {
+        if byte == 0 {
+            if i.is_multiple_of(2) {
+                even_nuls += 1;
+            } else {
+                odd_nuls += 1;
+            }
+        }
+    }
+
+    let (encoding, dominant, other) = if odd_nuls >= even_nuls {
+        (FileEncoding::Utf16Le, odd_nuls, even_nuls)
+    } else {
+        (FileEncoding::Utf16Be, even_nuls, odd_nuls)
+    };
+    if (dominant as f32) < (dominant + other) as f32 * UTF16_NUL_SHARE
+        || (dominant as f32) < (bytes.len() / 2) as f32 * UTF16_MIN_NUL_RATIO
+    {
+        return None;
+    }
+
+    // Unpaired surrogates mean this is not UTF-16 after all
+    let units: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|chunk| match encoding {
+            FileEncoding::Utf16Le => u16::from_le_bytes([chunk[0], chunk[1]]),
+            _ => u16::from_be_bytes([chunk[0], chunk[1]]),
+        })
+        .collect();
+    let Ok(text) = String::from_utf16(&units) else {
+        return None;
+    };
+
+    // Both the NULs and the characters they decode to count as evidence
+    let text_score = score(encoding, &text);
+    let margin = (dominant - other) as f32 / (dominant + other) as f32;
+    let evidence = ((dominant as f32 + text_score.total.max(0.0)) / MIN_EVIDENCE).min(1.0);
+    let confidence = margin * evidence;
+    Some(Detection {
+        encoding,
+        confidence,
+        ambiguous: confidence < AMBIGUOUS_CONFIDENCE,
+    })
+}
+
+fn score(encoding: FileEncoding, text: &str) -> Score {
+    let mut total = 0.0;
+    let mut chars = 0;
+    for ch in text.chars().filter(|ch| !ch.is_ascii()) {
+        total += char_weight(ch);
+        chars += 1;
+    }
+    Score {
+        encoding,
+        total,
+        chars,
+    }
+}
+
+/// Detect the encoding of a file's contents
+pub fn detect(bytes: &[u8]) -> Detection {
+    // Byte order marks (UTF-32 LE first, its BOM starts like the UTF-16 LE one)
+    if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return Detection::certain(FileEncoding::Utf8Bom);
+    }
+    if bytes.starts_with(&[0xFF, 0xFE, 0x00, 0x00]) {
+        return Detection::certain(FileEncoding::Utf32Le);
+    }
+    if bytes.starts_with(&[0x00, 0x00, 0xFE, 0xFF]) {
+        return Detection::certain(FileEncoding::Utf32Be);
+    }
+    if bytes.starts_with(&[0xFF, 0xFE]) {
+        return Detection::certain(FileEncoding::Utf16Le);
+    }
+    if bytes.starts_with(&[0xFE, 0xFF]) {
+        return Detection::certain(FileEncoding::Utf16Be);
+    }
+
+    // NUL bytes: UTF-16 or UTF-32 without a BOM
+    if bytes.contains(&0) {
+        if let Some(encoding) = detect_utf32(bytes) {
+            return Detection::certain(encoding);
+        }
+        if let Some(detection) = detect_utf16(bytes) {
+            return detection;
+        }
+    }
+
+    // 7-bit text is either plain ASCII or ISO-2022-JP
+    if bytes.is_ascii() {
+        if iso_2022_jp::has_escape_sequences(bytes) {
+            return Detection::certain(FileEncoding::Iso2022Jp);
+        }
+        return Detection::certain(FileEncoding::Utf8);
+    }
+
+    // Candidates in order of preference when scores tie
+    let mut scores = [
+        score(FileEncoding::Utf8, &String::from_utf8_lossy(bytes)),
+        score(FileEncoding::ShiftJis, &cp932::decode(bytes)),
+        score(FileEncoding::EucJp, &euc_jp::decode(bytes)),
+    ];
+    scores.sort_by(|a, b| b.total.total_cmp(&a.total));
+
+    let best = scores[0];
+    let runner_up = scores[1];
+
+    let confidence = if best.total <= 0.0 {
+        0.0
+    } else {
+        let margin = (best.total - runner_up.total.max(0.0)) / best.total;
+        let evidence = (best.chars as f32 / MIN_EVIDENCE).min(1.0);
+        (margin * evidence).clamp(0.0, 1.0)
+    };
+
+    Detection {
+        encoding: best.encoding,
+        confidence,
+        ambiguous: confidence < AMBIGUOUS_CONFIDENCE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JAPANESE: &str = "吾輩は猫である。名前はまだ無い。\r\nどこで生れたかとんと見当がつかぬ。";
+
+    #[test]
+    fn test_ascii_is_utf8() {
+        let detection = detect(b"hello world\r\n");
+        assert_eq!(detection.encoding, FileEncoding::Utf8);
+        assert!(!detection.ambiguous);
+    }
+
+    #[test]
+    fn test_bom() {
+        assert_eq!(
+            detect(&[0xEF, 0xBB, 0xBF, 0x41]).encoding,
+            FileEncoding::Utf8Bom
+        );
+        assert_eq!(
+            detect(&[0xFF, 0xFE, 0x41, 0x00]).encoding,
+            FileEncoding::Utf16Le
+        );
+        assert_eq!(
+            detect(&[0xFE, 0xFF, 0x00, 0x41]).encoding,
+            FileEncoding::Utf16Be
+        );
+    }
+
+    #[test]
+    fn test_utf32_bom() {
+        assert_eq!(
+            detect(&[0xFF, 0xFE, 0x00, 0x00, 0x41, 0x00, 0x00, 0x00]).encoding,
+            FileEncoding::Utf32Le
+        );
+        assert_eq!(
+            detect(&[0x00, 0x00, 0xFE, 0xFF, 0x00, 0x00, 0x00, 0x41]).encoding,
+            FileEncoding::Utf32Be
+        );
+    }
+
+    #[test]
+    fn test_utf16_without_bom() {
+        let le: Vec<u8> = JAPANESE.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let detection = detect(&le);
+        assert_eq!(detection.encoding, FileEncoding::Utf16Le);
+        assert!(!detection.ambiguous);
+
+        let be: Vec<u8> = "hello\r\nworld"
+            .encode_utf16()
+            .flat_map(u16::to_be_bytes)
+            .collect();
+        let detection = detect(&be);
+        assert_eq!(detection.encoding, FileEncoding::Utf16Be);
+        assert!(!detection.ambiguous);
+    }
+
+    #[test]
+    fn test_utf32_without_bom() {
+        let le: Vec<u8> = JAPANESE
+            .chars()
+            .flat_map(|ch| (ch as u32).to_le_bytes())
+            .collect();
+        assert_eq!(detect(&le).encoding, FileEncoding::Utf32Le);
+
+        let be: Vec<u8> = "hello"
+            .chars()
+            .flat_map(|ch| (ch as u32).to_be_bytes())
+            .collect();
+        assert_eq!(detect(&be).encoding, FileEncoding::Utf32Be);
+    }
+
+    #[test]
+    fn test_stray_nul_is_not_utf16() {
+        let mut bytes = cp932::encode(JAPANESE);
+        bytes.push(0x00);
+        assert_eq!(detect(&bytes).encoding, FileEncoding::ShiftJis);
+    }
+
+    #[test]
+    fn test_utf8_japanese() {
+        let detection = detect(JAPANESE.as_bytes());
+        assert_eq!(detection.encoding, FileEncoding::Utf8);
+        assert!(detection.confidence > 0.9);
+        assert!(!detection.ambiguous);
+    }
+
+    #[test]
+    fn test_shift_jis_japanese() {
+        let detection = detect(&cp932::encode(JAPANESE));
+        assert_eq!(detection.encoding, FileEncoding::ShiftJis);
+        assert!(!detection.ambiguous);
+    }
+
+    #[test]
+    fn test_euc_jp_japanese() {
+        let detection = detect(&euc_jp::encode(JAPANESE));
+        assert_eq!(detection.encoding, FileEncoding::EucJp);
+        assert!(!detection.ambiguous);
+    }
+
+    #[test]
+    fn test_iso_2022_jp_japanese() {
+        let detection = detect(&iso_2022_jp::encode(JAPANESE));
+        assert_eq!(detection.encoding, FileEncoding::Iso2022Jp);
+    }
+
+    #[test]
+    fn test_shift_jis_that_is_valid_utf8_is_ambiguous() {
+        // E3 81 8A 41 is two kanji in CP932 and "おA" in UTF-8
+        let bytes = [0xE3, 0x81, 0x8A, 0x41];
+        assert!(std::str::from_utf8(&bytes).is_ok());
+        assert_eq!(cp932::decode(&bytes).chars().count(), 2);
+        assert!(detect(&bytes).ambiguous);
+    }
+
+    #[test]
+    fn test_shift_jis_with_valid_utf8_prefix() {
+        // The first two characters are valid UTF-8, the rest of the text is not
+        let mut bytes = vec![0xE3, 0x81, 0x8A, 0x41];
+        bytes.extend(cp932::encode(JAPANESE));
+        assert_eq!(detect(&bytes).encoding, FileEncoding::ShiftJis);
+    }
+
+    #[test]
+    fn test_utf8_latin() {
+        let detection = detect("café crème brûlée".as_bytes());
+        assert_eq!(detection.encoding, FileEncoding::Utf8);
+    }
+
+    #[test]
+    fn test_short_input_is_ambiguous() {
+        // A single two-byte sequence decodes as something in every candidate
+        let detection = detect(&[0xB0, 0xA1]);
+        assert!(detection.ambiguous);
+    }
+}
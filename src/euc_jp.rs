@@ -0,0 +1,184 @@
+// EUC-JP encoder and decoder
+//
+// Follows Microsoft's CP51932: JIS X 0208 with NEC special characters and NEC-selected
+// IBM extensions in the two-byte area, plus half-width katakana behind SS2 (0x8E).
+// JIS X 0212 (SS3, 0x8F) is recognized but decoded as U+FFFD.
+
+use crate::cp932;
+
+const SS2: u8 = 0x8E;
+const SS3: u8 = 0x8F;
+
+fn is_euc_byte(byte: u8) -> bool {
+    (0xA1..=0xFE).contains(&byte)
+}
+
+// Decode a two-byte JIS X 0208 sequence through the CP932 table
+fn decode_pair(lead: u8, trail: u8) -> Option<u16> {
+    if !is_euc_byte(lead) || !is_euc_byte(trail) {
+        return None;
+    }
+    let code = cp932::jis_to_sjis(lead - 0x80, trail - 0x80)?;
+    cp932::decode_pair((code >> 8) as u8, (code & 0xFF) as u8)
+}
+
+/// Decode EUC-JP bytes to a string
+///
+/// Invalid sequences are replaced with U+FFFD.
+pub fn decode(bytes: &[u8]) -> String {
+    decode_with_errors(bytes).0
+}
+
+/// Decode EUC-JP bytes, also returning the byte offset of every invalid sequence
+pub fn decode_with_errors(bytes: &[u8]) -> (String, Vec<usize>) {
+    let mut result = String::with_capacity(bytes.len());
+    let mut errors = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let byte = bytes[i];
+        match byte {
+            0x00..=0x7F => {
+                result.push(byte as char);
+                i += 1;
+            }
+            SS2 => match bytes.get(i + 1) {
+                Some(&kana @ 0xA1..=0xDF) => {
+                    let code = 0xFF61 + (kana - 0xA1) as u32;
+                    result.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+                    i += 2;
+                }
+                _ => {
+                    result.push('\u{FFFD}');
+                    errors.push(i);
+                    i += 1;
+                }
+            },
+            SS3 => {
+                // JIS X 0212 is not supported; skip the whole sequence if it is well-formed
+                let well_formed = bytes.get(i + 1).is_some_and(|&b| is_euc_byte(b))
+                    && bytes.get(i + 2).is_some_and(|&b| is_euc_byte(b));
+                result.push('\u{FFFD}');
+                errors.push(i);
+                i += if well_formed { 3 } else { 1 };
+            }
+            _ if is_euc_byte(byte) => {
+                let decoded = bytes.get(i + 1).and_then(|&trail| decode_pair(byte, trail));
+                match decoded {
+                    Some(code) => {
+                        result.push(char::from_u32(code as u32).unwrap_or('\u{FFFD}'));
+                        i += 2;
+                    }
+                    None => {
+                        result.push('\u{FFFD}');
+                        errors.push(i);
+                        i += if bytes.get(i + 1).is_some_and(|&b| is_euc_byte(b)) {
+                            2
+                        } else {
+                            1
+                        };
+                    }
+                }
+            }
+            _ => {
+                result.push('\u{FFFD}');
+                errors.push(i);
+                i += 1;
+            }
+        }
+    }
+
+    (result, errors)
+}
+
+/// Returns true if EUC-JP can represent the character
+pub fn can_encode(ch: char) -> bool {
+    let code = ch as u32;
+    code < 0x80
+        || (0xFF61..=0xFF9F).contains(&code)
+        || cp932::encode_char_jis_rows(ch)
+            .and_then(cp932::sjis_to_jis)
+            .is_some()
+}
+
+/// Encode a string as EUC-JP
+///
+/// Characters that EUC-JP cannot represent are written as `?`.
+pub fn encode(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    for ch in text.chars() {
+        let code = ch as u32;
+        if code < 0x80 {
+            bytes.push(code as u8);
+        } else if (0xFF61..=0xFF9F).contains(&code) {
+            bytes.push(SS2);
+            bytes.push((code - 0xFF61 + 0xA1) as u8);
+        } else if let Some((row, cell)) =
+            cp932::encode_char_jis_rows(ch).and_then(cp932::sjis_to_jis)
+        {
+            bytes.push(row + 0x80);
+            bytes.push(cell + 0x80);
+        } else {
+            bytes.push(cp932::REPLACEMENT_BYTE);
+        }
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ascii_round_trip() {
+        let text = "hello\r\nworld";
+        assert_eq!(encode(text), text.as_bytes());
+        assert_eq!(decode(text.as_bytes()), text);
+    }
+
+    #[test]
+    fn test_hiragana_and_kanji() {
+        assert_eq!(encode("あ漢"), [0xA4, 0xA2, 0xB4, 0xC1]);
+        assert_eq!(decode(&[0xA4, 0xA2, 0xB4, 0xC1]), "あ漢");
+    }
+
+    #[test]
+    fn test_half_width_katakana() {
+        assert_eq!(encode("ｱ"), [0x8E, 0xB1]);
+        assert_eq!(decode(&[0x8E, 0xB1]), "ｱ");
+    }
+
+    #[test]
+    fn test_nec_extensions() {
+        // NEC special characters (row 13)
+        assert_eq!(encode("①"), [0xAD, 0xA1]);
+        assert_eq!(decode(&[0xAD, 0xA1]), "①");
+        // IBM extensions are written as NEC-selected IBM extensions (rows 89-92)
+        assert_eq!(encode("髙"), [0xFC, 0xE2]);
+        assert_eq!(decode(&[0xFC, 0xE2]), "髙");
+    }
+
+    #[test]
+    fn test_unmappable_characters() {
+        assert_eq!(encode("a😀\u{E000}"), b"a??");
+    }
+
+    #[test]
+    fn test_jis_x_0212_is_replaced() {
+        assert_eq!(decode(&[0x8F, 0xB0, 0xA1, 0x41]), "\u{FFFD}A");
+    }
+
+    #[test]
+    fn test_invalid_sequences() {
+        assert_eq!(decode(&[0xA4]), "\u{FFFD}");
+        assert_eq!(decode(&[0xA4, 0x41]), "\u{FFFD}A");
+        assert_eq!(decode(&[0x80, 0xFF]), "\u{FFFD}\u{FFFD}");
+    }
+
+    #[test]
+    fn test_error_offsets() {
+        let (text, errors) = decode_with_errors(&[0xA4, 0xA2, 0x8F, 0xB0, 0xA1, 0x80, 0x41]);
+        assert_eq!(text, "あ\u{FFFD}\u{FFFD}A");
+        assert_eq!(errors, vec![2, 5]);
+    }
+}
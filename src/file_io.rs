@@ -1,9 +1,22 @@
+use crate::cp932;
+use crate::encoding_detect::{self, Detection};
+use crate::euc_jp;
 use crate::i18n::get_string;
+use crate::iso_2022_jp;
+use crate::line_ending::{self, LineEnding};
+use crate::unicode;
+use crate::unmappable::{self, UnmappableChar};
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
-use windows::Win32::Globalization::{
-    GetACP, MULTI_BYTE_TO_WIDE_CHAR_FLAGS, MultiByteToWideChar, WideCharToMultiByte,
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use windows::Win32::Foundation::{
+    ERROR_ACCESS_DENIED, ERROR_DISK_FULL, ERROR_FILE_NOT_FOUND, ERROR_FILENAME_EXCED_RANGE,
+    ERROR_HANDLE_DISK_FULL, ERROR_LOCK_VIOLATION, ERROR_PATH_NOT_FOUND, ERROR_SHARING_VIOLATION,
+    ERROR_UNABLE_TO_MOVE_REPLACEMENT, ERROR_UNABLE_TO_MOVE_REPLACEMENT_2, WIN32_ERROR,
 };
+use windows::Win32::Storage::FileSystem::{REPLACEFILE_WRITE_THROUGH, ReplaceFileW};
 use windows::Win32::System::Com::*;
 use windows::Win32::UI::Shell::Common::*;
 use windows::Win32::UI::Shell::*;
@@ -15,13 +28,110 @@ pub enum FileEncoding {
     Utf8Bom,
     Utf16Le,
     Utf16Be,
+    Utf32Le,
+    Utf32Be,
     ShiftJis,
+    EucJp,
+    Iso2022Jp,
     Auto,
 }
 
+/// Why a file could not be read or written
+#[derive(Debug)]
+pub enum FileIoError {
+    NotFound,
+    PermissionDenied,
+    /// The file is open in another process
+    SharingViolation,
+    /// The bytes are not valid in the requested encoding
+    InvalidData,
+    DiskFull,
+    PathTooLong,
+    /// The text has characters the target encoding cannot represent (nothing was written)
+    Unmappable(Vec<UnmappableChar>),
+    /// The original was removed but the new file could not be renamed into its place, so
+    /// the saved text is only in this temporary file
+    KeptInTemporaryFile(PathBuf),
+    /// Anything else, with the system's description
+    Other(String),
+}
+
+impl FileIoError {
+    /// i18n key of the message shown to the user
+    pub fn message_key(&self) -> &'static str {
+        match self {
+            FileIoError::NotFound => "ERROR_NOT_FOUND",
+            FileIoError::PermissionDenied => "ERROR_PERMISSION_DENIED",
+            FileIoError::SharingViolation => "ERROR_SHARING_VIOLATION",
+            FileIoError::InvalidData => "ERROR_INVALID_DATA",
+            FileIoError::DiskFull => "ERROR_DISK_FULL",
+            FileIoError::PathTooLong => "ERROR_PATH_TOO_LONG",
+            FileIoError::Unmappable(_) => "ERROR_UNMAPPABLE",
+            FileIoError::KeptInTemporaryFile(_) => "ERROR_KEPT_IN_TEMPORARY_FILE",
+            FileIoError::Other(_) => "ERROR_UNKNOWN",
+        }
+    }
+
+    fn from_win32(code: WIN32_ERROR) -> Option<Self> {
+        match code {
+            ERROR_FILE_NOT_FOUND | ERROR_PATH_NOT_FOUND => Some(FileIoError::NotFound),
+            ERROR_ACCESS_DENIED => Some(FileIoError::PermissionDenied),
+            ERROR_SHARING_VIOLATION | ERROR_LOCK_VIOLATION => Some(FileIoError::SharingViolation),
+            ERROR_DISK_FULL | ERROR_HANDLE_DISK_FULL => Some(FileIoError::DiskFull),
+            ERROR_FILENAME_EXCED_RANGE => Some(FileIoError::PathTooLong),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for FileIoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = get_string(self.message_key());
+        match self {
+            FileIoError::Other(detail) => write!(f, "{}", message.replace("{message}", detail)),
+            FileIoError::KeptInTemporaryFile(path) => write!(
+                f,
+                "{}",
+                message.replace("{file}", &path.display().to_string())
+            ),
+            _ => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for FileIoError {}
+
+impl From<std::io::Error> for FileIoError {
+    fn from(error: std::io::Error) -> Self {
+        // Prefer the raw Windows code: ErrorKind has no sharing violation
+        let win32 = error
+            .raw_os_error()
+            .and_then(|code| FileIoError::from_win32(WIN32_ERROR(code as u32)));
+        if let Some(mapped) = win32 {
+            return mapped;
+        }
+        match error.kind() {
+            std::io::ErrorKind::NotFound => FileIoError::NotFound,
+            std::io::ErrorKind::PermissionDenied => FileIoError::PermissionDenied,
+            std::io::ErrorKind::InvalidData => FileIoError::InvalidData,
+            std::io::ErrorKind::StorageFull => FileIoError::DiskFull,
+            _ => FileIoError::Other(error.to_string()),
+        }
+    }
+}
+
+impl From<windows::core::Error> for FileIoError {
+    fn from(error: windows::core::Error) -> Self {
+        WIN32_ERROR::from_error(&error)
+            .and_then(FileIoError::from_win32)
+            .unwrap_or_else(|| FileIoError::Other(error.message()))
+    }
+}
+
 const ENCODING_CONTROL_ID: u32 = 2000;
 
-pub fn open_file_dialog() -> Option<(PathBuf, FileEncoding)> {
+/// Show the Open dialog, optionally with a file already filled in
+pub fn open_file_dialog(current_file: Option<&PathBuf>) -> Option<(PathBuf, FileEncoding)> {
     unsafe {
         // Initialize COM
         let _ = CoInitializeEx(None, COINIT_APARTMENTTHREADED | COINIT_DISABLE_OLE1DDE);
@@ -64,6 +174,11 @@ pub fn open_file_dialog() -> Option<(PathBuf, FileEncoding)> {
         let _ = dialog.SetFileTypes(&file_types);
         let _ = dialog.SetFileTypeIndex(1);
 
+        // Fill in the full path so the same file can be opened again with another encoding
+        if let Some(path) = current_file {
+            let _ = dialog.SetFileName(&HSTRING::from(path.as_os_str()));
+        }
+
         // Get IFileDialogCustomize interface to add custom controls
         let customize: IFileDialogCustomize = match dialog.cast() {
             Ok(c) => c,
@@ -93,21 +208,26 @@ pub fn open_file_dialog() -> Option<(PathBuf, FileEncoding)> {
                 let auto_hstring = HSTRING::from(&auto_text);
                 let auto_label = PCWSTR(auto_hstring.as_ptr());
 
-                let ansi_text = get_string("ENCODING_ANSI");
-                let ansi_hstring = HSTRING::from(&ansi_text);
-                let ansi_label = PCWSTR(ansi_hstring.as_ptr());
-
+                let shift_jis_label = w!("Shift-JIS");
+                let euc_jp_label = w!("EUC-JP");
+                let iso_2022_jp_label = w!("ISO-2022-JP");
                 let utf16le_label = w!("UTF-16 LE");
                 let utf16be_label = w!("UTF-16 BE");
+                let utf32le_label = w!("UTF-32 LE");
+                let utf32be_label = w!("UTF-32 BE");
                 let utf8_label = w!("UTF-8");
                 let utf8bom_label = w!("UTF-8 (BOM)");
 
                 let _ = customize.AddControlItem(combo_id, 0, auto_label);
-                let _ = customize.AddControlItem(combo_id, 1, ansi_label);
-                let _ = customize.AddControlItem(combo_id, 2, utf16le_label);
-                let _ = customize.AddControlItem(combo_id, 3, utf16be_label);
-                let _ = customize.AddControlItem(combo_id, 4, utf8_label);
-                let _ = customize.AddControlItem(combo_id, 5, utf8bom_label);
+                let _ = customize.AddControlItem(combo_id, 1, shift_jis_label);
+                let _ = customize.AddControlItem(combo_id, 2, euc_jp_label);
+                let _ = customize.AddControlItem(combo_id, 3, iso_2022_jp_label);
+                let _ = customize.AddControlItem(combo_id, 4, utf16le_label);
+                let _ = customize.AddControlItem(combo_id, 5, utf16be_label);
+                let _ = customize.AddControlItem(combo_id, 6, utf32le_label);
+                let _ = customize.AddControlItem(combo_id, 7, utf32be_label);
+                let _ = customize.AddControlItem(combo_id, 8, utf8_label);
+                let _ = customize.AddControlItem(combo_id, 9, utf8bom_label);
                 let _ = customize.SetSelectedControlItem(combo_id, 0); // Default to Auto
             }
 
@@ -124,10 +244,14 @@ pub fn open_file_dialog() -> Option<(PathBuf, FileEncoding)> {
             if let Ok(selected) = customize.GetSelectedControlItem(combo_id) {
                 encoding = match selected {
                     1 => FileEncoding::ShiftJis,
-                    2 => FileEncoding::Utf16Le,
-                    3 => FileEncoding::Utf16Be,
-                    4 => FileEncoding::Utf8,
-                    5 => FileEncoding::Utf8Bom,
+                    2 => FileEncoding::EucJp,
+                    3 => FileEncoding::Iso2022Jp,
+                    4 => FileEncoding::Utf16Le,
+                    5 => FileEncoding::Utf16Be,
+                    6 => FileEncoding::Utf32Le,
+                    7 => FileEncoding::Utf32Be,
+                    8 => FileEncoding::Utf8,
+                    9 => FileEncoding::Utf8Bom,
                     _ => FileEncoding::Auto,
                 };
             }
@@ -229,28 +353,37 @@ pub fn save_file_dialog(
         {
             // Add combo box
             if customize.AddComboBox(combo_id).is_ok() {
-                let ansi_text = get_string("ENCODING_ANSI");
-                let ansi_hstring = HSTRING::from(&ansi_text);
-                let ansi_label = PCWSTR(ansi_hstring.as_ptr());
-
+                let shift_jis_label = w!("Shift-JIS");
+                let euc_jp_label = w!("EUC-JP");
+                let iso_2022_jp_label = w!("ISO-2022-JP");
                 let utf16le_label = w!("UTF-16 LE");
                 let utf16be_label = w!("UTF-16 BE");
+                let utf32le_label = w!("UTF-32 LE");
+                let utf32be_label = w!("UTF-32 BE");
                 let utf8_label = w!("UTF-8");
                 let utf8bom_label = w!("UTF-8 (BOM)");
 
-                let _ = customize.AddControlItem(combo_id, 0, ansi_label);
-                let _ = customize.AddControlItem(combo_id, 1, utf16le_label);
-                let _ = customize.AddControlItem(combo_id, 2, utf16be_label);
-                let _ = customize.AddControlItem(combo_id, 3, utf8_label);
-                let _ = customize.AddControlItem(combo_id, 4, utf8bom_label);
+                let _ = customize.AddControlItem(combo_id, 0, shift_jis_label);
+                let _ = customize.AddControlItem(combo_id, 1, euc_jp_label);
+                let _ = customize.AddControlItem(combo_id, 2, iso_2022_jp_label);
+                let _ = customize.AddControlItem(combo_id, 3, utf16le_label);
+                let _ = customize.AddControlItem(combo_id, 4, utf16be_label);
+                let _ = customize.AddControlItem(combo_id, 5, utf32le_label);
+                let _ = customize.AddControlItem(combo_id, 6, utf32be_label);
+                let _ = customize.AddControlItem(combo_id, 7, utf8_label);
+                let _ = customize.AddControlItem(combo_id, 8, utf8bom_label);
 
                 // Set default based on the provided encoding
                 let default_index = match default_encoding {
                     FileEncoding::ShiftJis => 0,
-                    FileEncoding::Utf16Le => 1,
-                    FileEncoding::Utf16Be => 2,
-                    FileEncoding::Utf8 | FileEncoding::Auto => 3,
-                    FileEncoding::Utf8Bom => 4,
+                    FileEncoding::EucJp => 1,
+                    FileEncoding::Iso2022Jp => 2,
+                    FileEncoding::Utf16Le => 3,
+                    FileEncoding::Utf16Be => 4,
+                    FileEncoding::Utf32Le => 5,
+                    FileEncoding::Utf32Be => 6,
+                    FileEncoding::Utf8 | FileEncoding::Auto => 7,
+                    FileEncoding::Utf8Bom => 8,
                 };
                 let _ = customize.SetSelectedControlItem(combo_id, default_index);
             }
@@ -269,9 +402,13 @@ pub fn save_file_dialog(
             if let Ok(selected) = customize.GetSelectedControlItem(combo_id) {
                 encoding = match selected {
                     0 => FileEncoding::ShiftJis,
-                    1 => FileEncoding::Utf16Le,
-                    2 => FileEncoding::Utf16Be,
-                    4 => FileEncoding::Utf8Bom,
+                    1 => FileEncoding::EucJp,
+                    2 => FileEncoding::Iso2022Jp,
+                    3 => FileEncoding::Utf16Le,
+                    4 => FileEncoding::Utf16Be,
+                    5 => FileEncoding::Utf32Le,
+                    6 => FileEncoding::Utf32Be,
+                    8 => FileEncoding::Utf8Bom,
                     _ => FileEncoding::Utf8,
                 };
             }
@@ -292,19 +429,142 @@ pub fn save_file_dialog(
     }
 }
 
+// Keep the previous version as "name.ext.bak" when saving over a file
+static BACKUP_ON_SAVE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_backup_on_save(enabled: bool) {
+    BACKUP_ON_SAVE.store(enabled, Ordering::SeqCst);
+}
+
+pub fn backup_on_save() -> bool {
+    BACKUP_ON_SAVE.load(Ordering::SeqCst)
+}
+
+// Append a suffix to the full file name ("a.txt" -> "a.txt.bak")
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+// Create a temporary file next to the target. A stale file left by an earlier run
+// (the process id may have been reused) is skipped rather than failing the save.
+fn create_temp_file(path: &Path) -> std::result::Result<(PathBuf, fs::File), FileIoError> {
+    let pid = std::process::id();
+    let mut attempt = 0;
+    loop {
+        let temp_path = sibling_path(path, &format!(".{}.{}.tmp", pid, attempt));
+        match fs::File::create_new(&temp_path) {
+            Ok(file) => return Ok((temp_path, file)),
+            Err(error) if error.kind() == std::io::ErrorKind::AlreadyExists && attempt < 100 => {
+                attempt += 1;
+            }
+            Err(error) => return Err(error.into()),
+        }
+    }
+}
+
+// Swap the temporary file into place with ReplaceFileW, which keeps the original's
+// attributes, ACL and creation time and can move the original to `backup_path`
+fn replace_file(
+    path: &Path,
+    temp_path: &Path,
+    backup_path: Option<&Path>,
+) -> std::result::Result<(), FileIoError> {
+    let backup = backup_path.map(|backup_path| HSTRING::from(backup_path.as_os_str()));
+    let result = unsafe {
+        ReplaceFileW(
+            &HSTRING::from(path.as_os_str()),
+            &HSTRING::from(temp_path.as_os_str()),
+            match &backup {
+                Some(backup) => PCWSTR(backup.as_ptr()),
+                None => PCWSTR::null(),
+            },
+            REPLACEFILE_WRITE_THROUGH,
+            None,
+            None,
+        )
+    };
+    let Err(error) = result else {
+        return Ok(());
+    };
+
+    // The original is already gone from the target path but the new file could not take
+    // its place: error 2 moves the original to the backup name, and error 1 without a
+    // backup name deletes it. Move the new file in, or failing that put the original back,
+    // so the target path is never left empty without saying where the new text is.
+    let code = WIN32_ERROR::from_error(&error);
+    let original_gone = code == Some(ERROR_UNABLE_TO_MOVE_REPLACEMENT_2)
+        || (code == Some(ERROR_UNABLE_TO_MOVE_REPLACEMENT) && backup_path.is_none());
+    if original_gone {
+        if fs::rename(temp_path, path).is_ok() {
+            return Ok(());
+        }
+        let restored = backup_path.is_some_and(|backup_path| fs::rename(backup_path, path).is_ok());
+        if !restored {
+            return Err(FileIoError::KeptInTemporaryFile(temp_path.to_path_buf()));
+        }
+    }
+    Err(error.into())
+}
+
+// Write the bytes to a temporary file next to the target, then swap it into place.
+// If anything fails, the original is left at the target path and the temporary file
+// is removed, unless it holds the only copy of the contents.
+fn write_atomically(path: &Path, bytes: &[u8]) -> std::result::Result<(), FileIoError> {
+    let (temp_path, mut file) = create_temp_file(path)?;
+    let had_original = path.exists();
+
+    let result = (|| -> std::result::Result<(), FileIoError> {
+        file.write_all(bytes)?;
+        file.sync_all()?;
+        drop(file);
+
+        if had_original {
+            let backup_path = sibling_path(path, ".bak");
+            replace_file(
+                path,
+                &temp_path,
+                backup_on_save().then_some(backup_path.as_path()),
+            )
+        } else {
+            fs::rename(&temp_path, path)?;
+            Ok(())
+        }
+    })();
+
+    if result.is_err() && (path.exists() || !had_original) {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Save the text in the given encoding and line ending style
+///
+/// Unless `allow_unmappable` is set, text with characters the encoding cannot represent
+/// is not written and `FileIoError::Unmappable` lists them instead.
 pub fn save_file(
-    path: &PathBuf,
+    path: &Path,
     content: &str,
     encoding: FileEncoding,
-) -> std::result::Result<(), Box<dyn std::error::Error>> {
-    match encoding {
-        FileEncoding::Utf8 | FileEncoding::Auto => {
-            fs::write(path, content)?;
+    line_ending: LineEnding,
+    allow_unmappable: bool,
+) -> std::result::Result<(), FileIoError> {
+    if !allow_unmappable {
+        let unmappable = unmappable::find(content, encoding);
+        if !unmappable.is_empty() {
+            return Err(FileIoError::Unmappable(unmappable));
         }
+    }
+
+    let converted = line_ending::convert(content, line_ending);
+    let content = converted.as_str();
+    let bytes = match encoding {
+        FileEncoding::Utf8 | FileEncoding::Auto => content.as_bytes().to_vec(),
         FileEncoding::Utf8Bom => {
             let mut bytes = vec![0xEF, 0xBB, 0xBF];
             bytes.extend_from_slice(content.as_bytes());
-            fs::write(path, &bytes)?;
+            bytes
         }
         FileEncoding::Utf16Le => {
             let mut bytes = vec![0xFF, 0xFE];
@@ -313,7 +573,7 @@ pub fn save_file(
                 bytes.push((word & 0xFF) as u8);
                 bytes.push((word >> 8) as u8);
             }
-            fs::write(path, &bytes)?;
+            bytes
         }
         FileEncoding::Utf16Be => {
             let mut bytes = vec![0xFE, 0xFF];
@@ -322,148 +582,95 @@ pub fn save_file(
                 bytes.push((word >> 8) as u8);
                 bytes.push((word & 0xFF) as u8);
             }
-            fs::write(path, &bytes)?;
+            bytes
         }
-        FileEncoding::ShiftJis => unsafe {
-            let utf16: Vec<u16> = content.encode_utf16().collect();
-            let code_page = GetACP();
-
-            let size = WideCharToMultiByte(code_page, 0, &utf16, None, None, None);
-
-            if size > 0 {
-                let mut buffer = vec![0u8; size as usize];
-                WideCharToMultiByte(code_page, 0, &utf16, Some(&mut buffer), None, None);
-                fs::write(path, &buffer)?;
+        FileEncoding::Utf32Le => {
+            let mut bytes = vec![0xFF, 0xFE, 0x00, 0x00];
+            for ch in content.chars() {
+                bytes.extend_from_slice(&(ch as u32).to_le_bytes());
             }
-        },
-    }
-    Ok(())
-}
-
-pub fn load_file(
-    path: &PathBuf,
-    encoding: FileEncoding,
-) -> std::result::Result<(String, FileEncoding), Box<dyn std::error::Error>> {
-    match encoding {
-        FileEncoding::Utf8 => {
-            let content = fs::read_to_string(path)?;
-            Ok((content, FileEncoding::Utf8))
-        }
-        FileEncoding::Utf8Bom => {
-            let bytes = fs::read(path)?;
-            let content = if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
-                String::from_utf8(bytes[3..].to_vec())?
-            } else {
-                String::from_utf8(bytes)?
-            };
-            Ok((content, FileEncoding::Utf8Bom))
+            bytes
         }
-        FileEncoding::Utf16Le => {
-            let bytes = fs::read(path)?;
-            let start = if bytes.starts_with(&[0xFF, 0xFE]) {
-                2
-            } else {
-                0
-            };
-            let utf16_data: Vec<u16> = bytes[start..]
-                .chunks_exact(2)
-                .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
-                .collect();
-            let content = String::from_utf16_lossy(&utf16_data);
-            Ok((content, FileEncoding::Utf16Le))
-        }
-        FileEncoding::Utf16Be => {
-            let bytes = fs::read(path)?;
-            let start = if bytes.starts_with(&[0xFE, 0xFF]) {
-                2
-            } else {
-                0
-            };
-            let utf16_data: Vec<u16> = bytes[start..]
-                .chunks_exact(2)
-                .map(|chunk| u16::from_be_bytes([chunk[0], chunk[1]]))
-                .collect();
-            let content = String::from_utf16_lossy(&utf16_data);
-            Ok((content, FileEncoding::Utf16Be))
-        }
-        FileEncoding::ShiftJis => {
-            let bytes = fs::read(path)?;
-            unsafe {
-                let code_page = GetACP();
-
-                let size =
-                    MultiByteToWideChar(code_page, MULTI_BYTE_TO_WIDE_CHAR_FLAGS(0), &bytes, None);
-
-                if size > 0 {
-                    let mut buffer = vec![0u16; size as usize];
-                    MultiByteToWideChar(
-                        code_page,
-                        MULTI_BYTE_TO_WIDE_CHAR_FLAGS(0),
-                        &bytes,
-                        Some(&mut buffer),
-                    );
-                    let content = String::from_utf16_lossy(&buffer);
-                    Ok((content, FileEncoding::ShiftJis))
-                } else {
-                    Ok((String::new(), FileEncoding::ShiftJis))
-                }
+        FileEncoding::Utf32Be => {
+            let mut bytes = vec![0x00, 0x00, 0xFE, 0xFF];
+            for ch in content.chars() {
+                bytes.extend_from_slice(&(ch as u32).to_be_bytes());
             }
+            bytes
         }
-        FileEncoding::Auto => {
-            let bytes = fs::read(path)?;
-
-            // Check for UTF-16 LE BOM
-            if bytes.starts_with(&[0xFF, 0xFE]) {
-                let utf16_data: Vec<u16> = bytes[2..]
-                    .chunks_exact(2)
-                    .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
-                    .collect();
-                let content = String::from_utf16_lossy(&utf16_data);
-                return Ok((content, FileEncoding::Utf16Le));
-            }
+        FileEncoding::ShiftJis => cp932::encode(content),
+        FileEncoding::EucJp => euc_jp::encode(content),
+        FileEncoding::Iso2022Jp => iso_2022_jp::encode(content),
+    };
+    write_atomically(path, &bytes)
+}
 
-            // Check for UTF-16 BE BOM
-            if bytes.starts_with(&[0xFE, 0xFF]) {
-                let utf16_data: Vec<u16> = bytes[2..]
-                    .chunks_exact(2)
-                    .map(|chunk| u16::from_be_bytes([chunk[0], chunk[1]]))
-                    .collect();
-                let content = String::from_utf16_lossy(&utf16_data);
-                return Ok((content, FileEncoding::Utf16Be));
-            }
+/// Contents of a file read by `load_file`
+pub struct LoadedFile {
+    pub content: String,
+    pub encoding: FileEncoding,
+    pub line_ending: LineEnding,
+    /// Detector result, present only when the file was opened with `FileEncoding::Auto`
+    pub detection: Option<Detection>,
+    /// Byte offsets of sequences that were invalid in `encoding` and replaced with U+FFFD
+    pub decode_errors: Vec<usize>,
+}
 
-            // Check for UTF-8 BOM
-            if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
-                let content = String::from_utf8(bytes[3..].to_vec())?;
-                return Ok((content, FileEncoding::Utf8Bom));
-            }
+// Decode file contents in a known encoding, returning the text and the byte offsets
+// of invalid sequences (counted from the start of the file, including any BOM)
+fn decode_bytes(bytes: &[u8], encoding: FileEncoding) -> (String, Vec<usize>) {
+    let bom: &[u8] = match encoding {
+        FileEncoding::Utf8Bom => &[0xEF, 0xBB, 0xBF],
+        FileEncoding::Utf16Le => &[0xFF, 0xFE],
+        FileEncoding::Utf16Be => &[0xFE, 0xFF],
+        FileEncoding::Utf32Le => &[0xFF, 0xFE, 0x00, 0x00],
+        FileEncoding::Utf32Be => &[0x00, 0x00, 0xFE, 0xFF],
+        _ => &[],
+    };
+    let (bom_len, bytes) = match bytes.strip_prefix(bom) {
+        Some(rest) => (bom.len(), rest),
+        None => (0, bytes),
+    };
+
+    let (content, errors) = match encoding {
+        FileEncoding::Utf8 | FileEncoding::Utf8Bom | FileEncoding::Auto => {
+            unicode::decode_utf8(bytes)
+        }
+        FileEncoding::Utf16Le => unicode::decode_utf16(bytes, false),
+        FileEncoding::Utf16Be => unicode::decode_utf16(bytes, true),
+        FileEncoding::Utf32Le => unicode::decode_utf32(bytes, false),
+        FileEncoding::Utf32Be => unicode::decode_utf32(bytes, true),
+        FileEncoding::ShiftJis => cp932::decode_with_errors(bytes),
+        FileEncoding::EucJp => euc_jp::decode_with_errors(bytes),
+        FileEncoding::Iso2022Jp => iso_2022_jp::decode_with_errors(bytes),
+    };
+    let errors = errors.into_iter().map(|offset| offset + bom_len).collect();
+    (content, errors)
+}
 
-            // Try UTF-8 first
-            if let Ok(content) = String::from_utf8(bytes.clone()) {
-                return Ok((content, FileEncoding::Utf8));
-            }
+/// Read a file and decode it
+///
+/// Invalid sequences do not fail the load; they are replaced with U+FFFD and listed
+/// in `LoadedFile::decode_errors` so the caller can warn about them.
+pub fn load_file(
+    path: &PathBuf,
+    encoding: FileEncoding,
+) -> std::result::Result<LoadedFile, FileIoError> {
+    let bytes = fs::read(path)?;
 
-            // Fall back to ANSI (system default code page)
-            unsafe {
-                let code_page = GetACP();
-
-                let size =
-                    MultiByteToWideChar(code_page, MULTI_BYTE_TO_WIDE_CHAR_FLAGS(0), &bytes, None);
-
-                if size > 0 {
-                    let mut buffer = vec![0u16; size as usize];
-                    MultiByteToWideChar(
-                        code_page,
-                        MULTI_BYTE_TO_WIDE_CHAR_FLAGS(0),
-                        &bytes,
-                        Some(&mut buffer),
-                    );
-                    let content = String::from_utf16_lossy(&buffer);
-                    Ok((content, FileEncoding::ShiftJis))
-                } else {
-                    Ok((String::new(), FileEncoding::ShiftJis))
-                }
-            }
-        }
-    }
+    let detection = if encoding == FileEncoding::Auto {
+        Some(encoding_detect::detect(&bytes))
+    } else {
+        None
+    };
+    let encoding = detection.map_or(encoding, |detection| detection.encoding);
+
+    let (content, decode_errors) = decode_bytes(&bytes, encoding);
+    Ok(LoadedFile {
+        line_ending: line_ending::detect(&content),
+        content,
+        encoding,
+        detection,
+        decode_errors,
+    })
 }
@@ -0,0 +1,962 @@
+// Modeless Find and Replace dialog
+//
+// The dialog is a plain window whose keyboard navigation comes from IsDialogMessageW in the
+// main message loop. Find and Replace share the window; Replace mode shows the extra
+// controls. Closing it only hides it, so the query and options stay available to
+// Find Next (F3) and Find Previous (Shift+F3).
+
+use crate::i18n::get_string;
+use crate::search::{self, Query, SearchOptions};
+use crate::theme::{active_theme, should_use_dark_mode};
+use std::sync::Mutex;
+use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, LRESULT, POINT, RECT, WPARAM};
+use windows::Win32::Graphics::Dwm::{DWMWA_USE_IMMERSIVE_DARK_MODE, DwmSetWindowAttribute};
+use windows::Win32::Graphics::Gdi::{
+    BACKGROUND_MODE, CreateFontW, CreateSolidBrush, DRAW_TEXT_FORMAT, DeleteObject, DrawFocusRect,
+    DrawTextW, FONT_CHARSET, FONT_CLIP_PRECISION, FONT_OUTPUT_PRECISION, FONT_QUALITY, FillRect,
+    FrameRect, HBRUSH, HDC, HFONT, InvalidateRect, REDRAW_WINDOW_FLAGS, RedrawWindow, SetBkColor,
+    SetBkMode, SetTextColor,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::Controls::{
+    BST_CHECKED, BST_UNCHECKED, CheckDlgButton, DRAWITEMSTRUCT, IsDlgButtonChecked,
+};
+use windows::Win32::UI::Input::KeyboardAndMouse::{EnableWindow, SetFocus};
+use windows::Win32::UI::WindowsAndMessaging::{
+    AdjustWindowRectEx, CreateWindowExW, DefWindowProcW, GW_OWNER, GetClientRect, GetDlgItem,
+    GetWindow, GetWindowLongPtrW, GetWindowRect, GetWindowTextLengthW, GetWindowTextW, HMENU,
+    IDC_ARROW, IsWindowVisible, LoadCursorW, MB_ICONINFORMATION, MessageBoxW, RegisterClassW,
+    SET_WINDOW_POS_FLAGS, SW_HIDE, SW_SHOW, SendMessageW, SetForegroundWindow, SetWindowPos,
+    SetWindowTextW, ShowWindow, WINDOW_EX_STYLE, WINDOW_LONG_PTR_INDEX, WINDOW_STYLE, WM_CLOSE,
+    WM_COMMAND, WM_CREATE, WM_DESTROY, WM_DRAWITEM, WM_SETFONT, WNDCLASS_STYLES, WNDCLASSW,
+};
+use windows::core::PCWSTR;
+
+// Control IDs. IsDialogMessageW sends IDOK for Enter and IDCANCEL for Esc.
+const IDOK: i32 = 1;
+const IDCANCEL: i32 = 2;
+const ID_FIND_TEXT: i32 = 1001;
+const ID_FIND_PREV: i32 = 1002;
+const ID_MATCH_CASE: i32 = 1003;
+const ID_WHOLE_WORD: i32 = 1004;
+const ID_WRAP_AROUND: i32 = 1005;
+const ID_FIND_LABEL: i32 = 1006;
+const ID_REPLACE_LABEL: i32 = 1007;
+const ID_REPLACE_TEXT: i32 = 1008;
+const ID_REPLACE: i32 = 1009;
+const ID_REPLACE_ALL: i32 = 1010;
+/// Result and error line; the Go To dialog uses the same ID so `set_message` fits both
+pub const ID_MESSAGE: i32 = 1011;
+const ID_REGEX: i32 = 1012;
+
+// Control positions as [x, y, width, height] in Find mode and in Replace mode.
+// A control without a position in the current mode is hidden.
+type ControlLayout = (i32, Option<[i32; 4]>, Option<[i32; 4]>);
+const LAYOUT: [ControlLayout; 14] = [
+    (
+        ID_FIND_LABEL,
+        Some([12, 15, 92, 20]),
+        Some([12, 15, 92, 20]),
+    ),
+    (
+        ID_FIND_TEXT,
+        Some([106, 12, 200, 23]),
+        Some([106, 12, 200, 23]),
+    ),
+    (ID_REPLACE_LABEL, None, Some([12, 47, 92, 20])),
+    (ID_REPLACE_TEXT, None, Some([106, 44, 200, 23])),
+    (
+        ID_MATCH_CASE,
+        Some([12, 48, 294, 20]),
+        Some([12, 80, 294, 20]),
+    ),
+    (
+        ID_WHOLE_WORD,
+        Some([12, 72, 294, 20]),
+        Some([12, 104, 294, 20]),
+    ),
+    (
+        ID_WRAP_AROUND,
+        Some([12, 96, 294, 20]),
+        Some([12, 128, 294, 20]),
+    ),
+    (ID_REGEX, Some([12, 120, 294, 20]), Some([12, 152, 294, 20])),
+    (IDOK, Some([318, 12, 90, 26]), Some([318, 12, 90, 26])),
+    (
+        ID_FIND_PREV,
+        Some([318, 44, 90, 26]),
+        Some([318, 44, 90, 26]),
+    ),
+    (ID_REPLACE, None, Some([318, 76, 90, 26])),
+    (ID_REPLACE_ALL, None, Some([318, 108, 90, 26])),
+    (IDCANCEL, Some([318, 76, 90, 26]), Some([318, 140, 90, 26])),
+    (
+        ID_MESSAGE,
+        Some([12, 148, 396, 20]),
+        Some([12, 180, 396, 20]),
+    ),
+];
+
+// Client area size of the dialog in each mode
+const DIALOG_WIDTH: i32 = 420;
+const FIND_HEIGHT: i32 = 176;
+const REPLACE_HEIGHT: i32 = 208;
+
+struct FindState {
+    query: Vec<u16>,
+    replacement: Vec<u16>,
+    options: SearchOptions,
+}
+
+static FIND_DIALOG: Mutex<Option<isize>> = Mutex::new(None);
+static FIND_STATE: Mutex<FindState> = Mutex::new(FindState {
+    query: Vec::new(),
+    replacement: Vec::new(),
+    options: SearchOptions {
+        match_case: false,
+        whole_word: false,
+        wrap_around: true,
+        regex: false,
+    },
+});
+static DIALOG_FONT: Mutex<Option<isize>> = Mutex::new(None);
+// Background brushes handed out from WM_CTLCOLOR*, one per color
+static BRUSHES: Mutex<Vec<(u32, isize)>> = Mutex::new(Vec::new());
+
+/// The Find dialog, if it is currently shown
+pub fn dialog_hwnd() -> Option<HWND> {
+    let hwnd = HWND(FIND_DIALOG.lock().ok().and_then(|dialog| *dialog)? as _);
+    unsafe { IsWindowVisible(hwnd).as_bool().then_some(hwnd) }
+}
+
+/// Show the Find dialog, or the Replace dialog when `replace` is true, for the main window.
+/// A single-line selection in the editor becomes the search text.
+pub fn show_find_dialog(owner: HWND, replace: bool) {
+    let existing = FIND_DIALOG.lock().ok().and_then(|dialog| *dialog);
+    let hwnd = match existing {
+        Some(hwnd) => HWND(hwnd as _),
+        None => {
+            let Some(hwnd) = create_dialog(owner) else {
+                return;
+            };
+            if let Ok(mut dialog) = FIND_DIALOG.lock() {
+                *dialog = Some(hwnd.0 as isize);
+            }
+            hwnd
+        }
+    };
+
+    layout(hwnd, replace);
+    set_message(hwnd, "");
+    update_buttons(hwnd);
+
+    unsafe {
+        let edit_hwnd = editor_hwnd(owner);
+        let selection = selected_text(edit_hwnd);
+        if let Ok(text_hwnd) = GetDlgItem(Some(hwnd), ID_FIND_TEXT) {
+            if !selection.is_empty() && !selection.contains(&CR) {
+                let text: Vec<u16> = selection.iter().copied().chain(Some(0)).collect();
+                let _ = SetWindowTextW(text_hwnd, PCWSTR(text.as_ptr()));
+            }
+            const EM_SETSEL: u32 = 0x00B1;
+            SendMessageW(text_hwnd, EM_SETSEL, Some(WPARAM(0)), Some(LPARAM(-1)));
+            let _ = SetFocus(Some(text_hwnd));
+        }
+
+        let _ = ShowWindow(hwnd, SW_SHOW);
+        let _ = SetForegroundWindow(hwnd);
+    }
+}
+
+/// Select the next (or previous) match of the last search in the editor.
+/// Opens the dialog when nothing has been searched for yet.
+pub fn find_next(owner: HWND, forward: bool) {
+    let Some((query_text, options)) = FIND_STATE
+        .lock()
+        .ok()
+        .map(|state| (state.query.clone(), state.options))
+    else {
+        return;
+    };
+    if query_text.is_empty() {
+        show_find_dialog(owner, false);
+        return;
+    }
+    // The dialog only stores patterns that compile
+    let Ok(query) = Query::new(&query_text, options) else {
+        return;
+    };
+
+    unsafe {
+        let edit_hwnd = editor_hwnd(owner);
+        let text = search::collapse_crlf(&editor_text(edit_hwnd));
+        let (start, end) = selection(edit_hwnd);
+        let from = if forward { end } else { start };
+
+        match search::find(&text, &query, from, forward) {
+            Some((match_start, match_end)) => {
+                const EM_SETSEL: u32 = 0x00B1;
+                const EM_SCROLLCARET: u32 = 0x00B7;
+                SendMessageW(
+                    edit_hwnd,
+                    EM_SETSEL,
+                    Some(WPARAM(match_start)),
+                    Some(LPARAM(match_end as isize)),
+                );
+                SendMessageW(edit_hwnd, EM_SCROLLCARET, Some(WPARAM(0)), Some(LPARAM(0)));
+            }
+            None => {
+                let message = format!(
+                    "{}\0",
+                    get_string("FIND_NOT_FOUND")
+                        .replace("{text}", &String::from_utf16_lossy(&query_text))
+                );
+                let message_utf16: Vec<u16> = message.encode_utf16().collect();
+                let title = format!("{}\0", get_string("WINDOW_TITLE"));
+                let title_utf16: Vec<u16> = title.encode_utf16().collect();
+                let _ = MessageBoxW(
+                    Some(dialog_hwnd().unwrap_or(owner)),
+                    PCWSTR(message_utf16.as_ptr()),
+                    PCWSTR(title_utf16.as_ptr()),
+                    MB_ICONINFORMATION,
+                );
+            }
+        }
+    }
+}
+
+/// Repaint the dialog after the theme changes
+pub fn apply_theme() {
+    let Some(hwnd) = FIND_DIALOG.lock().ok().and_then(|dialog| *dialog) else {
+        return;
+    };
+    let hwnd = HWND(hwnd as _);
+    unsafe {
+        set_title_bar_theme(hwnd);
+        const RDW_INVALIDATE: u32 = 0x0001;
+        const RDW_ERASE: u32 = 0x0004;
+        const RDW_ALLCHILDREN: u32 = 0x0080;
+        let _ = RedrawWindow(
+            Some(hwnd),
+            None,
+            None,
+            REDRAW_WINDOW_FLAGS(RDW_INVALIDATE | RDW_ERASE | RDW_ALLCHILDREN),
+        );
+    }
+}
+
+// Register the Find dialog window class
+pub unsafe fn register_find_dialog_class() {
+    unsafe {
+        let hinstance = GetModuleHandleW(None).unwrap_or_default();
+        let class_name = "FindDialogClass\0".encode_utf16().collect::<Vec<_>>();
+        let find_class = WNDCLASSW {
+            style: WNDCLASS_STYLES(0x0001 | 0x0002), // CS_HREDRAW | CS_VREDRAW
+            lpfnWndProc: Some(find_dialog_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: hinstance.into(),
+            hIcon: Default::default(),
+            hCursor: LoadCursorW(None, IDC_ARROW).unwrap_or_default(),
+            hbrBackground: HBRUSH::default(), // No background brush, use WM_ERASEBKGND
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: PCWSTR(class_name.as_ptr()),
+        };
+        let _ = RegisterClassW(&find_class);
+    }
+}
+
+const CR: u16 = 0x000D;
+
+// WS_POPUP | WS_CAPTION | WS_SYSMENU
+const DIALOG_STYLE: WINDOW_STYLE = WINDOW_STYLE(0x80000000 | 0x00C00000 | 0x00080000);
+const DIALOG_EX_STYLE: WINDOW_EX_STYLE = WINDOW_EX_STYLE(0x00000001); // WS_EX_DLGMODALFRAME
+
+// Outer window size for a client area size
+fn window_size(client_width: i32, client_height: i32) -> (i32, i32) {
+    let mut rect = RECT {
+        left: 0,
+        top: 0,
+        right: client_width,
+        bottom: client_height,
+    };
+    unsafe {
+        let _ = AdjustWindowRectEx(&mut rect, DIALOG_STYLE, false, DIALOG_EX_STYLE);
+    }
+    (rect.right - rect.left, rect.bottom - rect.top)
+}
+
+fn create_dialog(owner: HWND) -> Option<HWND> {
+    unsafe {
+        let hinstance = GetModuleHandleW(None).unwrap_or_default();
+        let class_name = "FindDialogClass\0".encode_utf16().collect::<Vec<_>>();
+
+        // Open near the top right corner of the main window.
+        // The title and height are set by `layout`.
+        let mut owner_rect = RECT::default();
+        let _ = GetWindowRect(owner, &mut owner_rect);
+        let (width, height) = window_size(DIALOG_WIDTH, FIND_HEIGHT);
+        let x = (owner_rect.right - width - 32).max(owner_rect.left);
+        let y = owner_rect.top + 80;
+
+        let hwnd = CreateWindowExW(
+            DIALOG_EX_STYLE,
+            PCWSTR(class_name.as_ptr()),
+            PCWSTR::null(),
+            DIALOG_STYLE,
+            x,
+            y,
+            width,
+            height,
+            Some(owner),
+            None,
+            Some(hinstance.into()),
+            None,
+        )
+        .ok()?;
+        set_title_bar_theme(hwnd);
+        Some(hwnd)
+    }
+}
+
+/// Match the title bar to the dark mode setting
+pub fn set_title_bar_theme(hwnd: HWND) {
+    let use_dark_mode: i32 = if should_use_dark_mode() { 1 } else { 0 };
+    unsafe {
+        let _ = DwmSetWindowAttribute(
+            hwnd,
+            DWMWA_USE_IMMERSIVE_DARK_MODE,
+            &use_dark_mode as *const _ as *const _,
+            std::mem::size_of::<i32>() as u32,
+        );
+    }
+}
+
+/// The Segoe UI font used by the dialogs. The caller deletes it.
+pub fn create_dialog_font() -> HFONT {
+    let font_name = "Segoe UI";
+    unsafe {
+        CreateFontW(
+            -12,                      // cHeight
+            0,                        // cWidth
+            0,                        // cEscapement
+            0,                        // cOrientation
+            400,                      // cWeight (FW_NORMAL)
+            0,                        // bItalic
+            0,                        // bUnderline
+            0,                        // bStrikeOut
+            FONT_CHARSET(1),          // iCharSet (DEFAULT_CHARSET)
+            FONT_OUTPUT_PRECISION(0), // iOutPrecision
+            FONT_CLIP_PRECISION(0),   // iClipPrecision
+            FONT_QUALITY(0),          // iQuality
+            0,                        // iPitchAndFamily
+            PCWSTR(
+                font_name
+                    .encode_utf16()
+                    .chain(Some(0))
+                    .collect::<Vec<_>>()
+                    .as_ptr(),
+            ),
+        )
+    }
+}
+
+fn create_controls(hwnd: HWND) {
+    const WS_CHILD: u32 = 0x40000000;
+    const WS_TABSTOP: u32 = 0x00010000;
+    const WS_BORDER: u32 = 0x00800000;
+    const ES_AUTOHSCROLL: u32 = 0x0080;
+    const BS_AUTOCHECKBOX: u32 = 0x0003;
+    const BS_OWNERDRAW: u32 = 0x000B;
+
+    // Creation order is the tab order. `layout` positions and shows the controls.
+    let controls: [(&str, String, u32, i32); 14] = [
+        ("STATIC", get_string("FIND_WHAT"), 0, ID_FIND_LABEL),
+        (
+            "EDIT",
+            String::new(),
+            WS_TABSTOP | WS_BORDER | ES_AUTOHSCROLL,
+            ID_FIND_TEXT,
+        ),
+        ("STATIC", get_string("REPLACE_WITH"), 0, ID_REPLACE_LABEL),
+        (
+            "EDIT",
+            String::new(),
+            WS_TABSTOP | WS_BORDER | ES_AUTOHSCROLL,
+            ID_REPLACE_TEXT,
+        ),
+        (
+            "BUTTON",
+            get_string("FIND_MATCH_CASE"),
+            WS_TABSTOP | BS_AUTOCHECKBOX,
+            ID_MATCH_CASE,
+        ),
+        (
+            "BUTTON",
+            get_string("FIND_WHOLE_WORD"),
+            WS_TABSTOP | BS_AUTOCHECKBOX,
+            ID_WHOLE_WORD,
+        ),
+        (
+            "BUTTON",
+            get_string("FIND_WRAP_AROUND"),
+            WS_TABSTOP | BS_AUTOCHECKBOX,
+            ID_WRAP_AROUND,
+        ),
+        (
+            "BUTTON",
+            get_string("FIND_REGEX"),
+            WS_TABSTOP | BS_AUTOCHECKBOX,
+            ID_REGEX,
+        ),
+        (
+            "BUTTON",
+            get_string("FIND_NEXT"),
+            WS_TABSTOP | BS_OWNERDRAW,
+            IDOK,
+        ),
+        (
+            "BUTTON",
+            get_string("FIND_PREV"),
+            WS_TABSTOP | BS_OWNERDRAW,
+            ID_FIND_PREV,
+        ),
+        (
+            "BUTTON",
+            get_string("REPLACE"),
+            WS_TABSTOP | BS_OWNERDRAW,
+            ID_REPLACE,
+        ),
+        (
+            "BUTTON",
+            get_string("REPLACE_ALL"),
+            WS_TABSTOP | BS_OWNERDRAW,
+            ID_REPLACE_ALL,
+        ),
+        (
+            "BUTTON",
+            get_string("FIND_CANCEL"),
+            WS_TABSTOP | BS_OWNERDRAW,
+            IDCANCEL,
+        ),
+        ("STATIC", String::new(), 0, ID_MESSAGE),
+    ];
+
+    unsafe {
+        let hinstance = GetModuleHandleW(None).unwrap_or_default();
+        let font = create_dialog_font();
+        if let Ok(mut dialog_font) = DIALOG_FONT.lock() {
+            *dialog_font = Some(font.0 as isize);
+        }
+
+        for (class, text, style, id) in controls {
+            let class_utf16: Vec<u16> = class.encode_utf16().chain(Some(0)).collect();
+            let text_utf16: Vec<u16> = text.encode_utf16().chain(Some(0)).collect();
+            if let Ok(control) = CreateWindowExW(
+                WINDOW_EX_STYLE(0),
+                PCWSTR(class_utf16.as_ptr()),
+                PCWSTR(text_utf16.as_ptr()),
+                WINDOW_STYLE(WS_CHILD | style),
+                0,
+                0,
+                0,
+                0,
+                Some(hwnd),
+                Some(HMENU(id as isize as *mut core::ffi::c_void)),
+                Some(hinstance.into()),
+                None,
+            ) {
+                SendMessageW(
+                    control,
+                    WM_SETFONT,
+                    Some(WPARAM(font.0 as usize)),
+                    Some(LPARAM(1)),
+                );
+            }
+        }
+
+        if let Ok(state) = FIND_STATE.lock() {
+            for (id, checked) in [
+                (ID_MATCH_CASE, state.options.match_case),
+                (ID_WHOLE_WORD, state.options.whole_word),
+                (ID_WRAP_AROUND, state.options.wrap_around),
+                (ID_REGEX, state.options.regex),
+            ] {
+                let _ = CheckDlgButton(hwnd, id, if checked { BST_CHECKED } else { BST_UNCHECKED });
+            }
+        }
+    }
+}
+
+// Position the controls for Find or Replace mode and fit the window around them
+fn layout(hwnd: HWND, replace: bool) {
+    const SWP_NOMOVE: u32 = 0x0002;
+    const SWP_NOZORDER: u32 = 0x0004;
+    unsafe {
+        for (id, find_rect, replace_rect) in LAYOUT {
+            let Ok(control) = GetDlgItem(Some(hwnd), id) else {
+                continue;
+            };
+            match if replace { replace_rect } else { find_rect } {
+                Some([x, y, width, height]) => {
+                    let _ = SetWindowPos(
+                        control,
+                        None,
+                        x,
+                        y,
+                        width,
+                        height,
+                        SET_WINDOW_POS_FLAGS(SWP_NOZORDER),
+                    );
+                    let _ = ShowWindow(control, SW_SHOW);
+                }
+                None => {
+                    let _ = ShowWindow(control, SW_HIDE);
+                }
+            }
+        }
+
+        let client_height = if replace { REPLACE_HEIGHT } else { FIND_HEIGHT };
+        let (width, height) = window_size(DIALOG_WIDTH, client_height);
+        let _ = SetWindowPos(
+            hwnd,
+            None,
+            0,
+            0,
+            width,
+            height,
+            SET_WINDOW_POS_FLAGS(SWP_NOMOVE | SWP_NOZORDER),
+        );
+
+        let title = format!(
+            "{}\0",
+            get_string(if replace {
+                "REPLACE_TITLE"
+            } else {
+                "FIND_TITLE"
+            })
+        );
+        let title_utf16: Vec<u16> = title.encode_utf16().collect();
+        let _ = SetWindowTextW(hwnd, PCWSTR(title_utf16.as_ptr()));
+    }
+}
+
+/// Show a result or error line in the dialog's `ID_MESSAGE` control
+pub fn set_message(hwnd: HWND, message: &str) {
+    unsafe {
+        if let Ok(message_hwnd) = GetDlgItem(Some(hwnd), ID_MESSAGE) {
+            let message_utf16: Vec<u16> = message.encode_utf16().chain(Some(0)).collect();
+            let _ = SetWindowTextW(message_hwnd, PCWSTR(message_utf16.as_ptr()));
+        }
+    }
+}
+
+// The find buttons only work with something to search for, and the replace buttons also
+// need an editable document
+fn update_buttons(hwnd: HWND) {
+    unsafe {
+        let has_text = GetDlgItem(Some(hwnd), ID_FIND_TEXT)
+            .map(|text_hwnd| GetWindowTextLengthW(text_hwnd) > 0)
+            .unwrap_or(false);
+        let can_edit = GetWindow(hwnd, GW_OWNER)
+            .map(|owner| !is_read_only(editor_hwnd(owner)))
+            .unwrap_or(false);
+        for (id, enabled) in [
+            (IDOK, has_text),
+            (ID_FIND_PREV, has_text),
+            (ID_REPLACE, has_text && can_edit),
+            (ID_REPLACE_ALL, has_text && can_edit),
+        ] {
+            if let Ok(button) = GetDlgItem(Some(hwnd), id) {
+                let _ = EnableWindow(button, enabled);
+            }
+        }
+    }
+}
+
+fn control_text(hwnd: HWND, id: i32) -> Vec<u16> {
+    unsafe {
+        GetDlgItem(Some(hwnd), id)
+            .map(|control| {
+                let mut buffer = vec![0u16; GetWindowTextLengthW(control) as usize + 1];
+                let len = GetWindowTextW(control, &mut buffer);
+                buffer.truncate(len.max(0) as usize);
+                buffer
+            })
+            .unwrap_or_default()
+    }
+}
+
+// Store the dialog's search text, replacement and options for later searches.
+// Returns None when there is nothing to search for or the regular expression is invalid.
+fn store_dialog_state(hwnd: HWND) -> Option<Query> {
+    let query_text = control_text(hwnd, ID_FIND_TEXT);
+    if query_text.is_empty() {
+        return None;
+    }
+    let options = unsafe {
+        SearchOptions {
+            match_case: IsDlgButtonChecked(hwnd, ID_MATCH_CASE) == BST_CHECKED.0,
+            whole_word: IsDlgButtonChecked(hwnd, ID_WHOLE_WORD) == BST_CHECKED.0,
+            wrap_around: IsDlgButtonChecked(hwnd, ID_WRAP_AROUND) == BST_CHECKED.0,
+            regex: IsDlgButtonChecked(hwnd, ID_REGEX) == BST_CHECKED.0,
+        }
+    };
+    let query = match Query::new(&query_text, options) {
+        Ok(query) => query,
+        Err(error) => {
+            set_message(
+                hwnd,
+                &get_string("FIND_REGEX_ERROR").replace("{error}", &error),
+            );
+            return None;
+        }
+    };
+    set_message(hwnd, "");
+
+    if let Ok(mut state) = FIND_STATE.lock() {
+        state.query = query_text;
+        state.replacement = control_text(hwnd, ID_REPLACE_TEXT);
+        state.options = options;
+    }
+    Some(query)
+}
+
+// Search the owner's editor with the dialog's settings
+fn search_from_dialog(hwnd: HWND, forward: bool) {
+    if store_dialog_state(hwnd).is_none() {
+        return;
+    }
+    if let Ok(owner) = unsafe { GetWindow(hwnd, GW_OWNER) } {
+        find_next(owner, forward);
+    }
+}
+
+// Replace the selection if it is a match, then select the next match
+fn replace_from_dialog(hwnd: HWND) {
+    let Some(query) = store_dialog_state(hwnd) else {
+        return;
+    };
+    let Ok(owner) = (unsafe { GetWindow(hwnd, GW_OWNER) }) else {
+        return;
+    };
+    let edit_hwnd = editor_hwnd(owner);
+    if is_read_only(edit_hwnd) {
+        return;
+    }
+
+    let text = search::collapse_crlf(&editor_text(edit_hwnd));
+    let replacement = control_text(hwnd, ID_REPLACE_TEXT);
+    if let Some(replaced) = search::replace_match(&text, &query, selection(edit_hwnd), &replacement)
+    {
+        const EM_REPLACESEL: u32 = 0x00C2;
+        let replaced: Vec<u16> = replaced.into_iter().chain(Some(0)).collect();
+        unsafe {
+            SendMessageW(
+                edit_hwnd,
+                EM_REPLACESEL,
+                Some(WPARAM(1)), // Can be undone
+                Some(LPARAM(replaced.as_ptr() as isize)),
+            );
+        }
+    }
+    find_next(owner, true);
+}
+
+// Replace every match in the document with one edit, so a single Undo reverts it
+fn replace_all_from_dialog(hwnd: HWND) {
+    let Some(query) = store_dialog_state(hwnd) else {
+        return;
+    };
+    let Ok(owner) = (unsafe { GetWindow(hwnd, GW_OWNER) }) else {
+        return;
+    };
+    let edit_hwnd = editor_hwnd(owner);
+    if is_read_only(edit_hwnd) {
+        return;
+    }
+
+    let text = search::collapse_crlf(&editor_text(edit_hwnd));
+    let replacement = control_text(hwnd, ID_REPLACE_TEXT);
+    let Some(replaced) = search::replace_all(&text, &query, &replacement) else {
+        let query_text = control_text(hwnd, ID_FIND_TEXT);
+        set_message(
+            hwnd,
+            &get_string("FIND_NOT_FOUND").replace("{text}", &String::from_utf16_lossy(&query_text)),
+        );
+        return;
+    };
+    let replaced_text: Vec<u16> = replaced.text.into_iter().chain(Some(0)).collect();
+
+    const WM_SETREDRAW: u32 = 0x000B;
+    const EM_SETSEL: u32 = 0x00B1;
+    const EM_REPLACESEL: u32 = 0x00C2;
+    const EM_STOPGROUPTYPING: u32 = 0x0458;
+    const EM_GETSCROLLPOS: u32 = 0x04DD;
+    const EM_SETSCROLLPOS: u32 = 0x04DE;
+    unsafe {
+        // Keep the view where it was instead of scrolling through the document
+        let mut scroll_pos = POINT::default();
+        SendMessageW(
+            edit_hwnd,
+            EM_GETSCROLLPOS,
+            Some(WPARAM(0)),
+            Some(LPARAM(&mut scroll_pos as *mut POINT as isize)),
+        );
+        SendMessageW(edit_hwnd, WM_SETREDRAW, Some(WPARAM(0)), Some(LPARAM(0)));
+
+        // Don't merge the replacement into the undo step of earlier typing
+        SendMessageW(
+            edit_hwnd,
+            EM_STOPGROUPTYPING,
+            Some(WPARAM(0)),
+            Some(LPARAM(0)),
+        );
+        SendMessageW(
+            edit_hwnd,
+            EM_SETSEL,
+            Some(WPARAM(replaced.start)),
+            Some(LPARAM(replaced.end as isize)),
+        );
+        SendMessageW(
+            edit_hwnd,
+            EM_REPLACESEL,
+            Some(WPARAM(1)), // Can be undone
+            Some(LPARAM(replaced_text.as_ptr() as isize)),
+        );
+
+        SendMessageW(
+            edit_hwnd,
+            EM_SETSCROLLPOS,
+            Some(WPARAM(0)),
+            Some(LPARAM(&mut scroll_pos as *mut POINT as isize)),
+        );
+        SendMessageW(edit_hwnd, WM_SETREDRAW, Some(WPARAM(1)), Some(LPARAM(0)));
+        let _ = InvalidateRect(Some(edit_hwnd), None, true);
+    }
+
+    set_message(
+        hwnd,
+        &get_string("REPLACE_COUNT").replace("{count}", &replaced.count.to_string()),
+    );
+}
+
+fn editor_hwnd(owner: HWND) -> HWND {
+    unsafe { HWND(GetWindowLongPtrW(owner, WINDOW_LONG_PTR_INDEX(0)) as _) }
+}
+
+/// Whether the editor is locked against edits
+///
+/// EM_SETREADONLY keeps the ES_READONLY style in sync, so the style is the only record of
+/// the read-only state.
+pub fn is_read_only(edit_hwnd: HWND) -> bool {
+    const GWL_STYLE: WINDOW_LONG_PTR_INDEX = WINDOW_LONG_PTR_INDEX(-16);
+    const ES_READONLY: isize = 0x0800;
+    unsafe { GetWindowLongPtrW(edit_hwnd, GWL_STYLE) & ES_READONLY != 0 }
+}
+
+/// Get the editor text as UTF-16, line breaks as CRLF
+pub fn editor_text(edit_hwnd: HWND) -> Vec<u16> {
+    unsafe {
+        let text_len = SendMessageW(edit_hwnd, 0x000E, Some(WPARAM(0)), Some(LPARAM(0))).0 as usize; // WM_GETTEXTLENGTH
+        let mut buffer: Vec<u16> = vec![0; text_len + 1];
+        let copied = SendMessageW(
+            edit_hwnd,
+            0x000D, // WM_GETTEXT
+            Some(WPARAM(text_len + 1)),
+            Some(LPARAM(buffer.as_mut_ptr() as isize)),
+        )
+        .0 as usize;
+        buffer.truncate(copied.min(text_len));
+        buffer
+    }
+}
+
+/// The editor's selection as (start, end)
+pub fn selection(edit_hwnd: HWND) -> (usize, usize) {
+    const EM_GETSEL: u32 = 0x00B0;
+    let mut start_pos: u32 = 0;
+    let mut end_pos: u32 = 0;
+    unsafe {
+        SendMessageW(
+            edit_hwnd,
+            EM_GETSEL,
+            Some(WPARAM(&mut start_pos as *mut u32 as usize)),
+            Some(LPARAM(&mut end_pos as *mut u32 as isize)),
+        );
+    }
+    (start_pos as usize, end_pos as usize)
+}
+
+fn selected_text(edit_hwnd: HWND) -> Vec<u16> {
+    let (start, end) = selection(edit_hwnd);
+    if start == end {
+        return Vec::new();
+    }
+    let text = search::collapse_crlf(&editor_text(edit_hwnd));
+    text.get(start..end.min(text.len()))
+        .map(|selected| selected.to_vec())
+        .unwrap_or_default()
+}
+
+/// A solid brush for a color, kept for the lifetime of the app
+pub fn brush_for(color: COLORREF) -> HBRUSH {
+    let Ok(mut brushes) = BRUSHES.lock() else {
+        return HBRUSH::default();
+    };
+    if let Some((_, brush)) = brushes
+        .iter()
+        .find(|(brush_color, _)| *brush_color == color.0)
+    {
+        return HBRUSH(*brush as _);
+    }
+    let brush = unsafe { CreateSolidBrush(color) };
+    brushes.push((color.0, brush.0 as isize));
+    brush
+}
+
+/// Colors for the dialog background, text and edit box background
+pub fn dialog_colors() -> (COLORREF, COLORREF, COLORREF) {
+    let theme = active_theme();
+    (
+        COLORREF(theme.menu_bg),
+        COLORREF(theme.menu_text),
+        COLORREF(theme.editor_bg),
+    )
+}
+
+/// Draw an owner-drawn push button as a flat button in the current palette
+pub fn draw_button(item: &DRAWITEMSTRUCT) {
+    const ODS_SELECTED: u32 = 0x0001;
+    const ODS_DISABLED: u32 = 0x0004;
+    const ODS_FOCUS: u32 = 0x0010;
+
+    let state = item.itemState.0;
+    let theme = active_theme();
+    let (bg, pressed, border, text, disabled) = (
+        COLORREF(theme.button_bg),
+        COLORREF(theme.button_pressed),
+        COLORREF(theme.button_border),
+        COLORREF(theme.menu_text),
+        COLORREF(theme.menu_text_disabled),
+    );
+
+    unsafe {
+        let hdc = item.hDC;
+        let mut rect = item.rcItem;
+        let fill = if state & ODS_SELECTED != 0 {
+            pressed
+        } else {
+            bg
+        };
+        FillRect(hdc, &rect, brush_for(fill));
+        FrameRect(hdc, &rect, brush_for(border));
+
+        let len = GetWindowTextLengthW(item.hwndItem);
+        let mut label = vec![0u16; len as usize + 1];
+        let copied = GetWindowTextW(item.hwndItem, &mut label);
+        label.truncate(copied.max(0) as usize);
+
+        let _ = SetBkMode(hdc, BACKGROUND_MODE(1)); // TRANSPARENT
+        let _ = SetTextColor(
+            hdc,
+            if state & ODS_DISABLED != 0 {
+                disabled
+            } else {
+                text
+            },
+        );
+        let _ = DrawTextW(
+            hdc,
+            &mut label,
+            &mut rect,
+            DRAW_TEXT_FORMAT(0x00000001 | 0x00000020 | 0x00000004), // DT_CENTER | DT_SINGLELINE | DT_VCENTER
+        );
+
+        if state & ODS_FOCUS != 0 {
+            let mut focus_rect = item.rcItem;
+            focus_rect.left += 3;
+            focus_rect.top += 3;
+            focus_rect.right -= 3;
+            focus_rect.bottom -= 3;
+            let _ = DrawFocusRect(hdc, &focus_rect);
+        }
+    }
+}
+
+extern "system" fn find_dialog_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match msg {
+            WM_CREATE => {
+                create_controls(hwnd);
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                let id = (wparam.0 & 0xFFFF) as i32;
+                let code = (wparam.0 >> 16) & 0xFFFF;
+                const EN_CHANGE: usize = 0x0300;
+                match id {
+                    IDOK => search_from_dialog(hwnd, true),
+                    ID_FIND_PREV => search_from_dialog(hwnd, false),
+                    ID_REPLACE => replace_from_dialog(hwnd),
+                    ID_REPLACE_ALL => replace_all_from_dialog(hwnd),
+                    IDCANCEL => {
+                        let _ = ShowWindow(hwnd, SW_HIDE);
+                    }
+                    ID_FIND_TEXT if code == EN_CHANGE => update_buttons(hwnd),
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                // Keep the window and its state for the next time Find is opened
+                let _ = ShowWindow(hwnd, SW_HIDE);
+                LRESULT(0)
+            }
+            WM_DRAWITEM => {
+                let item = &*(lparam.0 as *const DRAWITEMSTRUCT);
+                draw_button(item);
+                LRESULT(1)
+            }
+            0x0014 => {
+                // WM_ERASEBKGND - Fill with the dialog background color
+                let hdc = HDC(wparam.0 as isize as *mut core::ffi::c_void);
+                let mut rect = RECT::default();
+                let _ = GetClientRect(hwnd, &mut rect);
+                let (bg_color, _, _) = dialog_colors();
+                FillRect(hdc, &rect, brush_for(bg_color));
+                LRESULT(1)
+            }
+            0x0133 | 0x0135 | 0x0138 => {
+                // WM_CTLCOLOREDIT, WM_CTLCOLORBTN, WM_CTLCOLORSTATIC
+                let hdc = HDC(wparam.0 as isize as *mut core::ffi::c_void);
+                let (bg_color, text_color, edit_bg_color) = dialog_colors();
+                let bg_color = if msg == 0x0133 {
+                    edit_bg_color
+                } else {
+                    bg_color
+                };
+                SetTextColor(hdc, text_color);
+                SetBkColor(hdc, bg_color);
+                LRESULT(brush_for(bg_color).0 as isize)
+            }
+            WM_DESTROY => {
+                if let Ok(mut dialog) = FIND_DIALOG.lock() {
+                    *dialog = None;
+                }
+                if let Ok(mut dialog_font) = DIALOG_FONT.lock()
+                    && let Some(font) = dialog_font.take()
+                {
+                    let _ = DeleteObject(HFONT(font as _).into());
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+        }
+    }
+}
@@ -0,0 +1,386 @@
+// Go To dialog
+//
+// A small modal window that moves the caret to `line` or `line:column`. It shares the Find
+// dialog's look, and runs its own message loop while the main window is disabled.
+
+use crate::find_dialog::{
+    ID_MESSAGE, brush_for, create_dialog_font, dialog_colors, draw_button, editor_text, selection,
+    set_message, set_title_bar_theme,
+};
+use crate::i18n::get_string;
+use crate::line_column::{calculate_position, count_lines, parse_line_column};
+use crate::search::collapse_crlf;
+use std::sync::Mutex;
+use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    DeleteObject, FillRect, HBRUSH, HDC, HFONT, SetBkColor, SetTextColor,
+};
+use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::Controls::DRAWITEMSTRUCT;
+use windows::Win32::UI::Input::KeyboardAndMouse::{EnableWindow, SetFocus};
+use windows::Win32::UI::WindowsAndMessaging::{
+    AdjustWindowRectEx, CreateWindowExW, DefWindowProcW, DestroyWindow, DispatchMessageW, GW_OWNER,
+    GetClientRect, GetDlgItem, GetMessageW, GetWindow, GetWindowLongPtrW, GetWindowRect,
+    GetWindowTextLengthW, GetWindowTextW, HMENU, IDC_ARROW, IsDialogMessageW, LoadCursorW, MSG,
+    PostQuitMessage, RegisterClassW, SW_SHOW, SendMessageW, SetWindowTextW, ShowWindow,
+    TranslateMessage, WINDOW_EX_STYLE, WINDOW_LONG_PTR_INDEX, WINDOW_STYLE, WM_CLOSE, WM_COMMAND,
+    WM_CREATE, WM_DESTROY, WM_DRAWITEM, WM_SETFONT, WNDCLASS_STYLES, WNDCLASSW,
+};
+use windows::core::PCWSTR;
+
+// Control IDs. IsDialogMessageW sends IDOK for Enter and IDCANCEL for Esc.
+const IDOK: i32 = 1;
+const IDCANCEL: i32 = 2;
+const ID_LINE_LABEL: i32 = 1001;
+const ID_LINE_TEXT: i32 = 1002;
+
+// Client area size of the dialog
+const DIALOG_WIDTH: i32 = 300;
+const DIALOG_HEIGHT: i32 = 110;
+
+const CR: u16 = 0x000D;
+const LF: u16 = 0x000A;
+
+// The open dialog and its font, set while the dialog is shown
+static GOTO_DIALOG: Mutex<Option<isize>> = Mutex::new(None);
+static DIALOG_FONT: Mutex<Option<isize>> = Mutex::new(None);
+
+// Register the Go To dialog window class
+pub unsafe fn register_goto_dialog_class() {
+    unsafe {
+        let hinstance = GetModuleHandleW(None).unwrap_or_default();
+        let class_name = "GotoDialogClass\0".encode_utf16().collect::<Vec<_>>();
+        let goto_class = WNDCLASSW {
+            style: WNDCLASS_STYLES(0x0001 | 0x0002), // CS_HREDRAW | CS_VREDRAW
+            lpfnWndProc: Some(goto_dialog_proc),
+            cbClsExtra: 0,
+            cbWndExtra: 0,
+            hInstance: hinstance.into(),
+            hIcon: Default::default(),
+            hCursor: LoadCursorW(None, IDC_ARROW).unwrap_or_default(),
+            hbrBackground: HBRUSH::default(), // No background brush, use WM_ERASEBKGND
+            lpszMenuName: PCWSTR::null(),
+            lpszClassName: PCWSTR(class_name.as_ptr()),
+        };
+        let _ = RegisterClassW(&goto_class);
+    }
+}
+
+/// Show the Go To dialog for the main window and return once it is closed.
+/// The input starts with the caret's current line.
+pub fn show_goto_dialog(owner: HWND) {
+    if GOTO_DIALOG.lock().ok().and_then(|dialog| *dialog).is_some() {
+        return;
+    }
+
+    unsafe {
+        let edit_hwnd = HWND(GetWindowLongPtrW(owner, WINDOW_LONG_PTR_INDEX(0)) as _);
+        let text = collapse_crlf(&editor_text(edit_hwnd));
+        let (caret, _) = selection(edit_hwnd);
+        let current_line = count_lines(&String::from_utf16_lossy(&text[..caret.min(text.len())]));
+
+        let Some(hwnd) = create_dialog(owner) else {
+            return;
+        };
+        if let Ok(mut dialog) = GOTO_DIALOG.lock() {
+            *dialog = Some(hwnd.0 as isize);
+        }
+
+        if let Ok(line_hwnd) = GetDlgItem(Some(hwnd), ID_LINE_TEXT) {
+            let line_text: Vec<u16> = format!("{}\0", current_line).encode_utf16().collect();
+            let _ = SetWindowTextW(line_hwnd, PCWSTR(line_text.as_ptr()));
+            const EM_SETSEL: u32 = 0x00B1;
+            SendMessageW(line_hwnd, EM_SETSEL, Some(WPARAM(0)), Some(LPARAM(-1)));
+        }
+
+        // Modal: the main window stays disabled until the dialog is closed
+        let _ = EnableWindow(owner, false);
+        let _ = ShowWindow(hwnd, SW_SHOW);
+        if let Ok(line_hwnd) = GetDlgItem(Some(hwnd), ID_LINE_TEXT) {
+            let _ = SetFocus(Some(line_hwnd));
+        }
+
+        let mut msg = MSG::default();
+        while GOTO_DIALOG.lock().ok().and_then(|dialog| *dialog).is_some() {
+            if !GetMessageW(&mut msg, None, 0, 0).as_bool() {
+                // Leave WM_QUIT for the main message loop
+                PostQuitMessage(msg.wParam.0 as i32);
+                break;
+            }
+            if !IsDialogMessageW(hwnd, &msg).as_bool() {
+                let _ = TranslateMessage(&msg);
+                let _ = DispatchMessageW(&msg);
+            }
+        }
+    }
+}
+
+fn create_dialog(owner: HWND) -> Option<HWND> {
+    unsafe {
+        let hinstance = GetModuleHandleW(None).unwrap_or_default();
+        let class_name = "GotoDialogClass\0".encode_utf16().collect::<Vec<_>>();
+        let title = format!("{}\0", get_string("GOTO_TITLE"));
+        let title_utf16: Vec<u16> = title.encode_utf16().collect();
+
+        // WS_POPUP | WS_CAPTION | WS_SYSMENU
+        let style = WINDOW_STYLE(0x80000000 | 0x00C00000 | 0x00080000);
+        let ex_style = WINDOW_EX_STYLE(0x00000001); // WS_EX_DLGMODALFRAME
+        let mut rect = RECT {
+            left: 0,
+            top: 0,
+            right: DIALOG_WIDTH,
+            bottom: DIALOG_HEIGHT,
+        };
+        let _ = AdjustWindowRectEx(&mut rect, style, false, ex_style);
+
+        // Center over the main window
+        let mut owner_rect = RECT::default();
+        let _ = GetWindowRect(owner, &mut owner_rect);
+        let width = rect.right - rect.left;
+        let height = rect.bottom - rect.top;
+        let x = owner_rect.left + (owner_rect.right - owner_rect.left - width) / 2;
+        let y = owner_rect.top + (owner_rect.bottom - owner_rect.top - height) / 2;
+
+        let hwnd = CreateWindowExW(
+            ex_style,
+            PCWSTR(class_name.as_ptr()),
+            PCWSTR(title_utf16.as_ptr()),
+            style,
+            x,
+            y,
+            width,
+            height,
+            Some(owner),
+            None,
+            Some(hinstance.into()),
+            None,
+        )
+        .ok()?;
+        set_title_bar_theme(hwnd);
+        Some(hwnd)
+    }
+}
+
+fn create_controls(hwnd: HWND) {
+    const WS_CHILD_VISIBLE: u32 = 0x40000000 | 0x10000000;
+    const WS_TABSTOP: u32 = 0x00010000;
+    const WS_BORDER: u32 = 0x00800000;
+    const ES_AUTOHSCROLL: u32 = 0x0080;
+    const BS_OWNERDRAW: u32 = 0x000B;
+
+    let controls: [(&str, String, u32, i32, [i32; 4]); 5] = [
+        (
+            "STATIC",
+            get_string("GOTO_LINE"),
+            0,
+            ID_LINE_LABEL,
+            [12, 15, 120, 20],
+        ),
+        (
+            "EDIT",
+            String::new(),
+            WS_TABSTOP | WS_BORDER | ES_AUTOHSCROLL,
+            ID_LINE_TEXT,
+            [136, 12, 152, 23],
+        ),
+        ("STATIC", String::new(), 0, ID_MESSAGE, [12, 44, 276, 20]),
+        (
+            "BUTTON",
+            get_string("GOTO_OK"),
+            WS_TABSTOP | BS_OWNERDRAW,
+            IDOK,
+            [104, 72, 90, 26],
+        ),
+        (
+            "BUTTON",
+            get_string("FIND_CANCEL"),
+            WS_TABSTOP | BS_OWNERDRAW,
+            IDCANCEL,
+            [198, 72, 90, 26],
+        ),
+    ];
+
+    unsafe {
+        let hinstance = GetModuleHandleW(None).unwrap_or_default();
+        let font = create_dialog_font();
+        if let Ok(mut dialog_font) = DIALOG_FONT.lock() {
+            *dialog_font = Some(font.0 as isize);
+        }
+
+        for (class, text, style, id, [x, y, width, height]) in controls {
+            let class_utf16: Vec<u16> = class.encode_utf16().chain(Some(0)).collect();
+            let text_utf16: Vec<u16> = text.encode_utf16().chain(Some(0)).collect();
+            if let Ok(control) = CreateWindowExW(
+                WINDOW_EX_STYLE(0),
+                PCWSTR(class_utf16.as_ptr()),
+                PCWSTR(text_utf16.as_ptr()),
+                WINDOW_STYLE(WS_CHILD_VISIBLE | style),
+                x,
+                y,
+                width,
+                height,
+                Some(hwnd),
+                Some(HMENU(id as isize as *mut core::ffi::c_void)),
+                Some(hinstance.into()),
+                None,
+            ) {
+                SendMessageW(
+                    control,
+                    WM_SETFONT,
+                    Some(WPARAM(font.0 as usize)),
+                    Some(LPARAM(1)),
+                );
+            }
+        }
+    }
+}
+
+// Move the caret to the entered position, or explain what is wrong with the input
+fn go_to(hwnd: HWND) {
+    unsafe {
+        let Ok(owner) = GetWindow(hwnd, GW_OWNER) else {
+            return;
+        };
+        let edit_hwnd = HWND(GetWindowLongPtrW(owner, WINDOW_LONG_PTR_INDEX(0)) as _);
+
+        let input = GetDlgItem(Some(hwnd), ID_LINE_TEXT)
+            .map(|line_hwnd| {
+                let mut buffer = vec![0u16; GetWindowTextLengthW(line_hwnd) as usize + 1];
+                let len = GetWindowTextW(line_hwnd, &mut buffer);
+                String::from_utf16_lossy(&buffer[..len.max(0) as usize])
+            })
+            .unwrap_or_default();
+        let Some((line, col)) = parse_line_column(&input) else {
+            set_message(hwnd, &get_string("GOTO_INVALID"));
+            return;
+        };
+
+        let text = editor_text(edit_hwnd);
+        let text_str = String::from_utf16_lossy(&text);
+        let line_count = count_lines(&text_str);
+        if line > line_count {
+            set_message(
+                hwnd,
+                &get_string("GOTO_OUT_OF_RANGE").replace("{count}", &line_count.to_string()),
+            );
+            return;
+        }
+        close_dialog(hwnd);
+        select_position(edit_hwnd, &text, line, col.unwrap_or(1));
+        let _ = SetFocus(Some(edit_hwnd));
+    }
+}
+
+/// Move the caret to a 1-indexed line and column and scroll it into view
+///
+/// A line past the end of the text goes to the last line, and a column past the end of a
+/// line goes to the end of that line.
+pub fn move_caret_to(edit_hwnd: HWND, line: i32, col: i32) {
+    let text = editor_text(edit_hwnd);
+    let line = line.clamp(1, count_lines(&String::from_utf16_lossy(&text)));
+    select_position(edit_hwnd, &text, line, col);
+}
+
+// Put the caret at a line and column of the editor's text
+fn select_position(edit_hwnd: HWND, text: &[u16], line: i32, col: i32) {
+    let Some(offset) = calculate_position(&String::from_utf16_lossy(text), line, col) else {
+        return;
+    };
+
+    // RichEdit counts each CRLF as a single position
+    let offset = offset as usize;
+    let crlf_count = text[..offset.min(text.len())]
+        .windows(2)
+        .filter(|pair| *pair == [CR, LF])
+        .count();
+    let position = offset - crlf_count;
+
+    const EM_SETSEL: u32 = 0x00B1;
+    const EM_SCROLLCARET: u32 = 0x00B7;
+    unsafe {
+        SendMessageW(
+            edit_hwnd,
+            EM_SETSEL,
+            Some(WPARAM(position)),
+            Some(LPARAM(position as isize)),
+        );
+        SendMessageW(edit_hwnd, EM_SCROLLCARET, Some(WPARAM(0)), Some(LPARAM(0)));
+    }
+}
+
+// Re-enable the main window before destroying the dialog, so it gets the focus back
+fn close_dialog(hwnd: HWND) {
+    unsafe {
+        if let Ok(owner) = GetWindow(hwnd, GW_OWNER) {
+            let _ = EnableWindow(owner, true);
+        }
+        let _ = DestroyWindow(hwnd);
+    }
+}
+
+extern "system" fn goto_dialog_proc(
+    hwnd: HWND,
+    msg: u32,
+    wparam: WPARAM,
+    lparam: LPARAM,
+) -> LRESULT {
+    unsafe {
+        match msg {
+            WM_CREATE => {
+                create_controls(hwnd);
+                LRESULT(0)
+            }
+            WM_COMMAND => {
+                match (wparam.0 & 0xFFFF) as i32 {
+                    IDOK => go_to(hwnd),
+                    IDCANCEL => close_dialog(hwnd),
+                    _ => {}
+                }
+                LRESULT(0)
+            }
+            WM_CLOSE => {
+                close_dialog(hwnd);
+                LRESULT(0)
+            }
+            WM_DRAWITEM => {
+                let item = &*(lparam.0 as *const DRAWITEMSTRUCT);
+                draw_button(item);
+                LRESULT(1)
+            }
+            0x0014 => {
+                // WM_ERASEBKGND - Fill with the dialog background color
+                let hdc = HDC(wparam.0 as isize as *mut core::ffi::c_void);
+                let mut rect = RECT::default();
+                let _ = GetClientRect(hwnd, &mut rect);
+                let (bg_color, _, _) = dialog_colors();
+                FillRect(hdc, &rect, brush_for(bg_color));
+                LRESULT(1)
+            }
+            0x0133 | 0x0138 => {
+                // WM_CTLCOLOREDIT, WM_CTLCOLORSTATIC
+                let hdc = HDC(wparam.0 as isize as *mut core::ffi::c_void);
+                let (bg_color, text_color, edit_bg_color) = dialog_colors();
+                let bg_color = if msg == 0x0133 {
+                    edit_bg_color
+                } else {
+                    bg_color
+                };
+                SetTextColor(hdc, text_color);
+                SetBkColor(hdc, bg_color);
+                LRESULT(brush_for(bg_color).0 as isize)
+            }
+            WM_DESTROY => {
+                if let Ok(mut dialog) = GOTO_DIALOG.lock() {
+                    *dialog = None;
+                }
+                if let Ok(mut dialog_font) = DIALOG_FONT.lock()
+                    && let Some(font) = dialog_font.take()
+                {
+                    let _ = DeleteObject(HFONT(font as _).into());
+                }
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+        }
+    }
+}
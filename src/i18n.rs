@@ -52,11 +52,15 @@ fn get_japanese(key: &str) -> &'static str {
         // Menu
         "MENU_FILE" => "ファイル(&F)",
         "MENU_EDIT" => "編集(&E)",
+        "MENU_FORMAT" => "書式(&O)",
         "MENU_VIEW" => "表示(&V)",
         "MENU_NEW" => "新規(&N)",
         "MENU_OPEN" => "開く(&O)",
         "MENU_SAVE" => "上書き保存(&S)",
         "MENU_SAVEAS" => "名前を付けて保存(&A)",
+        "MENU_BACKUP" => "保存時にバックアップを作成(&B)",
+        "MENU_RECENT" => "最近使ったファイル(&R)",
+        "MENU_RECENT_EMPTY" => "(なし)",
         "MENU_EXIT" => "終了(&X)",
         "MENU_UNDO" => "元に戻す(&U)",
         "MENU_REDO" => "やり直し(&R)",
@@ -64,12 +68,27 @@ fn get_japanese(key: &str) -> &'static str {
         "MENU_CUT" => "切り取り(&X)",
         "MENU_PASTE" => "貼り付け(&V)",
         "MENU_DELETE" => "削除(&D)",
+        "MENU_FIND" => "検索(&F)...",
+        "MENU_FIND_NEXT" => "次を検索(&N)",
+        "MENU_FIND_PREV" => "前を検索(&P)",
+        "MENU_REPLACE" => "置換(&H)...",
+        "MENU_GOTO" => "行へ移動(&G)...",
         "MENU_SELECTALL" => "すべて選択(&A)",
+        "MENU_FONT" => "フォント(&F)...",
         "MENU_WORDWRAP" => "右端で折り返す(&W)",
         "MENU_STATUSBAR" => "ステータスバー(&B)",
+        "MENU_ZOOM" => "ズーム(&Z)",
         "MENU_ZOOMIN" => "拡大(&I)",
         "MENU_ZOOMOUT" => "縮小(&O)",
-        "MENU_DARKMODE" => "ダークモード(&D)",
+        "MENU_ZOOMRESET" => "ズームを既定に戻す(&R)",
+        "MENU_THEME" => "テーマ(&T)",
+        "MENU_THEME_SYSTEM" => "システム設定に合わせる(&S)",
+        "MENU_THEME_LIGHT" => "ライト(&L)",
+        "MENU_THEME_DARK" => "ダーク(&D)",
+        "MENU_LINEENDING" => "改行コード(&L)",
+        "MENU_ENCODING" => "エンコード(&N)",
+        "MENU_REOPEN_ENCODING" => "エンコードを指定して開き直す(&R)",
+        "MENU_SAVE_ENCODING" => "保存時のエンコード(&S)",
         // Context menu
         "CONTEXT_UNDO" => "元に戻す (Ctrl+Z)",
         "CONTEXT_REDO" => "やり直し (Ctrl+Y)",
@@ -80,6 +99,7 @@ fn get_japanese(key: &str) -> &'static str {
         "CONTEXT_SELECTALL" => "すべて選択 (Ctrl+A)",
         // Window title
         "WINDOW_TITLE" => "普通のメモ帳",
+        "TITLE_READ_ONLY" => "(読み取り専用)",
         // File
         "FILE_UNTITLED" => "無題",
         "FILE_DIALOG_OPEN" => "ファイルを開く",
@@ -88,10 +108,64 @@ fn get_japanese(key: &str) -> &'static str {
         "FILE_FILTER_TEXT" => "テキストファイル (*.txt)",
         "FILE_ENCODING" => "エンコード:",
         "ENCODING_AUTO" => "自動検出",
-        "ENCODING_ANSI" => "Shift-JIS",
+        // Dialogs
+        "DIALOG_SAVE_CHANGES" => "{file} への変更内容を保存しますか?",
+        "DIALOG_UNMAPPABLE" => {
+            "次の文字は {encoding} で表せないため、「?」に置き換えられます。\n\n{chars}\n\n代わりに UTF-8 で保存しますか?\n[いいえ] を選ぶと {encoding} のまま保存します。"
+        }
+        "DIALOG_UNMAPPABLE_CHAR" => "行 {line}、列 {col}: {char} (U+{code})",
+        "DIALOG_UNMAPPABLE_MORE" => "ほか {count} 文字",
+        "DIALOG_DECODE_ERRORS" => {
+            "{file} には {encoding} として正しくないバイト列が {count} か所あり、� (U+FFFD) に置き換えられました。このまま保存すると元のバイトは失われます。\n\n位置 (バイト): {offsets}\n\n読み取り専用で開きますか?\n[いいえ] を選ぶと別のエンコードで開き直します。"
+        }
+        // Find dialog
+        "FIND_TITLE" => "検索",
+        "FIND_WHAT" => "検索する文字列(&N):",
+        "FIND_MATCH_CASE" => "大文字と小文字を区別する(&C)",
+        "FIND_WHOLE_WORD" => "単語単位で探す(&W)",
+        "FIND_WRAP_AROUND" => "折り返す(&R)",
+        "FIND_REGEX" => "正規表現を使用する(&X)",
+        "FIND_NEXT" => "次を検索(&F)",
+        "FIND_PREV" => "前を検索(&P)",
+        "FIND_CANCEL" => "キャンセル",
+        "FIND_NOT_FOUND" => "「{text}」が見つかりません。",
+        "FIND_REGEX_ERROR" => "正規表現が正しくありません: {error}",
+        "REPLACE_TITLE" => "置換",
+        "REPLACE_WITH" => "置換後の文字列(&E):",
+        "REPLACE" => "置換(&H)",
+        "REPLACE_ALL" => "すべて置換(&A)",
+        "REPLACE_COUNT" => "{count} 件置換しました。",
+        // Go To dialog
+        "GOTO_TITLE" => "行へ移動",
+        "GOTO_LINE" => "行番号[:桁](&L):",
+        "GOTO_OK" => "移動",
+        "GOTO_INVALID" => "行番号か「行:桁」を入力してください。",
+        "GOTO_OUT_OF_RANGE" => "行番号は 1 から {count} の範囲で指定してください。",
+        // File errors
+        "ERROR_OPEN_FAILED" => "{file} を開けませんでした。",
+        "ERROR_SAVE_FAILED" => "{file} を保存できませんでした。",
+        "ERROR_NOT_FOUND" => "ファイルが見つかりません。",
+        "ERROR_PERMISSION_DENIED" => "アクセスが拒否されました。",
+        "ERROR_SHARING_VIOLATION" => "ファイルは別のプロセスで使用されています。",
+        "ERROR_INVALID_DATA" => "ファイルの内容が選択したエンコードとして正しくありません。",
+        "ERROR_DISK_FULL" => "ディスクの空き容量が不足しています。",
+        "ERROR_PATH_TOO_LONG" => "ファイルのパスが長すぎます。",
+        "ERROR_UNMAPPABLE" => "選択したエンコードで表せない文字が含まれています。",
+        "ERROR_KEPT_IN_TEMPORARY_FILE" => {
+            "ファイルを置き換えられませんでした。保存した内容は {file} にあります。"
+        }
+        "ERROR_UNKNOWN" => "予期しないエラーが発生しました: {message}",
+        // Command line
+        "CLI_UNKNOWN_OPTION" => "不明なオプションです: {option}",
+        "CLI_MISSING_VALUE" => "{option} の値が指定されていません。",
+        "CLI_INVALID_ENCODING" => "不明なエンコードです: {encoding}",
+        "CLI_INVALID_NUMBER" => "{option} の値が正しくありません: {value}",
+        "CLI_TOO_MANY_PATHS" => "一度に開けるファイルは 1 つだけです。",
         // Status bar
         "STATUS_LINE_COL" => "行 {line}、列 {col}",
         "STATUS_CHAR_COUNT" => "{count} 文字",
+        "STATUS_ENCODING_AMBIGUOUS" => "{encoding} (推定)",
+        "STATUS_LINEENDING_MIXED" => "混在",
         _ => "",
     }
 }
@@ -101,11 +175,15 @@ fn get_english(key: &str) -> &'static str {
         // Menu
         "MENU_FILE" => "File(&F)",
         "MENU_EDIT" => "Edit(&E)",
+        "MENU_FORMAT" => "Format(&O)",
         "MENU_VIEW" => "View(&V)",
         "MENU_NEW" => "New(&N)",
         "MENU_OPEN" => "Open(&O)",
         "MENU_SAVE" => "Save(&S)",
         "MENU_SAVEAS" => "Save As(&A)",
+        "MENU_BACKUP" => "Keep Backup on Save(&B)",
+        "MENU_RECENT" => "Recent Files(&R)",
+        "MENU_RECENT_EMPTY" => "(Empty)",
         "MENU_EXIT" => "Exit(&X)",
         "MENU_UNDO" => "Undo(&U)",
         "MENU_REDO" => "Redo(&R)",
@@ -113,12 +191,27 @@ fn get_english(key: &str) -> &'static str {
         "MENU_CUT" => "Cut(&X)",
         "MENU_PASTE" => "Paste(&V)",
         "MENU_DELETE" => "Delete(&D)",
+        "MENU_FIND" => "Find(&F)...",
+        "MENU_FIND_NEXT" => "Find Next(&N)",
+        "MENU_FIND_PREV" => "Find Previous(&P)",
+        "MENU_REPLACE" => "Replace(&H)...",
+        "MENU_GOTO" => "Go To(&G)...",
         "MENU_SELECTALL" => "Select All(&A)",
+        "MENU_FONT" => "Font(&F)...",
         "MENU_WORDWRAP" => "Word Wrap(&W)",
         "MENU_STATUSBAR" => "Status Bar(&B)",
+        "MENU_ZOOM" => "Zoom(&Z)",
         "MENU_ZOOMIN" => "Zoom In(&I)",
         "MENU_ZOOMOUT" => "Zoom Out(&O)",
-        "MENU_DARKMODE" => "Dark Mode(&D)",
+        "MENU_ZOOMRESET" => "Restore Default Zoom(&R)",
+        "MENU_THEME" => "Theme(&T)",
+        "MENU_THEME_SYSTEM" => "Use System Setting(&S)",
+        "MENU_THEME_LIGHT" => "Light(&L)",
+        "MENU_THEME_DARK" => "Dark(&D)",
+        "MENU_LINEENDING" => "Line Endings(&L)",
+        "MENU_ENCODING" => "Encoding(&N)",
+        "MENU_REOPEN_ENCODING" => "Reopen with Encoding(&R)",
+        "MENU_SAVE_ENCODING" => "Save with Encoding(&S)",
         // Context menu
         "CONTEXT_UNDO" => "Undo (Ctrl+Z)",
         "CONTEXT_REDO" => "Redo (Ctrl+Y)",
@@ -129,6 +222,7 @@ fn get_english(key: &str) -> &'static str {
         "CONTEXT_SELECTALL" => "Select All (Ctrl+A)",
         // Window title
         "WINDOW_TITLE" => "Normal Notepad",
+        "TITLE_READ_ONLY" => "(Read-Only)",
         // File
         "FILE_UNTITLED" => "Untitled",
         "FILE_DIALOG_OPEN" => "Open File",
@@ -137,10 +231,66 @@ fn get_english(key: &str) -> &'static str {
         "FILE_FILTER_TEXT" => "Text Files (*.txt)",
         "FILE_ENCODING" => "Encoding:",
         "ENCODING_AUTO" => "Auto",
-        "ENCODING_ANSI" => "ANSI",
+        // Dialogs
+        "DIALOG_SAVE_CHANGES" => "Do you want to save changes to {file}?",
+        "DIALOG_UNMAPPABLE" => {
+            "The following characters cannot be represented in {encoding} and will be replaced with \"?\":\n\n{chars}\n\nDo you want to save as UTF-8 instead?\nChoose No to save as {encoding} anyway."
+        }
+        "DIALOG_UNMAPPABLE_CHAR" => "Ln {line}, Col {col}: {char} (U+{code})",
+        "DIALOG_UNMAPPABLE_MORE" => "and {count} more",
+        "DIALOG_DECODE_ERRORS" => {
+            "{file} contains {count} byte sequences that are not valid {encoding}. They were replaced with � (U+FFFD), and saving would lose the original bytes.\n\nByte offsets: {offsets}\n\nDo you want to open the file read-only?\nChoose No to reopen it with a different encoding."
+        }
+        // Find dialog
+        "FIND_TITLE" => "Find",
+        "FIND_WHAT" => "Find what(&N):",
+        "FIND_MATCH_CASE" => "Match case(&C)",
+        "FIND_WHOLE_WORD" => "Match whole word only(&W)",
+        "FIND_WRAP_AROUND" => "Wrap around(&R)",
+        "FIND_REGEX" => "Use regular expressions(&X)",
+        "FIND_NEXT" => "Find Next(&F)",
+        "FIND_PREV" => "Find Previous(&P)",
+        "FIND_CANCEL" => "Cancel",
+        "FIND_NOT_FOUND" => "Cannot find \"{text}\".",
+        "FIND_REGEX_ERROR" => "Invalid regular expression: {error}",
+        "REPLACE_TITLE" => "Replace",
+        "REPLACE_WITH" => "Replace with(&E):",
+        "REPLACE" => "Replace(&H)",
+        "REPLACE_ALL" => "Replace All(&A)",
+        "REPLACE_COUNT" => "{count} replacements made.",
+        // Go To dialog
+        "GOTO_TITLE" => "Go To Line",
+        "GOTO_LINE" => "Line[:column](&L):",
+        "GOTO_OK" => "Go To",
+        "GOTO_INVALID" => "Enter a line number or line:column.",
+        "GOTO_OUT_OF_RANGE" => "The line number must be between 1 and {count}.",
+        // File errors
+        "ERROR_OPEN_FAILED" => "Could not open {file}.",
+        "ERROR_SAVE_FAILED" => "Could not save {file}.",
+        "ERROR_NOT_FOUND" => "The file was not found.",
+        "ERROR_PERMISSION_DENIED" => "Access is denied.",
+        "ERROR_SHARING_VIOLATION" => "The file is being used by another process.",
+        "ERROR_INVALID_DATA" => "The file is not valid in the selected encoding.",
+        "ERROR_DISK_FULL" => "There is not enough space on the disk.",
+        "ERROR_PATH_TOO_LONG" => "The file path is too long.",
+        "ERROR_UNMAPPABLE" => {
+            "The text contains characters that the selected encoding cannot represent."
+        }
+        "ERROR_KEPT_IN_TEMPORARY_FILE" => {
+            "The file could not be replaced. The saved text is in {file}."
+        }
+        "ERROR_UNKNOWN" => "An unexpected error occurred: {message}",
+        // Command line
+        "CLI_UNKNOWN_OPTION" => "Unknown option: {option}",
+        "CLI_MISSING_VALUE" => "Missing value for {option}.",
+        "CLI_INVALID_ENCODING" => "Unknown encoding: {encoding}",
+        "CLI_INVALID_NUMBER" => "Invalid value for {option}: {value}",
+        "CLI_TOO_MANY_PATHS" => "Only one file can be opened at a time.",
         // Status bar
         "STATUS_LINE_COL" => "Ln {line}, Col {col}",
         "STATUS_CHAR_COUNT" => "{count} characters",
+        "STATUS_ENCODING_AMBIGUOUS" => "{encoding} (guessed)",
+        "STATUS_LINEENDING_MIXED" => "Mixed",
         _ => "",
     }
 }
@@ -0,0 +1,241 @@
+// INI file format for the portable settings file (nn.ini)
+//
+//   ; Lines starting with ; or # are comments
+//   [Settings]
+//   WordWrap=1
+//   FontFace=MS Gothic
+//
+// Only the [Settings] section is read; its keys are listed in `settings`. Section names
+// ignore case, keys do not, and spaces around keys and values are ignored. Unknown keys,
+// comments and other sections are kept when the file is written back.
+//
+// Theme files (see `color_theme`) use the same format with a [Theme] section.
+
+const SECTION: &str = "Settings";
+
+/// Key/value pairs of the [Settings] section, in file order
+pub fn parse(text: &str) -> Vec<(String, String)> {
+    parse_section(text, SECTION)
+}
+
+/// Key/value pairs of one section, in file order
+pub fn parse_section(text: &str, section: &str) -> Vec<(String, String)> {
+    let mut entries = Vec::new();
+    let mut in_section = false;
+    for line in text.lines() {
+        let line = line.trim();
+        if let Some(name) = section_name(line) {
+            in_section = name.eq_ignore_ascii_case(section);
+        } else if in_section
+            && !is_comment(line)
+            && let Some((key, value)) = line.split_once('=')
+        {
+            entries.push((key.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    entries
+}
+
+/// Write `entries` into the [Settings] section of an existing file's text. Keys already
+/// there are updated in place, new keys go at the end of the section, and the section is
+/// added if the file does not have one.
+pub fn update(existing: &str, entries: &[(&str, String)]) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut written = vec![false; entries.len()];
+    let mut in_section = false;
+    // Where new keys go: after the last non-blank line of the section
+    let mut section_end = None;
+
+    for line in existing.lines() {
+        let trimmed = line.trim();
+        if let Some(name) = section_name(trimmed) {
+            in_section = name.eq_ignore_ascii_case(SECTION);
+            lines.push(line.to_string());
+            if in_section {
+                section_end = Some(lines.len());
+            }
+            continue;
+        }
+
+        if in_section
+            && !is_comment(trimmed)
+            && let Some((key, _)) = trimmed.split_once('=')
+            && let Some(index) = entries.iter().position(|(name, _)| *name == key.trim())
+        {
+            // Only the first occurrence is read, so drop any later ones
+            if !written[index] {
+                written[index] = true;
+                lines.push(format!("{}={}", entries[index].0, entries[index].1));
+                section_end = Some(lines.len());
+            }
+            continue;
+        }
+
+        lines.push(line.to_string());
+        if in_section && !trimmed.is_empty() {
+            section_end = Some(lines.len());
+        }
+    }
+
+    let missing: Vec<String> = entries
+        .iter()
+        .zip(&written)
+        .filter(|(_, written)| !**written)
+        .map(|((key, value), _)| format!("{}={}", key, value))
+        .collect();
+    match section_end {
+        Some(end) => {
+            lines.splice(end..end, missing);
+        }
+        None => {
+            if lines.last().is_some_and(|line| !line.trim().is_empty()) {
+                lines.push(String::new());
+            }
+            lines.push(format!("[{}]", SECTION));
+            lines.extend(missing);
+        }
+    }
+
+    let mut text = lines.join("\r\n");
+    text.push_str("\r\n");
+    text
+}
+
+fn section_name(line: &str) -> Option<&str> {
+    line.strip_prefix('[')?.strip_suffix(']').map(str::trim)
+}
+
+fn is_comment(line: &str) -> bool {
+    line.starts_with(';') || line.starts_with('#')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::{FontSettings, Settings, ThemeMode, WindowPlacement};
+    use std::path::PathBuf;
+
+    fn entries(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let text = "Before=1\n\
+                    ; comment\n\
+                    [ settings ]\n\
+                    \x20 WordWrap = 0 \n\
+                    # FontSize=20\n\
+                    NoEquals\n\
+                    FontFace=MS Gothic\n\
+                    [Other]\n\
+                    Zoom=200\n";
+        assert_eq!(
+            parse(text),
+            entries(&[("WordWrap", "0"), ("FontFace", "MS Gothic")])
+        );
+        assert_eq!(parse(""), entries(&[]));
+    }
+
+    #[test]
+    fn test_update_keeps_other_lines() {
+        let existing = "; my settings\r\n\
+                        [Settings]\r\n\
+                        Zoom=100\r\n\
+                        FutureOption=yes\r\n\
+                        Zoom=300\r\n\
+                        \r\n\
+                        [Other]\r\n\
+                        Zoom=5\r\n";
+        let updated = update(
+            existing,
+            &[("Zoom", "150".to_string()), ("WordWrap", "0".to_string())],
+        );
+        assert_eq!(
+            updated,
+            "; my settings\r\n\
+             [Settings]\r\n\
+             Zoom=150\r\n\
+             FutureOption=yes\r\n\
+             WordWrap=0\r\n\
+             \r\n\
+             [Other]\r\n\
+             Zoom=5\r\n"
+        );
+    }
+
+    #[test]
+    fn test_update_adds_section() {
+        assert_eq!(
+            update("", &[("Zoom", "150".to_string())]),
+            "[Settings]\r\nZoom=150\r\n"
+        );
+        assert_eq!(
+            update("[Other]\nA=1", &[("Zoom", "150".to_string())]),
+            "[Other]\r\nA=1\r\n\r\n[Settings]\r\nZoom=150\r\n"
+        );
+    }
+
+    #[test]
+    fn test_settings_round_trip() {
+        let settings = Settings {
+            word_wrap: false,
+            status_bar: true,
+            theme: ThemeMode::Light,
+            font: FontSettings {
+                face: "Consolas".to_string(),
+                size: 11.0,
+                weight: 700,
+            },
+            zoom: 80,
+            window: Some(WindowPlacement {
+                x: 100,
+                y: -20,
+                width: 640,
+                height: 480,
+                maximized: false,
+            }),
+            backup_on_save: true,
+            recent_files: vec![
+                PathBuf::from("C:\\Users\\ryu\\Documents\\todo.txt"),
+                PathBuf::from("\\\\server\\share\\a;b#c.txt"),
+            ],
+        };
+
+        for existing in ["", "; comment\n[Settings]\nWordWrap=1\nUnknown=x\n"] {
+            let text = update(existing, &settings.to_entries());
+            let parsed = parse(&text);
+            let loaded = Settings::from_entries(
+                parsed
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), value.as_str())),
+            );
+            assert_eq!(loaded, settings);
+            // Writing the same settings again changes nothing
+            assert_eq!(update(&text, &settings.to_entries()), text);
+        }
+    }
+
+    #[test]
+    fn test_recent_files_shrink() {
+        let mut settings = Settings {
+            recent_files: vec![PathBuf::from("C:\\a.txt"), PathBuf::from("C:\\b.txt")],
+            ..Settings::default()
+        };
+        let text = update("", &settings.to_entries());
+
+        settings.recent_files.truncate(1);
+        let text = update(&text, &settings.to_entries());
+        assert!(text.contains("Recent1=C:\\a.txt\r\nRecent2=\r\n"));
+        let parsed = parse(&text);
+        let loaded = Settings::from_entries(
+            parsed
+                .iter()
+                .map(|(key, value)| (key.as_str(), value.as_str())),
+        );
+        assert_eq!(loaded.recent_files, settings.recent_files);
+    }
+}
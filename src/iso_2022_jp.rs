@@ -0,0 +1,270 @@
+// ISO-2022-JP (JIS) encoder and decoder
+//
+// Follows Microsoft's CP50221: ASCII, JIS X 0201 Roman, JIS X 0208 (with NEC special
+// characters and NEC-selected IBM extensions) and half-width katakana via ESC ( I.
+
+use crate::cp932;
+
+const ESC: u8 = 0x1B;
+
+const ESC_ASCII: &[u8] = b"\x1B(B";
+const ESC_KATAKANA: &[u8] = b"\x1B(I";
+const ESC_JIS_X_0208: &[u8] = b"\x1B$B";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    Ascii,
+    Roman,
+    Katakana,
+    JisX0208,
+}
+
+// Parse an escape sequence at the start of the slice
+fn parse_escape(bytes: &[u8]) -> Option<(Mode, usize)> {
+    match bytes {
+        [ESC, b'(', b'B', ..] => Some((Mode::Ascii, 3)),
+        [ESC, b'(', b'J', ..] => Some((Mode::Roman, 3)),
+        [ESC, b'(', b'I', ..] => Some((Mode::Katakana, 3)),
+        [ESC, b'$', b'@', ..] | [ESC, b'$', b'B', ..] => Some((Mode::JisX0208, 3)),
+        [ESC, b'$', b'(', b'B', ..] => Some((Mode::JisX0208, 4)),
+        _ => None,
+    }
+}
+
+/// Returns true if the bytes contain a JIS X 0208 or katakana designation
+pub fn has_escape_sequences(bytes: &[u8]) -> bool {
+    bytes
+        .iter()
+        .enumerate()
+        .filter(|&(_, &b)| b == ESC)
+        .any(|(i, _)| {
+            matches!(
+                parse_escape(&bytes[i..]),
+                Some((Mode::JisX0208 | Mode::Katakana, _))
+            )
+        })
+}
+
+/// Decode ISO-2022-JP bytes to a string, also returning the byte offset of every
+/// invalid sequence
+///
+/// Invalid sequences and unknown escape sequences are replaced with U+FFFD.
+pub fn decode_with_errors(bytes: &[u8]) -> (String, Vec<usize>) {
+    let mut result = String::with_capacity(bytes.len());
+    let mut errors = Vec::new();
+    let mut mode = Mode::Ascii;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        let byte = bytes[i];
+
+        if byte == ESC {
+            match parse_escape(&bytes[i..]) {
+                Some((new_mode, len)) => {
+                    mode = new_mode;
+                    i += len;
+                }
+                None => {
+                    result.push('\u{FFFD}');
+                    errors.push(i);
+                    i += 1;
+                }
+            }
+            continue;
+        }
+
+        // Line breaks are accepted in any mode
+        if byte == b'\r' || byte == b'\n' {
+            result.push(byte as char);
+            i += 1;
+            continue;
+        }
+
+        if byte >= 0x80 {
+            result.push('\u{FFFD}');
+            errors.push(i);
+            i += 1;
+            continue;
+        }
+
+        match mode {
+            Mode::Ascii => {
+                result.push(byte as char);
+                i += 1;
+            }
+            Mode::Roman => {
+                result.push(match byte {
+                    0x5C => '\u{00A5}',
+                    0x7E => '\u{203E}',
+                    _ => byte as char,
+                });
+                i += 1;
+            }
+            Mode::Katakana => {
+                if (0x21..=0x5F).contains(&byte) {
+                    let code = 0xFF61 + (byte - 0x21) as u32;
+                    result.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+                } else {
+                    result.push('\u{FFFD}');
+                    errors.push(i);
+                }
+                i += 1;
+            }
+            Mode::JisX0208 => {
+                let decoded = bytes
+                    .get(i + 1)
+                    .and_then(|&cell| cp932::jis_to_sjis(byte, cell))
+                    .and_then(|code| cp932::decode_pair((code >> 8) as u8, (code & 0xFF) as u8));
+                match decoded {
+                    Some(code) => {
+                        result.push(char::from_u32(code as u32).unwrap_or('\u{FFFD}'));
+                        i += 2;
+                    }
+                    None => {
+                        result.push('\u{FFFD}');
+                        errors.push(i);
+                        i += if bytes
+                            .get(i + 1)
+                            .is_some_and(|&b| (0x21..=0x7E).contains(&b))
+                        {
+                            2
+                        } else {
+                            1
+                        };
+                    }
+                }
+            }
+        }
+    }
+
+    (result, errors)
+}
+
+/// Returns true if ISO-2022-JP can represent the character
+///
+/// A literal ESC cannot be written because it would start an escape sequence.
+pub fn can_encode(ch: char) -> bool {
+    let code = ch as u32;
+    (code < 0x80 && code != ESC as u32)
+        || (0xFF61..=0xFF9F).contains(&code)
+        || cp932::encode_char_jis_rows(ch)
+            .and_then(cp932::sjis_to_jis)
+            .is_some()
+}
+
+/// Encode a string as ISO-2022-JP
+///
+/// The output always ends in ASCII mode. Characters that cannot be represented are
+/// written as `?`.
+pub fn encode(text: &str) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(text.len());
+    let mut mode = Mode::Ascii;
+
+    let mut switch_to = |bytes: &mut Vec<u8>, new_mode: Mode| {
+        if mode != new_mode {
+            bytes.extend_from_slice(match new_mode {
+                Mode::Katakana => ESC_KATAKANA,
+                Mode::JisX0208 => ESC_JIS_X_0208,
+                _ => ESC_ASCII,
+            });
+            mode = new_mode;
+        }
+    };
+
+    for ch in text.chars() {
+        let code = ch as u32;
+        if code < 0x80 && code != ESC as u32 {
+            switch_to(&mut bytes, Mode::Ascii);
+            bytes.push(code as u8);
+        } else if (0xFF61..=0xFF9F).contains(&code) {
+            switch_to(&mut bytes, Mode::Katakana);
+            bytes.push((code - 0xFF61 + 0x21) as u8);
+        } else if let Some((row, cell)) =
+            cp932::encode_char_jis_rows(ch).and_then(cp932::sjis_to_jis)
+        {
+            switch_to(&mut bytes, Mode::JisX0208);
+            bytes.push(row);
+            bytes.push(cell);
+        } else {
+            switch_to(&mut bytes, Mode::Ascii);
+            bytes.push(cp932::REPLACEMENT_BYTE);
+        }
+    }
+
+    switch_to(&mut bytes, Mode::Ascii);
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8]) -> String {
+        decode_with_errors(bytes).0
+    }
+
+    #[test]
+    fn test_ascii_only() {
+        assert_eq!(encode("hello"), b"hello");
+        assert_eq!(decode(b"hello"), "hello");
+    }
+
+    #[test]
+    fn test_kanji_round_trip() {
+        let encoded = encode("aあ漢b");
+        assert_eq!(encoded, b"a\x1B$B$\"4A\x1B(Bb");
+        assert_eq!(decode(&encoded), "aあ漢b");
+    }
+
+    #[test]
+    fn test_returns_to_ascii_at_end() {
+        assert_eq!(encode("あ"), b"\x1B$B$\"\x1B(B");
+    }
+
+    #[test]
+    fn test_half_width_katakana() {
+        let encoded = encode("ｱ");
+        assert_eq!(encoded, b"\x1B(I1\x1B(B");
+        assert_eq!(decode(&encoded), "ｱ");
+    }
+
+    #[test]
+    fn test_jis_x_0201_roman() {
+        assert_eq!(decode(b"\x1B(J\\~\x1B(B"), "\u{00A5}\u{203E}");
+    }
+
+    #[test]
+    fn test_old_jis_designation() {
+        assert_eq!(decode(b"\x1B$@$\"\x1B(B"), "あ");
+    }
+
+    #[test]
+    fn test_line_breaks_inside_double_byte_mode() {
+        assert_eq!(decode(b"\x1B$B$\"\r\n$\"\x1B(B"), "あ\r\nあ");
+    }
+
+    #[test]
+    fn test_unmappable_characters() {
+        assert_eq!(encode("あ😀"), b"\x1B$B$\"\x1B(B?");
+    }
+
+    #[test]
+    fn test_invalid_sequences() {
+        assert_eq!(decode(b"\x1B%Z"), "\u{FFFD}%Z");
+        assert_eq!(decode(&[0xA4, 0xA2]), "\u{FFFD}\u{FFFD}");
+    }
+
+    #[test]
+    fn test_error_offsets() {
+        let (text, errors) = decode_with_errors(b"a\x1B%Z\x1B$B$\"\xA4");
+        assert_eq!(text, "a\u{FFFD}%Zあ\u{FFFD}");
+        assert_eq!(errors, vec![1, 9]);
+    }
+
+    #[test]
+    fn test_has_escape_sequences() {
+        assert!(has_escape_sequences(b"abc\x1B$Bxyz"));
+        assert!(!has_escape_sequences(b"abc\x1B(Bxyz"));
+        assert!(!has_escape_sequences(b"plain text"));
+    }
+}
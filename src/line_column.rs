@@ -71,6 +71,96 @@ pub fn calculate_line_column(text: &str, cursor_pos: i32) -> (i32, i32) {
     (line, col)
 }
 
+/// Calculate cursor position from line and column number in text
+///
+/// The inverse of `calculate_line_column`, with the same rules: CR, LF and CRLF each end a
+/// line, a surrogate pair is one column and null characters take no column. A column past
+/// the end of the line gives the end of the line.
+///
+/// # Arguments
+/// * `text` - The full text content (as UTF-16 would be)
+/// * `line` - Line number, 1-indexed
+/// * `col` - Column number, 1-indexed
+///
+/// # Returns
+/// Cursor position in UTF-16 code units, or None if the line does not exist
+pub fn calculate_position(text: &str, line: i32, col: i32) -> Option<i32> {
+    if line < 1 {
+        return None;
+    }
+
+    let mut current_line = 1;
+    let mut current_col = 1;
+    let mut utf16_pos = 0;
+    let mut chars_iter = text.chars().peekable();
+
+    while let Some(ch) = chars_iter.next() {
+        if current_line == line && current_col >= col {
+            return Some(utf16_pos);
+        }
+
+        if ch == '\r' || ch == '\n' {
+            // The column is past the end of the requested line
+            if current_line == line {
+                return Some(utf16_pos);
+            }
+            utf16_pos += 1;
+
+            // CRLF is a single line break
+            if ch == '\r' && chars_iter.peek() == Some(&'\n') {
+                chars_iter.next();
+                utf16_pos += 1;
+            }
+
+            current_line += 1;
+            current_col = 1;
+        } else {
+            utf16_pos += ch.len_utf16() as i32;
+            if ch != '\0' {
+                current_col += 1;
+            }
+        }
+    }
+
+    // The requested line is the last one
+    (current_line == line).then_some(utf16_pos)
+}
+
+/// Count the lines in text, where CR, LF and CRLF each end a line
+pub fn count_lines(text: &str) -> i32 {
+    let mut lines = 1;
+    let mut chars_iter = text.chars().peekable();
+    while let Some(ch) = chars_iter.next() {
+        if ch == '\r' && chars_iter.peek() == Some(&'\n') {
+            chars_iter.next();
+        }
+        if ch == '\r' || ch == '\n' {
+            lines += 1;
+        }
+    }
+    lines
+}
+
+/// Parse a Go To input of the form `line` or `line:column`
+///
+/// # Returns
+/// Tuple of (line_number, column_number), or None if the input is not valid.
+/// Both numbers are 1-indexed; the column is None when only a line is given.
+pub fn parse_line_column(input: &str) -> Option<(i32, Option<i32>)> {
+    let parse_number = |number: &str| {
+        let number = number.trim();
+        if number.is_empty() || !number.bytes().all(|byte| byte.is_ascii_digit()) {
+            return None;
+        }
+        number.parse::<i32>().ok().filter(|&number| number >= 1)
+    };
+
+    match input.split_once(':') {
+        Some((line, col)) => Some((parse_number(line)?, Some(parse_number(col)?))),
+        None => Some((parse_number(input)?, None)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -144,4 +234,42 @@ mod tests {
         let (line, col) = calculate_line_column(text, 12);
         assert_eq!((line, col), (2, 6));
     }
+
+    #[test]
+    fn test_position_round_trip() {
+        let text = "line1\r\nあ😀b\nx\ry";
+        for pos in [0, 3, 7, 8, 10, 11, 13, 15] {
+            let (line, col) = calculate_line_column(text, pos);
+            assert_eq!(calculate_position(text, line, col), Some(pos));
+        }
+    }
+
+    #[test]
+    fn test_position_clamps_column() {
+        let text = "ab\r\ncd";
+        assert_eq!(calculate_position(text, 1, 1), Some(0));
+        assert_eq!(calculate_position(text, 1, 99), Some(2));
+        assert_eq!(calculate_position(text, 2, 99), Some(6));
+        assert_eq!(calculate_position(text, 3, 1), None);
+        assert_eq!(calculate_position(text, 0, 1), None);
+        assert_eq!(calculate_position("", 1, 5), Some(0));
+    }
+
+    #[test]
+    fn test_count_lines() {
+        assert_eq!(count_lines(""), 1);
+        assert_eq!(count_lines("a\r\nb\nc\rd"), 4);
+        assert_eq!(count_lines("a\r\n"), 2);
+    }
+
+    #[test]
+    fn test_parse_line_column() {
+        assert_eq!(parse_line_column("120"), Some((120, None)));
+        assert_eq!(parse_line_column(" 120 : 15 "), Some((120, Some(15))));
+        assert_eq!(parse_line_column("0"), None);
+        assert_eq!(parse_line_column("12:"), None);
+        assert_eq!(parse_line_column("-3"), None);
+        assert_eq!(parse_line_column("1:2:3"), None);
+        assert_eq!(parse_line_column("abc"), None);
+    }
 }
@@ -0,0 +1,114 @@
+// Line ending detection and conversion
+//
+// The RichEdit control keeps its own paragraph breaks and hands text back with CRLF,
+// so the file's original style is detected on load and restored on save.
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum LineEnding {
+    Crlf,
+    Lf,
+    Cr,
+    /// More than one style in the same file
+    Mixed,
+}
+
+/// Detect the line ending style of a text
+///
+/// Text without any line breaks is reported as CRLF, the Windows default.
+pub fn detect(text: &str) -> LineEnding {
+    let mut crlf = false;
+    let mut lf = false;
+    let mut cr = false;
+
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\r' if chars.peek() == Some(&'\n') => {
+                chars.next();
+                crlf = true;
+            }
+            '\r' => cr = true,
+            '\n' => lf = true,
+            _ => {}
+        }
+    }
+
+    match (crlf, lf, cr) {
+        (_, false, false) => LineEnding::Crlf,
+        (false, true, false) => LineEnding::Lf,
+        (false, false, true) => LineEnding::Cr,
+        _ => LineEnding::Mixed,
+    }
+}
+
+/// Convert every line break in the text to the given style
+///
+/// `Mixed` cannot be reproduced once the text has been edited, so it converts to CRLF.
+pub fn convert(text: &str, line_ending: LineEnding) -> String {
+    let line_break = match line_ending {
+        LineEnding::Crlf | LineEnding::Mixed => "\r\n",
+        LineEnding::Lf => "\n",
+        LineEnding::Cr => "\r",
+    };
+
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '\r' => {
+                if chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                result.push_str(line_break);
+            }
+            '\n' => result.push_str(line_break),
+            _ => result.push(ch),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_single_styles() {
+        assert_eq!(detect("a\r\nb\r\n"), LineEnding::Crlf);
+        assert_eq!(detect("a\nb\n"), LineEnding::Lf);
+        assert_eq!(detect("a\rb\r"), LineEnding::Cr);
+    }
+
+    #[test]
+    fn test_detect_without_line_breaks() {
+        assert_eq!(detect(""), LineEnding::Crlf);
+        assert_eq!(detect("hello"), LineEnding::Crlf);
+    }
+
+    #[test]
+    fn test_detect_mixed() {
+        assert_eq!(detect("a\r\nb\n"), LineEnding::Mixed);
+        assert_eq!(detect("a\rb\n"), LineEnding::Mixed);
+        assert_eq!(detect("a\r\nb\r"), LineEnding::Mixed);
+    }
+
+    #[test]
+    fn test_detect_lf_cr_is_not_crlf() {
+        assert_eq!(detect("a\n\rb"), LineEnding::Mixed);
+    }
+
+    #[test]
+    fn test_convert() {
+        let text = "a\r\nb\nc\rd";
+        assert_eq!(convert(text, LineEnding::Crlf), "a\r\nb\r\nc\r\nd");
+        assert_eq!(convert(text, LineEnding::Lf), "a\nb\nc\nd");
+        assert_eq!(convert(text, LineEnding::Cr), "a\rb\rc\rd");
+        assert_eq!(convert(text, LineEnding::Mixed), "a\r\nb\r\nc\r\nd");
+    }
+
+    #[test]
+    fn test_convert_keeps_blank_lines() {
+        assert_eq!(convert("a\r\n\r\nb", LineEnding::Lf), "a\n\nb");
+        assert_eq!(convert("\n\n", LineEnding::Crlf), "\r\n\r\n");
+    }
+}
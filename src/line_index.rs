@@ -0,0 +1,438 @@
+// Line index over the editor text
+//
+// Keeps the start and the character count of every line, so the caret's line and the
+// document's character count can be looked up without copying the text out of RichEdit.
+// Offsets are RichEdit positions: UTF-16 code units with each line break counted once.
+// After an edit only the lines it touched are scanned again.
+//
+// Line lengths are kept in a Fenwick tree, so finding a line or its start and applying an
+// edit that keeps the number of lines (typing within a line) take O(log lines). An edit
+// that adds or removes line breaks rebuilds the tree in O(lines), as inserting into a
+// vector of line starts would anyway.
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineIndex {
+    // Units in each line including its line break; the start of a line is the sum of the
+    // lengths before it
+    line_lens: LineLengths,
+    // Characters in each line without its line break, counted like `calculate_line_column`
+    // counts columns: a surrogate pair is one character and null characters are skipped
+    line_chars: Vec<usize>,
+    len: usize,
+    total_chars: usize,
+}
+
+impl LineIndex {
+    /// Index a text. CR, LF and CRLF each end a line.
+    pub fn new(text: &[u16]) -> Self {
+        let (breaks, line_chars) = scan(text);
+        LineIndex {
+            line_lens: LineLengths::new(&piece_lens(&breaks, text.len(), true)),
+            total_chars: line_chars.iter().sum(),
+            line_chars,
+            len: text.len(),
+        }
+    }
+
+    /// Length of the indexed text in UTF-16 code units
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.line_lens.count()
+    }
+
+    /// Characters in the text, with each line break counted as one
+    pub fn char_count(&self) -> usize {
+        self.total_chars + self.line_count() - 1
+    }
+
+    /// The 0-indexed line containing `position`
+    pub fn line_of(&self, position: usize) -> usize {
+        // Lines ending at or before `position` come before it, except that the last line
+        // also holds the end of the text
+        self.line_lens
+            .count_within(position)
+            .min(self.line_count() - 1)
+    }
+
+    pub fn line_start(&self, line: usize) -> usize {
+        self.line_lens.sum(line)
+    }
+
+    /// The range of the edited text that `apply_edit` needs, covering every line touched
+    /// by replacing `removed` units at `start` with `inserted` units
+    pub fn edit_range(&self, start: usize, removed: usize, inserted: usize) -> (usize, usize) {
+        let (first, last) = self.edit_lines(start, removed);
+        (
+            self.line_start(first),
+            self.line_end(last) + inserted - removed,
+        )
+    }
+
+    /// Update the index after `removed` units at `start` were replaced with `inserted`
+    /// units. `text` is the edited text in the range returned by `edit_range`.
+    ///
+    /// Returns false, leaving the index unchanged, when `text` does not fit the edit.
+    pub fn apply_edit(
+        &mut self,
+        start: usize,
+        removed: usize,
+        inserted: usize,
+        text: &[u16],
+    ) -> bool {
+        if start + removed > self.len {
+            return false;
+        }
+        let (first, last) = self.edit_lines(start, removed);
+        let span_start = self.line_start(first);
+        let old_end = self.line_end(last);
+        if span_start + text.len() + removed != old_end + inserted {
+            return false;
+        }
+
+        let (breaks, mut chars) = scan(text);
+        let is_last_line = last + 1 == self.line_count();
+        if !is_last_line {
+            // The text ends with the line break of `last`, so the empty piece after it is
+            // the unchanged start of the following line
+            if breaks.last() != Some(&text.len()) {
+                return false;
+            }
+            chars.pop();
+        }
+
+        self.line_lens
+            .splice(first..=last, piece_lens(&breaks, text.len(), is_last_line));
+
+        let old_chars: usize = self.line_chars[first..=last].iter().sum();
+        self.total_chars = self.total_chars - old_chars + chars.iter().sum::<usize>();
+        self.line_chars.splice(first..=last, chars);
+        self.len = self.len + inserted - removed;
+        true
+    }
+
+    // The first and last lines an edit touches
+    fn edit_lines(&self, start: usize, removed: usize) -> (usize, usize) {
+        let mut first = self.line_of(start);
+        // An edit at a line start can join the CR before it with an LF after it
+        if first > 0 && start == self.line_start(first) {
+            first -= 1;
+        }
+        (first, self.line_of(start + removed))
+    }
+
+    // End of a line, including its line break
+    fn line_end(&self, line: usize) -> usize {
+        self.line_lens.sum(line + 1)
+    }
+}
+
+// Lengths of the pieces of a text of `len` units split after each of `breaks`; the piece
+// after the last break is only wanted when it is a line of its own
+fn piece_lens(breaks: &[usize], len: usize, with_tail: bool) -> Vec<usize> {
+    let mut lens = Vec::with_capacity(breaks.len() + 1);
+    let mut previous = 0;
+    for &offset in breaks {
+        lens.push(offset - previous);
+        previous = offset;
+    }
+    if with_tail {
+        lens.push(len - previous);
+    }
+    lens
+}
+
+// Fenwick tree over line lengths: node `i` (1-based) holds the sum of the `lowest_bit(i)`
+// lengths ending with the `i`th
+#[derive(Debug, Clone, PartialEq)]
+struct LineLengths {
+    tree: Vec<usize>,
+}
+
+impl LineLengths {
+    fn new(lens: &[usize]) -> Self {
+        let mut tree = vec![0; lens.len() + 1];
+        tree[1..].copy_from_slice(lens);
+        for node in 1..tree.len() {
+            let parent = node + lowest_bit(node);
+            if parent < tree.len() {
+                tree[parent] += tree[node];
+            }
+        }
+        LineLengths { tree }
+    }
+
+    fn count(&self) -> usize {
+        self.tree.len() - 1
+    }
+
+    // Sum of the first `count` lengths
+    fn sum(&self, count: usize) -> usize {
+        let mut sum = 0;
+        let mut node = count;
+        while node > 0 {
+            sum += self.tree[node];
+            node -= lowest_bit(node);
+        }
+        sum
+    }
+
+    // How many of the first lengths add up to at most `total`
+    fn count_within(&self, total: usize) -> usize {
+        let mut count = 0;
+        let mut remaining = total;
+        let mut step = self.count().checked_ilog2().map_or(0, |bits| 1 << bits);
+        while step > 0 {
+            let node = count + step;
+            if node <= self.count() && self.tree[node] <= remaining {
+                count = node;
+                remaining -= self.tree[node];
+            }
+            step >>= 1;
+        }
+        count
+    }
+
+    fn get(&self, line: usize) -> usize {
+        self.sum(line + 1) - self.sum(line)
+    }
+
+    fn set(&mut self, line: usize, len: usize) {
+        // A shorter line adds a wrapped negative difference, leaving every node exact
+        let difference = len.wrapping_sub(self.get(line));
+        let mut node = line + 1;
+        while node < self.tree.len() {
+            self.tree[node] = self.tree[node].wrapping_add(difference);
+            node += lowest_bit(node);
+        }
+    }
+
+    // Replace the lengths of `lines`. Only a change in the number of lines rebuilds the tree.
+    fn splice(&mut self, lines: std::ops::RangeInclusive<usize>, lens: Vec<usize>) {
+        let first = *lines.start();
+        if lines.clone().count() == lens.len() {
+            for (offset, len) in lens.into_iter().enumerate() {
+                self.set(first + offset, len);
+            }
+            return;
+        }
+        let mut all: Vec<usize> = (0..self.count()).map(|line| self.get(line)).collect();
+        all.splice(lines, lens);
+        *self = LineLengths::new(&all);
+    }
+}
+
+fn lowest_bit(node: usize) -> usize {
+    node & node.wrapping_neg()
+}
+
+/// Work out the edit that typing made, from the selection and text length before and after
+///
+/// Typing replaces the selection, overwrites what follows the caret (overtype mode), or
+/// deletes before (Backspace) or after (Delete) the caret. Returns `(start, removed,
+/// inserted)`, or None when the change does not look like any of these and the index
+/// should be rebuilt instead.
+pub fn infer_edit(
+    old_selection: (usize, usize),
+    old_len: usize,
+    new_selection: (usize, usize),
+    new_len: usize,
+) -> Option<(usize, usize, usize)> {
+    let (old_start, old_end) = old_selection;
+    let (caret, new_end) = new_selection;
+    if caret != new_end || caret > new_len || old_start > old_end || old_end > old_len {
+        return None;
+    }
+
+    let removed = old_end - old_start;
+    if caret >= old_start && old_len - removed + (caret - old_start) == new_len {
+        // The selection (possibly empty) was replaced with what is now before the caret
+        Some((old_start, removed, caret - old_start))
+    } else if removed == 0 && caret < old_start && old_len - (old_start - caret) == new_len {
+        // Backspace
+        Some((caret, old_start - caret, 0))
+    } else if removed == 0 && caret == old_start && new_len < old_len {
+        // Delete
+        Some((caret, old_len - new_len, 0))
+    } else if removed == 0 && caret > old_start && new_len == old_len {
+        // Overtype: what is now before the caret replaced as many characters after it
+        Some((old_start, caret - old_start, caret - old_start))
+    } else {
+        None
+    }
+}
+
+const CR: u16 = 0x000D;
+const LF: u16 = 0x000A;
+
+// Offsets just after each line break, and the character count of each piece between them
+fn scan(text: &[u16]) -> (Vec<usize>, Vec<usize>) {
+    let mut breaks = Vec::new();
+    let mut line_chars = vec![0];
+    let mut index = 0;
+    while index < text.len() {
+        let unit = text[index];
+        index += 1;
+        if unit == CR || unit == LF {
+            if unit == CR && text.get(index) == Some(&LF) {
+                index += 1;
+            }
+            breaks.push(index);
+            line_chars.push(0);
+            continue;
+        }
+        // The low half of a surrogate pair belongs to the same character
+        if (0xD800..0xDC00).contains(&unit)
+            && text
+                .get(index)
+                .is_some_and(|next| (0xDC00..0xE000).contains(next))
+        {
+            index += 1;
+        }
+        if unit != 0 {
+            *line_chars.last_mut().unwrap() += 1;
+        }
+    }
+    (breaks, line_chars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str) -> Vec<u16> {
+        text.encode_utf16().collect()
+    }
+
+    // Apply an edit to both the text and its index, and compare with a fresh index
+    fn check_edit(text: &str, start: usize, removed: usize, inserted: &str) {
+        let old_text = utf16(text);
+        let inserted = utf16(inserted);
+        let mut new_text = old_text[..start].to_vec();
+        new_text.extend_from_slice(&inserted);
+        new_text.extend_from_slice(&old_text[start + removed..]);
+
+        let mut index = LineIndex::new(&old_text);
+        let (range_start, range_end) = index.edit_range(start, removed, inserted.len());
+        assert!(index.apply_edit(
+            start,
+            removed,
+            inserted.len(),
+            &new_text[range_start..range_end]
+        ));
+        assert_eq!(index, LineIndex::new(&new_text), "{text:?} at {start}");
+    }
+
+    #[test]
+    fn test_new() {
+        let index = LineIndex::new(&utf16("ab\rc😀\0\r\nd\ne"));
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.len(), 12);
+        assert_eq!(index.line_start(2), 9);
+        // ab, c😀, d, e and three line breaks
+        assert_eq!(index.char_count(), 9);
+        assert_eq!(index.line_of(0), 0);
+        assert_eq!(index.line_of(2), 0);
+        assert_eq!(index.line_of(3), 1);
+        assert_eq!(index.line_of(12), 3);
+
+        let empty = LineIndex::new(&[]);
+        assert_eq!((empty.line_count(), empty.char_count()), (1, 0));
+    }
+
+    #[test]
+    fn test_typing() {
+        check_edit("ab\rcd", 1, 0, "x");
+        check_edit("ab\rcd", 1, 0, "\r");
+        check_edit("ab\rcd", 5, 0, "\r\r");
+        check_edit("", 0, 0, "a\rb");
+    }
+
+    #[test]
+    fn test_deleting() {
+        check_edit("ab\rcd", 2, 1, "");
+        check_edit("ab\rcd\ref", 1, 5, "");
+        check_edit("ab\rcd", 0, 5, "");
+        check_edit("ab\r", 2, 1, "");
+    }
+
+    #[test]
+    fn test_replacing_across_lines() {
+        check_edit("ab\rcd\ref\rgh", 1, 6, "X\rY\rZ");
+        check_edit("ab\rcd\ref", 3, 2, "😀");
+    }
+
+    #[test]
+    fn test_replacing_same_length() {
+        // Overtype, typing over one character and Replace of a same-length match
+        check_edit("ab\rcd", 1, 1, "x");
+        check_edit("ab\rcd", 2, 1, "x");
+        check_edit("ab\rcd", 1, 1, "\r");
+        check_edit("ab\rcd\ref", 3, 4, "XY\rZ");
+    }
+
+    #[test]
+    fn test_line_break_pairs() {
+        // Inserting an LF after a CR joins them into one line break
+        check_edit("a\rb", 2, 0, "\n");
+        // Deleting between a CR and an LF joins them too
+        check_edit("a\rX\nb", 2, 1, "");
+        // Deleting the CR of a CRLF leaves the LF as the line break
+        check_edit("a\r\nb", 1, 1, "");
+    }
+
+    #[test]
+    fn test_many_lines() {
+        let text: String = (0..300).map(|line| "x".repeat(line % 7) + "\r\n").collect();
+        let starts: Vec<usize> = std::iter::once(0)
+            .chain(
+                utf16(&text)
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, &unit)| unit == LF)
+                    .map(|(offset, _)| offset + 1),
+            )
+            .collect();
+        let index = LineIndex::new(&utf16(&text));
+        assert_eq!(index.line_count(), starts.len());
+        for position in 0..=index.len() {
+            let line = starts.partition_point(|&start| start <= position) - 1;
+            assert_eq!(index.line_of(position), line, "{position}");
+            assert_eq!(index.line_start(line), starts[line]);
+        }
+
+        // Edits within a line, joining lines and splitting them
+        check_edit(&text, 500, 2, "abc");
+        check_edit(&text, 500, 4, "");
+        check_edit(&text, 500, 3, "a\r\nb\r\n");
+    }
+
+    #[test]
+    fn test_apply_edit_rejects_wrong_text() {
+        let mut index = LineIndex::new(&utf16("ab\rcd"));
+        let before = index.clone();
+        assert!(!index.apply_edit(1, 0, 1, &utf16("axb")));
+        assert!(!index.apply_edit(4, 9, 0, &[]));
+        assert_eq!(index, before);
+    }
+
+    #[test]
+    fn test_infer_edit() {
+        // Typing, with and without a selection
+        assert_eq!(infer_edit((3, 3), 10, (4, 4), 11), Some((3, 0, 1)));
+        assert_eq!(infer_edit((3, 6), 10, (5, 5), 9), Some((3, 3, 2)));
+        // Backspace and Delete
+        assert_eq!(infer_edit((3, 3), 10, (2, 2), 9), Some((2, 1, 0)));
+        assert_eq!(infer_edit((3, 3), 10, (3, 3), 8), Some((3, 2, 0)));
+        // Edits that keep the length: overtype and typing over a selection
+        assert_eq!(infer_edit((3, 3), 10, (4, 4), 10), Some((3, 1, 1)));
+        assert_eq!(infer_edit((3, 4), 10, (4, 4), 10), Some((3, 1, 1)));
+        assert_eq!(infer_edit((3, 6), 10, (6, 6), 10), Some((3, 3, 3)));
+        // Anything else
+        assert_eq!(infer_edit((3, 3), 10, (0, 0), 20), None);
+        assert_eq!(infer_edit((3, 3), 10, (4, 6), 12), None);
+        assert_eq!(infer_edit((8, 8), 10, (8, 8), 2), None);
+    }
+}
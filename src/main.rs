@@ -1,30 +1,55 @@
 #![windows_subsystem = "windows"]
 
+mod cli;
+mod color_theme;
 mod constants;
 mod context_menu;
+mod cp932;
+mod encoding_detect;
+mod euc_jp;
 mod file_io;
+mod find_dialog;
+mod goto_dialog;
 mod i18n;
+mod ini;
+mod iso_2022_jp;
 mod line_column;
+mod line_ending;
+mod line_index;
+mod saved_text;
+mod search;
+mod settings;
+mod settings_store;
 mod status_bar;
 mod theme;
+mod unicode;
+mod unmappable;
 
 use constants::{
     EC_TOPMARGIN, EM_EXLIMITTEXT, EM_GETLANGOPTIONS, EM_GETTEXT, EM_SETLANGOPTIONS,
-    EM_SETPARAFORMAT, EM_SETTARGETDEVICE, EM_SETTEXT, ES_MULTILINE, ICON_BIG, ICON_SMALL,
-    ID_EDIT_COPY, ID_EDIT_CUT, ID_EDIT_DELETE, ID_EDIT_PASTE, ID_EDIT_REDO, ID_EDIT_SELECTALL,
-    ID_EDIT_UNDO, ID_FILE_EXIT, ID_FILE_NEW, ID_FILE_OPEN, ID_FILE_SAVE, ID_FILE_SAVEAS,
-    ID_VIEW_DARKMODE, ID_VIEW_STATUSBAR, ID_VIEW_WORDWRAP, IMF_AUTOFONT, IMF_DUALFONT,
-    OLE_PLACEHOLDER, PFM_LINESPACING, PFM_SPACEAFTER, PFM_SPACEBEFORE,
+    EM_SETPARAFORMAT, EM_SETTARGETDEVICE, EM_SETTEXT, ES_MULTILINE, ES_NOHIDESEL, ICON_BIG,
+    ICON_SMALL, ID_EDIT_COPY, ID_EDIT_CUT, ID_EDIT_DELETE, ID_EDIT_FIND, ID_EDIT_FIND_NEXT,
+    ID_EDIT_FIND_PREV, ID_EDIT_GOTO, ID_EDIT_LINEENDING_CR, ID_EDIT_LINEENDING_CRLF,
+    ID_EDIT_LINEENDING_LF, ID_EDIT_PASTE, ID_EDIT_REDO, ID_EDIT_REPLACE, ID_EDIT_SELECTALL,
+    ID_EDIT_UNDO, ID_ENCODING_REOPEN_FIRST, ID_ENCODING_SAVE_FIRST, ID_FILE_BACKUP, ID_FILE_EXIT,
+    ID_FILE_NEW, ID_FILE_OPEN, ID_FILE_RECENT_FIRST, ID_FILE_SAVE, ID_FILE_SAVEAS, ID_FORMAT_FONT,
+    ID_STATUS_ENCODING, ID_STATUS_LINEENDING, ID_STATUS_POSITION, ID_STATUS_ZOOM,
+    ID_VIEW_STATUSBAR, ID_VIEW_THEME_DARK, ID_VIEW_THEME_LIGHT, ID_VIEW_THEME_SYSTEM,
+    ID_VIEW_THEME_USER_FIRST, ID_VIEW_WORDWRAP, ID_VIEW_ZOOMIN, ID_VIEW_ZOOMOUT, ID_VIEW_ZOOMRESET,
+    IMF_AUTOFONT, IMF_DUALFONT, MAX_USER_THEMES, OLE_PLACEHOLDER, PFM_LINESPACING, PFM_SPACEAFTER,
+    PFM_SPACEBEFORE, ZOOM_MAX, ZOOM_MIN, ZOOM_STEP,
 };
 use context_menu::show_context_menu;
-use file_io::FileEncoding;
+use file_io::{FileEncoding, FileIoError};
+use find_dialog::is_read_only;
 use i18n::{get_string, init_language};
+use line_ending::LineEnding;
+use saved_text::SavedText;
+use settings::{FontSettings, Settings, ThemeMode, WindowPlacement};
 use status_bar::update_status_bar;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use theme::{
-    DARK_EDITOR_BG, DARK_EDITOR_TEXT, DARK_MENU_BG, DARK_MENU_BORDER, DARK_MENU_HOVER,
-    DARK_MENU_TEXT, DARK_MENU_TEXT_DISABLED, DARK_MODE_ENABLED, LIGHT_EDITOR_TEXT, LIGHT_MENU_BG,
     allow_dark_mode_for_window, flush_menu_themes, set_preferred_app_mode, set_window_theme,
     should_use_dark_mode,
 };
@@ -32,37 +57,55 @@ use theme::{
 // Global variables for file state
 static CURRENT_FILE: Mutex<Option<PathBuf>> = Mutex::new(None);
 static LAST_MODIFIED_STATE: Mutex<bool> = Mutex::new(false);
-static SAVED_CONTENT: Mutex<String> = Mutex::new(String::new());
+static SAVED_CONTENT: Mutex<SavedText> = Mutex::new(SavedText::EMPTY);
+// Whether the text differs from SAVED_CONTENT, until the next EN_CHANGE
+static MODIFIED_CACHE: Mutex<Option<bool>> = Mutex::new(None);
 static WORD_WRAP_ENABLED: Mutex<bool> = Mutex::new(true);
 static STATUSBAR_VISIBLE: Mutex<bool> = Mutex::new(true);
+// Editor font from the settings, None for the default
+static EDITOR_FONT: Mutex<Option<FontSettings>> = Mutex::new(None);
+// The HFONT the editor uses, deleted when the font changes
+static EDITOR_HFONT: Mutex<Option<isize>> = Mutex::new(None);
 static MENU_HANDLE: Mutex<Option<isize>> = Mutex::new(None);
+static ENCODING_MENU_HANDLE: Mutex<Option<isize>> = Mutex::new(None);
+static LINEENDING_MENU_HANDLE: Mutex<Option<isize>> = Mutex::new(None);
+static RECENT_MENU_HANDLE: Mutex<Option<isize>> = Mutex::new(None);
+// Recently opened or saved files, most recent first
+static RECENT_FILES: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
 static CURRENT_ENCODING: Mutex<FileEncoding> = Mutex::new(FileEncoding::Utf8);
-static DARK_BRUSH: Mutex<Option<isize>> = Mutex::new(None);
+static CURRENT_LINE_ENDING: Mutex<LineEnding> = Mutex::new(LineEnding::Crlf);
 
 use windows::Win32::Foundation::HINSTANCE;
-use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, LRESULT, RECT, WPARAM};
 use windows::Win32::Graphics::Dwm::{DWMWA_USE_IMMERSIVE_DARK_MODE, DwmSetWindowAttribute};
 use windows::Win32::Graphics::Gdi::{
     CreateFontW, CreateSolidBrush, DeleteObject, FONT_CHARSET, FONT_CLIP_PRECISION,
-    FONT_OUTPUT_PRECISION, FONT_QUALITY, FillRect, HBRUSH, InvalidateRect, SetBkColor,
-    SetTextColor,
+    FONT_OUTPUT_PRECISION, FONT_QUALITY, FillRect, GET_DEVICE_CAPS_INDEX, GetDC, GetDeviceCaps,
+    HBRUSH, HFONT, InvalidateRect, LOGFONTW, ReleaseDC, SetBkColor, SetTextColor,
 };
 use windows::Win32::System::DataExchange::{
     CloseClipboard, GetClipboardData, IsClipboardFormatAvailable, OpenClipboard,
 };
 use windows::Win32::System::LibraryLoader::{GetModuleHandleW, LoadLibraryW};
-use windows::Win32::UI::Controls::{EM_SETMARGINS, EM_SETMODIFY};
+use windows::Win32::UI::Controls::Dialogs::{
+    CF_FORCEFONTEXIST, CF_INITTOLOGFONTSTRUCT, CF_NOVERTFONTS, CF_SCREENFONTS, CHOOSEFONTW,
+    ChooseFontW,
+};
+use windows::Win32::UI::Controls::{EM_GETMODIFY, EM_SETMARGINS, EM_SETMODIFY, NMHDR};
 use windows::Win32::UI::Input::KeyboardAndMouse::GetKeyState;
 use windows::Win32::UI::WindowsAndMessaging::{
-    AppendMenuW, CheckMenuItem, CreateMenu, CreateWindowExW, DefWindowProcW, DestroyWindow,
-    DispatchMessageW, EnableMenuItem, GetClientRect, GetCursorPos, GetMenu, GetMessageW,
-    GetSubMenu, GetWindowLongPtrW, GetWindowRect, HMENU, IDC_ARROW, LoadCursorW, LoadIconW,
-    MENU_ITEM_FLAGS, MSG, PostQuitMessage, RegisterClassW, SET_WINDOW_POS_FLAGS, SHOW_WINDOW_CMD,
-    SendMessageW, SetCursor, SetMenu, SetWindowLongPtrW, SetWindowPos, SetWindowTextW, ShowWindow,
-    TranslateMessage, WINDOW_EX_STYLE, WINDOW_LONG_PTR_INDEX, WINDOW_STYLE, WM_CLOSE, WM_COMMAND,
-    WM_CONTEXTMENU, WM_COPY, WM_CREATE, WM_CUT, WM_DESTROY, WM_GETMINMAXINFO, WM_INITMENUPOPUP,
-    WM_KEYDOWN, WM_NOTIFY, WM_PASTE, WM_SETCURSOR, WM_SETFONT, WM_SETICON, WM_SIZE,
-    WNDCLASS_STYLES, WNDCLASSW,
+    AppendMenuW, CheckMenuItem, CreateMenu, CreateWindowExW, DefWindowProcW, DeleteMenu,
+    DestroyWindow, DispatchMessageW, EnableMenuItem, GetClientRect, GetCursorPos, GetMenu,
+    GetMenuItemCount, GetMessageW, GetSubMenu, GetWindowLongPtrW, GetWindowPlacement,
+    GetWindowRect, HMENU, IDC_ARROW, IDNO, IDYES, IsChild, IsDialogMessageW, LoadCursorW,
+    LoadIconW, MB_ICONERROR, MB_ICONWARNING, MB_OK, MB_YESNOCANCEL, MENU_ITEM_FLAGS,
+    MESSAGEBOX_RESULT, MSG, MessageBoxW, PostQuitMessage, RegisterClassW, SET_WINDOW_POS_FLAGS,
+    SHOW_WINDOW_CMD, SendMessageW, SetCursor, SetMenu, SetWindowLongPtrW, SetWindowPlacement,
+    SetWindowPos, SetWindowTextW, ShowWindow, TRACK_POPUP_MENU_FLAGS, TrackPopupMenu,
+    TranslateMessage, WINDOW_EX_STYLE, WINDOW_LONG_PTR_INDEX, WINDOW_STYLE, WINDOWPLACEMENT,
+    WM_CLOSE, WM_COMMAND, WM_CONTEXTMENU, WM_COPY, WM_CREATE, WM_CUT, WM_DESTROY, WM_GETMINMAXINFO,
+    WM_INITMENUPOPUP, WM_KEYDOWN, WM_NOTIFY, WM_PASTE, WM_QUERYENDSESSION, WM_SETCURSOR,
+    WM_SETFONT, WM_SETICON, WM_SIZE, WNDCLASS_STYLES, WNDCLASSW,
 };
 use windows::core::PCWSTR;
 
@@ -76,12 +119,11 @@ fn is_untitled_file(path: &PathBuf) -> bool {
     false
 }
 
-// Helper function to update title based on modified state
-fn update_title_if_needed(hwnd: HWND, edit_hwnd: HWND) {
+// Helper function to get the editor text (line breaks as CRLF)
+fn get_editor_text(edit_hwnd: HWND) -> String {
     unsafe {
-        // Get current text content
         let text_len = SendMessageW(edit_hwnd, 0x000E, Some(WPARAM(0)), Some(LPARAM(0))).0 as usize; // WM_GETTEXTLENGTH
-        let current_text = if text_len > 0 {
+        if text_len > 0 {
             let mut buffer: Vec<u16> = vec![0; text_len + 1];
             SendMessageW(
                 edit_hwnd,
@@ -92,14 +134,488 @@ fn update_title_if_needed(hwnd: HWND, edit_hwnd: HWND) {
             String::from_utf16(&buffer[..text_len]).unwrap_or_default()
         } else {
             String::new()
-        };
+        }
+    }
+}
+
+// Helper function to check whether the editor text differs from the saved content
+fn is_document_modified(edit_hwnd: HWND) -> bool {
+    unsafe {
+        // The modify flag is cleared whenever the text is saved or loaded
+        if SendMessageW(edit_hwnd, EM_GETMODIFY, Some(WPARAM(0)), Some(LPARAM(0))).0 == 0 {
+            if let Ok(mut cache) = MODIFIED_CACHE.lock() {
+                *cache = None;
+            }
+            return false;
+        }
+    }
+
+    // Edited since then, but possibly back to the saved text
+    if let Ok(cache) = MODIFIED_CACHE.lock()
+        && let Some(modified) = *cache
+    {
+        return modified;
+    }
+    let text_len = unsafe {
+        SendMessageW(edit_hwnd, 0x000E, Some(WPARAM(0)), Some(LPARAM(0))).0 as usize // WM_GETTEXTLENGTH
+    };
+    let modified = if let Ok(saved) = SAVED_CONTENT.lock() {
+        !saved.matches(text_len, || get_editor_text(edit_hwnd))
+    } else {
+        false
+    };
+    if let Ok(mut cache) = MODIFIED_CACHE.lock() {
+        *cache = Some(modified);
+    }
+    modified
+}
+
+// Tell the user why a file could not be opened or saved.
+// `key` is the message for the operation ("ERROR_OPEN_FAILED" or "ERROR_SAVE_FAILED").
+fn show_file_error(hwnd: HWND, key: &str, path: &Path, error: &FileIoError) {
+    let message = format!(
+        "{}\n\n{}\0",
+        get_string(key).replace("{file}", &path.display().to_string()),
+        error
+    );
+    let message_utf16: Vec<u16> = message.encode_utf16().collect();
+    let caption_utf16: Vec<u16> = format!("{}\0", get_string("WINDOW_TITLE"))
+        .encode_utf16()
+        .collect();
+    unsafe {
+        MessageBoxW(
+            Some(hwnd),
+            PCWSTR(message_utf16.as_ptr()),
+            PCWSTR(caption_utf16.as_ptr()),
+            MB_OK | MB_ICONERROR,
+        );
+    }
+}
+
+// Number of unmappable characters listed in the warning before "and N more"
+const UNMAPPABLE_LIST_LIMIT: usize = 10;
+
+// Warn that the text cannot be saved as-is in the encoding.
+// Returns IDYES to save as UTF-8, IDNO to save with replacements, or IDCANCEL.
+fn ask_unmappable(
+    hwnd: HWND,
+    encoding: FileEncoding,
+    chars: &[unmappable::UnmappableChar],
+) -> MESSAGEBOX_RESULT {
+    let mut lines: Vec<String> = chars
+        .iter()
+        .take(UNMAPPABLE_LIST_LIMIT)
+        .map(|unmappable| {
+            get_string("DIALOG_UNMAPPABLE_CHAR")
+                .replace("{line}", &unmappable.line.to_string())
+                .replace("{col}", &unmappable.col.to_string())
+                .replace("{char}", &unmappable.ch.to_string())
+                .replace("{code}", &format!("{:04X}", unmappable.ch as u32))
+        })
+        .collect();
+    if chars.len() > UNMAPPABLE_LIST_LIMIT {
+        lines.push(get_string("DIALOG_UNMAPPABLE_MORE").replace(
+            "{count}",
+            &(chars.len() - UNMAPPABLE_LIST_LIMIT).to_string(),
+        ));
+    }
+
+    let message = format!(
+        "{}\0",
+        get_string("DIALOG_UNMAPPABLE")
+            .replace("{encoding}", &status_bar::encoding_name(encoding))
+            .replace("{chars}", &lines.join("\n"))
+    );
+    let message_utf16: Vec<u16> = message.encode_utf16().collect();
+    let caption_utf16: Vec<u16> = format!("{}\0", get_string("WINDOW_TITLE"))
+        .encode_utf16()
+        .collect();
+    unsafe {
+        MessageBoxW(
+            Some(hwnd),
+            PCWSTR(message_utf16.as_ptr()),
+            PCWSTR(caption_utf16.as_ptr()),
+            MB_YESNOCANCEL | MB_ICONWARNING,
+        )
+    }
+}
+
+// Save the editor text, asking first if the encoding cannot represent some characters.
+// Returns the encoding actually used, or None if the user cancelled or the save failed.
+// Do not hold CURRENT_FILE while calling this: it may show a message box.
+fn save_document(
+    hwnd: HWND,
+    path: &Path,
+    text: &str,
+    encoding: FileEncoding,
+) -> Option<FileEncoding> {
+    let mut encoding = encoding;
+    let mut allow_unmappable = false;
+    loop {
+        match file_io::save_file(
+            path,
+            text,
+            encoding,
+            current_line_ending(),
+            allow_unmappable,
+        ) {
+            Ok(()) => return Some(encoding),
+            Err(FileIoError::Unmappable(chars)) => match ask_unmappable(hwnd, encoding, &chars) {
+                IDYES => encoding = FileEncoding::Utf8,
+                IDNO => allow_unmappable = true,
+                _ => return None,
+            },
+            Err(error) => {
+                show_file_error(hwnd, "ERROR_SAVE_FAILED", path, &error);
+                return None;
+            }
+        }
+    }
+}
+
+// Change the encoding used for saving and show it in the status bar
+fn set_current_encoding(hwnd: HWND, edit_hwnd: HWND, encoding: FileEncoding) {
+    if let Ok(mut current_encoding) = CURRENT_ENCODING.lock() {
+        *current_encoding = encoding;
+    }
+    status_bar::set_encoding_ambiguous(false);
+    unsafe {
+        let char_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(8)) as _);
+        let pos_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(32)) as _);
+        let encoding_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(48)) as _);
+        let zoom_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(64)) as _);
+        update_status_bar(
+            edit_hwnd,
+            char_hwnd,
+            pos_hwnd,
+            encoding_hwnd,
+            zoom_hwnd,
+            encoding,
+        );
+    }
+}
+
+// Number of invalid byte offsets listed in the decoding warning
+const DECODE_ERROR_LIST_LIMIT: usize = 5;
+
+// Warn that part of the file could not be decoded.
+// Returns IDYES to open read-only, IDNO to re-open with another encoding, or IDCANCEL.
+fn ask_decode_errors(hwnd: HWND, path: &Path, loaded: &file_io::LoadedFile) -> MESSAGEBOX_RESULT {
+    let mut offsets: Vec<String> = loaded
+        .decode_errors
+        .iter()
+        .take(DECODE_ERROR_LIST_LIMIT)
+        .map(|offset| format!("0x{:X}", offset))
+        .collect();
+    if loaded.decode_errors.len() > DECODE_ERROR_LIST_LIMIT {
+        offsets.push("...".to_string());
+    }
+
+    let filename = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let message = format!(
+        "{}\0",
+        get_string("DIALOG_DECODE_ERRORS")
+            .replace("{file}", &filename)
+            .replace("{count}", &loaded.decode_errors.len().to_string())
+            .replace("{encoding}", &status_bar::encoding_name(loaded.encoding))
+            .replace("{offsets}", &offsets.join(", "))
+    );
+    let message_utf16: Vec<u16> = message.encode_utf16().collect();
+    let caption_utf16: Vec<u16> = format!("{}\0", get_string("WINDOW_TITLE"))
+        .encode_utf16()
+        .collect();
+    unsafe {
+        MessageBoxW(
+            Some(hwnd),
+            PCWSTR(message_utf16.as_ptr()),
+            PCWSTR(caption_utf16.as_ptr()),
+            MB_YESNOCANCEL | MB_ICONWARNING,
+        )
+    }
+}
+
+// Lock the editor after a lossy load (or unlock it for a new document)
+fn set_read_only(edit_hwnd: HWND, read_only: bool) {
+    const EM_SETREADONLY: u32 = 0x00CF;
+    unsafe {
+        SendMessageW(
+            edit_hwnd,
+            EM_SETREADONLY,
+            Some(WPARAM(read_only as usize)),
+            Some(LPARAM(0)),
+        );
+    }
+}
+
+// Text shown after the file name in the title bar
+fn read_only_suffix(edit_hwnd: HWND) -> String {
+    if is_read_only(edit_hwnd) {
+        format!(" {}", get_string("TITLE_READ_ONLY"))
+    } else {
+        String::new()
+    }
+}
+
+// Load a file into the editor. Shows the error and returns false if the file could not be read
+// or the user chose not to open it.
+fn open_file(hwnd: HWND, edit_hwnd: HWND, path: &PathBuf, encoding: FileEncoding) -> bool {
+    let loaded = match file_io::load_file(path, encoding) {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            show_file_error(hwnd, "ERROR_OPEN_FAILED", path, &error);
+            return false;
+        }
+    };
+
+    // Saving text with U+FFFD would overwrite the original bytes, so let the user choose
+    let mut read_only = false;
+    if !loaded.decode_errors.is_empty() {
+        match ask_decode_errors(hwnd, path, &loaded) {
+            IDYES => read_only = true,
+            IDNO => {
+                return match file_io::open_file_dialog(Some(path)) {
+                    Some((path, encoding)) => open_file(hwnd, edit_hwnd, &path, encoding),
+                    None => false,
+                };
+            }
+            _ => return false,
+        }
+    }
+
+    unsafe {
+        // The editor hands text back with CRLF, so compare against that
+        let content = line_ending::convert(&loaded.content, LineEnding::Crlf);
+        let utf16: Vec<u16> = content.encode_utf16().chain(std::iter::once(0)).collect();
+
+        status_bar::reset_line_index();
+        SendMessageW(
+            edit_hwnd,
+            0x000C,
+            Some(WPARAM(0)),
+            Some(LPARAM(utf16.as_ptr() as isize)),
+        );
+
+        set_read_only(edit_hwnd, read_only);
+
+        if let Ok(mut current_file) = CURRENT_FILE.lock() {
+            *current_file = Some(path.clone());
+        }
+
+        if let Ok(mut current_encoding) = CURRENT_ENCODING.lock() {
+            *current_encoding = loaded.encoding;
+        }
+        status_bar::set_encoding_ambiguous(
+            loaded
+                .detection
+                .is_some_and(|detection| detection.ambiguous),
+        );
+        set_line_ending(hwnd, loaded.line_ending);
+
+        if let Ok(mut saved) = SAVED_CONTENT.lock() {
+            *saved = SavedText::new(&content);
+        }
+
+        SendMessageW(edit_hwnd, EM_SETMODIFY, Some(WPARAM(0)), Some(LPARAM(0)));
+
+        // Clear undo buffer
+        const EM_EMPTYUNDOBUFFER: u32 = 0x00CD;
+        SendMessageW(
+            edit_hwnd,
+            EM_EMPTYUNDOBUFFER,
+            Some(WPARAM(0)),
+            Some(LPARAM(0)),
+        );
+
+        if let Some(filename) = path.file_name() {
+            if let Some(filename_str) = filename.to_str() {
+                let app_name = get_string("WINDOW_TITLE");
+                let title = format!(
+                    "{}{} - {}\0",
+                    filename_str,
+                    read_only_suffix(edit_hwnd),
+                    app_name
+                );
+                let title_utf16: Vec<u16> = title.encode_utf16().collect();
+                let _ = SetWindowTextW(hwnd, PCWSTR(title_utf16.as_ptr()));
+            }
+        }
+
+        let char_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(8)) as _);
+        let pos_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(32)) as _);
+        let encoding_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(48)) as _);
+        let zoom_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(64)) as _);
+        update_status_bar(
+            edit_hwnd,
+            char_hwnd,
+            pos_hwnd,
+            encoding_hwnd,
+            zoom_hwnd,
+            loaded.encoding,
+        );
+    }
+    add_recent_file(path);
+    true
+}
+
+// Open the file and move the caret as requested on the command line
+fn apply_command_line(hwnd: HWND, command_line: &cli::CommandLine) {
+    let edit_hwnd = unsafe { HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(0)) as _) };
+
+    if command_line.encoding != FileEncoding::Auto
+        && let Ok(mut current_encoding) = CURRENT_ENCODING.lock()
+    {
+        *current_encoding = command_line.encoding;
+    }
 
-        // Compare with saved content
-        let is_modified = if let Ok(saved) = SAVED_CONTENT.lock() {
-            *saved != current_text
+    if let Some(path) = &command_line.path {
+        // Save later writes to this path, so do not depend on the working directory
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.clone());
+
+        if command_line.new_file {
+            if let Ok(mut current_file) = CURRENT_FILE.lock() {
+                *current_file = Some(path.clone());
+            }
+            if let Some(filename_str) = path.file_name().and_then(|name| name.to_str()) {
+                let app_name = get_string("WINDOW_TITLE");
+                let title = format!("{} - {}\0", filename_str, app_name);
+                let title_utf16: Vec<u16> = title.encode_utf16().collect();
+                unsafe {
+                    let _ = SetWindowTextW(hwnd, PCWSTR(title_utf16.as_ptr()));
+                }
+            }
+        } else if !open_file(hwnd, edit_hwnd, &path, command_line.encoding) {
+            return;
+        }
+    }
+
+    if let Some(line) = command_line.line {
+        let col = command_line.col.unwrap_or(1);
+        goto_dialog::move_caret_to(
+            edit_hwnd,
+            i32::try_from(line).unwrap_or(i32::MAX),
+            i32::try_from(col).unwrap_or(i32::MAX),
+        );
+    }
+}
+
+// Report a command-line error
+fn show_command_line_error(hwnd: HWND, error: &cli::CliError) {
+    let message = match error {
+        cli::CliError::UnknownOption(option) => {
+            get_string("CLI_UNKNOWN_OPTION").replace("{option}", option)
+        }
+        cli::CliError::MissingValue(option) => {
+            get_string("CLI_MISSING_VALUE").replace("{option}", option)
+        }
+        cli::CliError::InvalidEncoding(encoding) => {
+            get_string("CLI_INVALID_ENCODING").replace("{encoding}", encoding)
+        }
+        cli::CliError::InvalidNumber { option, value } => get_string("CLI_INVALID_NUMBER")
+            .replace("{option}", option)
+            .replace("{value}", value),
+        cli::CliError::TooManyPaths => get_string("CLI_TOO_MANY_PATHS"),
+    };
+    let message_utf16: Vec<u16> = format!("{}\0", message).encode_utf16().collect();
+    let caption_utf16: Vec<u16> = format!("{}\0", get_string("WINDOW_TITLE"))
+        .encode_utf16()
+        .collect();
+    unsafe {
+        MessageBoxW(
+            Some(hwnd),
+            PCWSTR(message_utf16.as_ptr()),
+            PCWSTR(caption_utf16.as_ptr()),
+            MB_OK | MB_ICONWARNING,
+        );
+    }
+}
+
+// Ask whether to save unsaved changes before they are discarded.
+// Returns false if the user cancelled (or the save did not happen).
+fn confirm_save_changes(hwnd: HWND, edit_hwnd: HWND) -> bool {
+    if !is_document_modified(edit_hwnd) {
+        return true;
+    }
+
+    let filename = if let Ok(current_file) = CURRENT_FILE.lock() {
+        current_file
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .and_then(|name| name.to_str())
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| get_string("FILE_UNTITLED"))
+    } else {
+        get_string("FILE_UNTITLED")
+    };
+
+    let message = format!(
+        "{}\0",
+        get_string("DIALOG_SAVE_CHANGES").replace("{file}", &filename)
+    );
+    let message_utf16: Vec<u16> = message.encode_utf16().collect();
+    let caption = format!("{}\0", get_string("WINDOW_TITLE"));
+    let caption_utf16: Vec<u16> = caption.encode_utf16().collect();
+
+    unsafe {
+        let result = MessageBoxW(
+            Some(hwnd),
+            PCWSTR(message_utf16.as_ptr()),
+            PCWSTR(caption_utf16.as_ptr()),
+            MB_YESNOCANCEL | MB_ICONWARNING,
+        );
+
+        if result == IDYES {
+            // Reuse the Save command, which asks for a file name if needed
+            SendMessageW(
+                hwnd,
+                WM_COMMAND,
+                Some(WPARAM(ID_FILE_SAVE as usize)),
+                Some(LPARAM(0)),
+            );
+            !is_document_modified(edit_hwnd)
         } else {
-            false
-        };
+            result == IDNO
+        }
+    }
+}
+
+// Refresh the status bar and the modified marker in the title after each message
+fn update_status_and_title(hwnd: HWND) {
+    unsafe {
+        let edit_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(0)) as _);
+
+        if let Ok(visible) = STATUSBAR_VISIBLE.lock() {
+            if *visible {
+                let char_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(8)) as _);
+                let pos_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(32)) as _);
+                let encoding_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(48)) as _);
+                let zoom_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(64)) as _);
+                let current_encoding = if let Ok(enc) = CURRENT_ENCODING.lock() {
+                    *enc
+                } else {
+                    FileEncoding::Utf8
+                };
+                status_bar::update_status_bar(
+                    edit_hwnd,
+                    char_hwnd,
+                    pos_hwnd,
+                    encoding_hwnd,
+                    zoom_hwnd,
+                    current_encoding,
+                );
+            }
+        }
+
+        update_title_if_needed(hwnd, edit_hwnd);
+    }
+}
+
+// Helper function to update title based on modified state
+fn update_title_if_needed(hwnd: HWND, edit_hwnd: HWND) {
+    unsafe {
+        let is_modified = is_document_modified(edit_hwnd);
 
         // Check if state changed
         if let Ok(mut last_state) = LAST_MODIFIED_STATE.lock() {
@@ -118,7 +634,12 @@ fn update_title_if_needed(hwnd: HWND, edit_hwnd: HWND) {
                         let title = if is_modified {
                             format!("*{} - {}\0", filename_str, app_name)
                         } else {
-                            format!("{} - {}\0", filename_str, app_name)
+                            format!(
+                                "{}{} - {}\0",
+                                filename_str,
+                                read_only_suffix(edit_hwnd),
+                                app_name
+                            )
                         };
                         let title_utf16: Vec<u16> = title.encode_utf16().collect();
                         let _ = SetWindowTextW(hwnd, PCWSTR(title_utf16.as_ptr()));
@@ -131,7 +652,7 @@ fn update_title_if_needed(hwnd: HWND, edit_hwnd: HWND) {
 
 // Draw the annoying 1-pixel white line below menu bar (Unity method)
 unsafe fn draw_menu_nc_bottom_line(hwnd: HWND) {
-    if !should_use_dark_mode() {
+    if theme::uses_system_colors() {
         return;
     }
 
@@ -163,7 +684,7 @@ unsafe fn draw_menu_nc_bottom_line(hwnd: HWND) {
 
         // Draw using window DC
         let hdc = GetWindowDC(Some(hwnd));
-        let brush = CreateSolidBrush(DARK_MENU_BORDER);
+        let brush = CreateSolidBrush(COLORREF(theme::active_theme().menu_border));
         FillRect(hdc, &rc_line, brush);
         let _ = DeleteObject(brush.into());
         let _ = ReleaseDC(Some(hwnd), hdc);
@@ -209,12 +730,15 @@ fn remove_ole_objects(edit_hwnd: HWND) {
         // If OLE objects were found and removed, update the text
         if has_ole {
             let new_text: Vec<u16> = buffer.iter().copied().chain(std::iter::once(0)).collect();
+            status_bar::reset_line_index();
             SendMessageW(
                 edit_hwnd,
                 EM_SETTEXT,
                 Some(WPARAM(0)),
                 Some(LPARAM(new_text.as_ptr() as isize)),
             );
+            // Setting the text clears the modify flag; let the saved text decide instead
+            SendMessageW(edit_hwnd, EM_SETMODIFY, Some(WPARAM(1)), Some(LPARAM(0)));
         }
     }
 }
@@ -273,6 +797,671 @@ fn update_wordwrap_menu_check() {
     }
 }
 
+// Helper function to get the line ending used when saving
+fn current_line_ending() -> LineEnding {
+    if let Ok(line_ending) = CURRENT_LINE_ENDING.lock() {
+        *line_ending
+    } else {
+        LineEnding::Crlf
+    }
+}
+
+// Helper function to change the line ending and update the status bar and menu
+fn set_line_ending(hwnd: HWND, line_ending: LineEnding) {
+    if let Ok(mut current) = CURRENT_LINE_ENDING.lock() {
+        *current = line_ending;
+    }
+
+    unsafe {
+        let linebreak_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(80)) as _);
+        status_bar::set_line_ending_text(linebreak_hwnd, line_ending);
+    }
+
+    if let Ok(menu_handle) = MENU_HANDLE.lock()
+        && let Some(hmenu_isize) = *menu_handle
+    {
+        for (id, item_line_ending) in [
+            (ID_EDIT_LINEENDING_CRLF, LineEnding::Crlf),
+            (ID_EDIT_LINEENDING_LF, LineEnding::Lf),
+            (ID_EDIT_LINEENDING_CR, LineEnding::Cr),
+        ] {
+            let check_state = if item_line_ending == line_ending {
+                MENU_ITEM_FLAGS(0x00000008) // MF_CHECKED
+            } else {
+                MENU_ITEM_FLAGS(0x00000000) // MF_UNCHECKED
+            };
+            unsafe {
+                let _ = CheckMenuItem(
+                    HMENU(hmenu_isize as *mut core::ffi::c_void),
+                    id as u32,
+                    check_state.0,
+                );
+            }
+        }
+    }
+}
+
+// Show a menu bar submenu just above a clicked status bar segment
+fn show_status_menu(hwnd: HWND, menu_handle: &Mutex<Option<isize>>, segment_hwnd: HWND) {
+    let Some(hmenu_isize) = menu_handle.lock().ok().and_then(|handle| *handle) else {
+        return;
+    };
+    unsafe {
+        let mut rect = RECT::default();
+        let _ = GetWindowRect(segment_hwnd, &mut rect);
+        let _ = TrackPopupMenu(
+            HMENU(hmenu_isize as *mut core::ffi::c_void),
+            TRACK_POPUP_MENU_FLAGS(0x0020), // TPM_BOTTOMALIGN
+            rect.left,
+            rect.top,
+            Some(0),
+            hwnd,
+            None,
+        );
+    }
+}
+
+// Helper function to create the editor font from the font settings
+fn create_editor_font(font: &FontSettings) -> HFONT {
+    unsafe {
+        let face: Vec<u16> = font.face.encode_utf16().chain(Some(0)).collect();
+        CreateFontW(
+            font_height(font.size),   // cHeight
+            0,                        // cWidth
+            0,                        // cEscapement
+            0,                        // cOrientation
+            font.weight as i32,       // cWeight
+            0,                        // bItalic
+            0,                        // bUnderline
+            0,                        // bStrikeOut
+            FONT_CHARSET(0),          // iCharSet
+            FONT_OUTPUT_PRECISION(0), // iOutPrecision
+            FONT_CLIP_PRECISION(0),   // iClipPrecision
+            FONT_QUALITY(0),          // iQuality
+            0,                        // iPitchAndFamily
+            PCWSTR(face.as_ptr()),
+        )
+    }
+}
+
+// Helper function to convert a point size to a font height in pixels.
+// The height is negative so that it selects by character height, like the Font dialog.
+fn font_height(size: f32) -> i32 {
+    unsafe {
+        let hdc = GetDC(None);
+        let dpi = GetDeviceCaps(Some(hdc), GET_DEVICE_CAPS_INDEX(90)); // LOGPIXELSY
+        ReleaseDC(None, hdc);
+        -(size * dpi as f32 / 72.0).round() as i32
+    }
+}
+
+// Helper function to switch the editor to a new font. The zoom stays the same, so it now
+// scales the new font size.
+fn set_editor_font(edit_hwnd: HWND, font: &FontSettings) {
+    unsafe {
+        let mut numerator: i32 = 0;
+        let mut denominator: i32 = 0;
+        SendMessageW(
+            edit_hwnd,
+            status_bar::EM_GETZOOM,
+            Some(WPARAM(&mut numerator as *mut i32 as usize)),
+            Some(LPARAM(&mut denominator as *mut i32 as isize)),
+        );
+
+        let hfont = create_editor_font(font);
+        SendMessageW(
+            edit_hwnd,
+            WM_SETFONT,
+            Some(WPARAM(hfont.0 as usize)),
+            Some(LPARAM(1)),
+        );
+        if let Ok(mut editor_hfont) = EDITOR_HFONT.lock()
+            && let Some(old_font) = editor_hfont.replace(hfont.0 as isize)
+        {
+            let _ = DeleteObject(HFONT(old_font as _).into());
+        }
+
+        // Keep RichEdit from swapping in other fonts for characters the font lacks
+        let lang_options = SendMessageW(
+            edit_hwnd,
+            EM_GETLANGOPTIONS,
+            Some(WPARAM(0)),
+            Some(LPARAM(0)),
+        );
+        SendMessageW(
+            edit_hwnd,
+            EM_SETLANGOPTIONS,
+            Some(WPARAM(0)),
+            Some(LPARAM(
+                lang_options.0 & !(IMF_AUTOFONT | IMF_DUALFONT) as isize,
+            )),
+        );
+
+        const EM_SETZOOM: u32 = 0x04E1;
+        SendMessageW(
+            edit_hwnd,
+            EM_SETZOOM,
+            Some(WPARAM(numerator as usize)),
+            Some(LPARAM(denominator as isize)),
+        );
+    }
+
+    if let Ok(mut editor_font) = EDITOR_FONT.lock() {
+        *editor_font = Some(font.clone());
+    }
+}
+
+// Helper function to let the user pick the editor font with the Font dialog
+fn choose_editor_font(hwnd: HWND, edit_hwnd: HWND) {
+    let current = EDITOR_FONT
+        .lock()
+        .ok()
+        .and_then(|font| font.clone())
+        .unwrap_or_default();
+
+    let mut log_font = LOGFONTW {
+        lfHeight: font_height(current.size),
+        lfWeight: current.weight as i32,
+        ..Default::default()
+    };
+    for (dest, src) in log_font
+        .lfFaceName
+        .iter_mut()
+        .zip(current.face.encode_utf16().take(31))
+    {
+        *dest = src;
+    }
+
+    let mut choose_font = CHOOSEFONTW {
+        lStructSize: std::mem::size_of::<CHOOSEFONTW>() as u32,
+        hwndOwner: hwnd,
+        lpLogFont: &mut log_font,
+        Flags: CF_SCREENFONTS | CF_INITTOLOGFONTSTRUCT | CF_FORCEFONTEXIST | CF_NOVERTFONTS,
+        ..Default::default()
+    };
+    if !unsafe { ChooseFontW(&mut choose_font) }.as_bool() {
+        return;
+    }
+
+    let face_len = log_font
+        .lfFaceName
+        .iter()
+        .position(|&ch| ch == 0)
+        .unwrap_or(log_font.lfFaceName.len());
+    let font = FontSettings {
+        face: String::from_utf16_lossy(&log_font.lfFaceName[..face_len]),
+        // iPointSize is in tenths of a point
+        size: (choose_font.iPointSize.max(10) as f32) / 10.0,
+        weight: if log_font.lfWeight > 0 {
+            log_font.lfWeight as u32
+        } else {
+            400
+        },
+    };
+    set_editor_font(edit_hwnd, &font);
+}
+
+// Helper function to collect the current settings so they can be saved on exit
+fn current_settings(hwnd: HWND) -> Settings {
+    unsafe {
+        let edit_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(0)) as _);
+        let zoom = current_zoom(edit_hwnd);
+
+        let mut placement = WINDOWPLACEMENT {
+            length: std::mem::size_of::<WINDOWPLACEMENT>() as u32,
+            ..Default::default()
+        };
+        let window = GetWindowPlacement(hwnd, &mut placement).ok().map(|_| {
+            let rect = placement.rcNormalPosition;
+            const SW_SHOWMINIMIZED: u32 = 2;
+            const SW_SHOWMAXIMIZED: u32 = 3;
+            const WPF_RESTORETOMAXIMIZED: u32 = 0x0002;
+            WindowPlacement {
+                x: rect.left,
+                y: rect.top,
+                width: rect.right - rect.left,
+                height: rect.bottom - rect.top,
+                // A minimized window remembers whether it was maximized before
+                maximized: placement.showCmd == SW_SHOWMAXIMIZED
+                    || (placement.showCmd == SW_SHOWMINIMIZED
+                        && placement.flags.0 & WPF_RESTORETOMAXIMIZED != 0),
+            }
+        });
+
+        Settings {
+            word_wrap: WORD_WRAP_ENABLED.lock().map(|wrap| *wrap).unwrap_or(true),
+            status_bar: STATUSBAR_VISIBLE
+                .lock()
+                .map(|visible| *visible)
+                .unwrap_or(true),
+            theme: theme::theme_mode(),
+            font: EDITOR_FONT
+                .lock()
+                .ok()
+                .and_then(|font| font.clone())
+                .unwrap_or_default(),
+            zoom,
+            window,
+            backup_on_save: file_io::backup_on_save(),
+            recent_files: RECENT_FILES
+                .lock()
+                .map(|recent_files| recent_files.clone())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+// Helper function to apply the settings that need the main window to exist
+fn apply_window_settings(hwnd: HWND, settings: &Settings) {
+    unsafe {
+        let edit_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(0)) as _);
+
+        // Word wrap and the status bar start on, so toggle them off through their commands
+        if !settings.word_wrap {
+            toggle_word_wrap(edit_hwnd);
+        }
+        if !settings.status_bar {
+            SendMessageW(
+                hwnd,
+                WM_COMMAND,
+                Some(WPARAM(ID_VIEW_STATUSBAR as usize)),
+                Some(LPARAM(0)),
+            );
+        }
+
+        if settings.zoom != 100 {
+            set_zoom(hwnd, edit_hwnd, settings.zoom);
+        }
+
+        if let Some(window) = settings.window {
+            const SW_SHOWNORMAL: u32 = 1;
+            const SW_SHOWMAXIMIZED: u32 = 3;
+            // SetWindowPlacement moves the window back on screen if the monitor is gone
+            let placement = WINDOWPLACEMENT {
+                length: std::mem::size_of::<WINDOWPLACEMENT>() as u32,
+                showCmd: if window.maximized {
+                    SW_SHOWMAXIMIZED
+                } else {
+                    SW_SHOWNORMAL
+                },
+                rcNormalPosition: RECT {
+                    left: window.x,
+                    top: window.y,
+                    right: window.x + window.width,
+                    bottom: window.y + window.height,
+                },
+                ..Default::default()
+            };
+            let _ = SetWindowPlacement(hwnd, &placement);
+        }
+    }
+}
+
+// Helper function to get the editor zoom in percent
+fn current_zoom(edit_hwnd: HWND) -> u32 {
+    let mut numerator: i32 = 0;
+    let mut denominator: i32 = 0;
+    unsafe {
+        SendMessageW(
+            edit_hwnd,
+            status_bar::EM_GETZOOM,
+            Some(WPARAM(&mut numerator as *mut i32 as usize)),
+            Some(LPARAM(&mut denominator as *mut i32 as isize)),
+        );
+    }
+    if numerator > 0 && denominator > 0 {
+        (numerator * 100 / denominator) as u32
+    } else {
+        100
+    }
+}
+
+// Helper function to zoom in or out by one step. A zoom set with Ctrl+wheel may be off the
+// step grid, so it moves to the next step in that direction.
+fn step_zoom(hwnd: HWND, edit_hwnd: HWND, zoom_in: bool) {
+    let zoom = current_zoom(edit_hwnd);
+    let next = if zoom_in {
+        (zoom / ZOOM_STEP + 1) * ZOOM_STEP
+    } else {
+        zoom.saturating_sub(1) / ZOOM_STEP * ZOOM_STEP
+    };
+    set_zoom(hwnd, edit_hwnd, next);
+}
+
+// Helper function to set the editor zoom in percent, kept within ZOOM_MIN and ZOOM_MAX, and
+// show it in the status bar
+fn set_zoom(hwnd: HWND, edit_hwnd: HWND, zoom: u32) {
+    const EM_SETZOOM: u32 = 0x04E1;
+    let zoom = zoom.clamp(ZOOM_MIN, ZOOM_MAX);
+    unsafe {
+        if zoom == 100 {
+            // A zero numerator and denominator turn zooming off
+            SendMessageW(edit_hwnd, EM_SETZOOM, Some(WPARAM(0)), Some(LPARAM(0)));
+        } else {
+            SendMessageW(
+                edit_hwnd,
+                EM_SETZOOM,
+                Some(WPARAM(zoom as usize)),
+                Some(LPARAM(100)),
+            );
+        }
+
+        let char_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(8)) as _);
+        let pos_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(32)) as _);
+        let encoding_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(48)) as _);
+        let zoom_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(64)) as _);
+        let current_encoding = if let Ok(enc) = CURRENT_ENCODING.lock() {
+            *enc
+        } else {
+            FileEncoding::Utf8
+        };
+        update_status_bar(
+            edit_hwnd,
+            char_hwnd,
+            pos_hwnd,
+            encoding_hwnd,
+            zoom_hwnd,
+            current_encoding,
+        );
+    }
+}
+
+// Encodings in Encoding menu order. Menu IDs count up from ID_ENCODING_REOPEN_FIRST and
+// ID_ENCODING_SAVE_FIRST in this order; the save submenu leaves out Auto.
+const MENU_ENCODINGS: [FileEncoding; 10] = [
+    FileEncoding::Auto,
+    FileEncoding::ShiftJis,
+    FileEncoding::EucJp,
+    FileEncoding::Iso2022Jp,
+    FileEncoding::Utf16Le,
+    FileEncoding::Utf16Be,
+    FileEncoding::Utf32Le,
+    FileEncoding::Utf32Be,
+    FileEncoding::Utf8,
+    FileEncoding::Utf8Bom,
+];
+
+// Map an Encoding menu command to its encoding
+fn menu_encoding(first_id: i32, cmd_id: i32) -> Option<FileEncoding> {
+    let index = usize::try_from(cmd_id.checked_sub(first_id)?).ok()?;
+    MENU_ENCODINGS.get(index).copied()
+}
+
+// Read the current file from disk again, decoding it with another encoding
+fn reopen_with_encoding(hwnd: HWND, edit_hwnd: HWND, encoding: FileEncoding) {
+    let path = CURRENT_FILE.lock().ok().and_then(|file| file.clone());
+    let Some(path) = path.filter(|path| !is_untitled_file(path)) else {
+        return;
+    };
+    if !confirm_save_changes(hwnd, edit_hwnd) {
+        return;
+    }
+    open_file(hwnd, edit_hwnd, &path, encoding);
+}
+
+// Themes in Theme menu order: the built-in ones, then the user themes, whose menu IDs count
+// up from ID_VIEW_THEME_USER_FIRST
+fn menu_themes() -> Vec<(i32, ThemeMode)> {
+    let mut themes = vec![
+        (ID_VIEW_THEME_SYSTEM, ThemeMode::System),
+        (ID_VIEW_THEME_LIGHT, ThemeMode::Light),
+        (ID_VIEW_THEME_DARK, ThemeMode::Dark),
+    ];
+    let user_themes = theme::user_themes().iter().take(MAX_USER_THEMES);
+    themes.extend(user_themes.enumerate().map(|(index, name)| {
+        (
+            ID_VIEW_THEME_USER_FIRST + index as i32,
+            ThemeMode::User(name.clone()),
+        )
+    }));
+    themes
+}
+
+// Helper function to repaint the window, menus, editor, status bar and Find dialog in the
+// active theme's colors
+fn apply_theme(hwnd: HWND) {
+    unsafe {
+        let edit_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(0)) as _);
+        let use_dark = should_use_dark_mode();
+
+        // Update title bar
+        let dark_mode_value: i32 = if use_dark { 1 } else { 0 };
+        let _ = DwmSetWindowAttribute(
+            hwnd,
+            DWMWA_USE_IMMERSIVE_DARK_MODE,
+            &dark_mode_value as *const _ as *const _,
+            std::mem::size_of::<i32>() as u32,
+        );
+
+        // Update menu dark mode
+        set_preferred_app_mode(if use_dark { 1 } else { 0 });
+        allow_dark_mode_for_window(hwnd, use_dark);
+        flush_menu_themes();
+
+        // Update RichEdit colors
+        const EM_SETBKGNDCOLOR: u32 = 0x0443;
+        const EM_SETCHARFORMAT: u32 = 0x0444;
+        const SCF_ALL: usize = 0x0004;
+        const CFM_COLOR: u32 = 0x40000000;
+
+        #[repr(C)]
+        #[allow(non_snake_case)]
+        struct CHARFORMAT2W {
+            cbsize: u32,
+            dwmask: u32,
+            dweffects: u32,
+            yheight: i32,
+            yoffset: i32,
+            crtext: windows::Win32::Foundation::COLORREF,
+            bcharset: u8,
+            bpitchandfamily: u8,
+            szfacename: [u16; 32],
+            wweight: u16,
+            sSpacing: i16,
+            crbackcolor: windows::Win32::Foundation::COLORREF,
+            lcid: u32,
+            dwcookie: u32,
+            sstyle: i16,
+            wkerning: u16,
+            bunits: u8,
+            bAnimation: u8,
+            bRevAuthor: u8,
+            bUnderlineType: u8,
+        }
+
+        let theme = theme::active_theme();
+        if theme::uses_system_colors() {
+            SendMessageW(
+                edit_hwnd,
+                EM_SETBKGNDCOLOR,
+                Some(WPARAM(1)), // Use system color
+                Some(LPARAM(0)),
+            );
+        } else {
+            SendMessageW(
+                edit_hwnd,
+                EM_SETBKGNDCOLOR,
+                Some(WPARAM(0)),
+                Some(LPARAM(theme.editor_bg as isize)),
+            );
+        }
+
+        let mut cf = CHARFORMAT2W {
+            cbsize: std::mem::size_of::<CHARFORMAT2W>() as u32,
+            dwmask: CFM_COLOR,
+            crtext: COLORREF(theme.editor_text),
+            ..std::mem::zeroed()
+        };
+
+        SendMessageW(
+            edit_hwnd,
+            EM_SETCHARFORMAT,
+            Some(WPARAM(SCF_ALL)),
+            Some(LPARAM(&mut cf as *mut _ as isize)),
+        );
+
+        // Redraw status bar and menu bar
+        use windows::Win32::UI::WindowsAndMessaging::DrawMenuBar;
+        let _ = DrawMenuBar(hwnd);
+        let _ = InvalidateRect(Some(hwnd), None, true);
+
+        // Redraw the menu bottom line
+        draw_menu_nc_bottom_line(hwnd);
+
+        // Update status bar components
+        let char_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(8)) as _);
+        let sep1_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(24)) as _);
+        let pos_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(32)) as _);
+        let sep2_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(40)) as _);
+        let encoding_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(48)) as _);
+        let sep3_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(56)) as _);
+        let zoom_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(64)) as _);
+        let sep4_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(72)) as _);
+        let linebreak_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(80)) as _);
+        let separator_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(16)) as _);
+
+        let _ = InvalidateRect(Some(char_hwnd), None, true);
+        let _ = InvalidateRect(Some(sep1_hwnd), None, true);
+        let _ = InvalidateRect(Some(pos_hwnd), None, true);
+        let _ = InvalidateRect(Some(sep2_hwnd), None, true);
+        let _ = InvalidateRect(Some(encoding_hwnd), None, true);
+        let _ = InvalidateRect(Some(sep3_hwnd), None, true);
+        let _ = InvalidateRect(Some(zoom_hwnd), None, true);
+        let _ = InvalidateRect(Some(sep4_hwnd), None, true);
+        let _ = InvalidateRect(Some(linebreak_hwnd), None, true);
+        let _ = InvalidateRect(Some(separator_hwnd), None, true);
+
+        // Update scrollbar theme
+        set_window_theme(edit_hwnd, use_dark);
+
+        find_dialog::apply_theme();
+    }
+}
+
+// Helper function to update Encoding menu state: check the save encoding and
+// allow reopening only when there is a file on disk
+fn update_encoding_menu(hmenu: HMENU) {
+    let current_encoding = if let Ok(enc) = CURRENT_ENCODING.lock() {
+        *enc
+    } else {
+        FileEncoding::Utf8
+    };
+    let has_file = CURRENT_FILE
+        .lock()
+        .ok()
+        .and_then(|file| file.clone())
+        .is_some_and(|path| !is_untitled_file(&path));
+
+    for (index, &encoding) in MENU_ENCODINGS.iter().enumerate() {
+        let reopen_flags = if has_file {
+            MENU_ITEM_FLAGS(0x00000000) // MF_ENABLED
+        } else {
+            MENU_ITEM_FLAGS(0x00000001) // MF_GRAYED
+        };
+        let check_state = if encoding == current_encoding {
+            MENU_ITEM_FLAGS(0x00000008) // MF_CHECKED
+        } else {
+            MENU_ITEM_FLAGS(0x00000000) // MF_UNCHECKED
+        };
+        unsafe {
+            let _ = EnableMenuItem(
+                hmenu,
+                (ID_ENCODING_REOPEN_FIRST + index as i32) as u32,
+                reopen_flags,
+            );
+            let _ = CheckMenuItem(
+                hmenu,
+                (ID_ENCODING_SAVE_FIRST + index as i32) as u32,
+                check_state.0,
+            );
+        }
+    }
+}
+
+// Helper function to update backup menu check state
+fn update_backup_menu_check() {
+    if let Ok(menu_handle) = MENU_HANDLE.lock()
+        && let Some(hmenu_isize) = *menu_handle
+    {
+        let check_state = if file_io::backup_on_save() {
+            MENU_ITEM_FLAGS(0x00000008) // MF_CHECKED
+        } else {
+            MENU_ITEM_FLAGS(0x00000000) // MF_UNCHECKED
+        };
+        unsafe {
+            let _ = CheckMenuItem(
+                HMENU(hmenu_isize as *mut core::ffi::c_void),
+                ID_FILE_BACKUP as u32,
+                check_state.0,
+            );
+        }
+    }
+}
+
+// Put a file at the top of File > Recent Files
+fn add_recent_file(path: &Path) {
+    if let Ok(mut recent_files) = RECENT_FILES.lock() {
+        settings::add_recent_file(&mut recent_files, path);
+    }
+    update_recent_menu();
+}
+
+fn remove_recent_file(path: &Path) {
+    if let Ok(mut recent_files) = RECENT_FILES.lock() {
+        recent_files.retain(|recent| recent != path);
+    }
+    update_recent_menu();
+}
+
+// The file a File > Recent Files command opens
+fn recent_file(cmd_id: i32) -> Option<PathBuf> {
+    let index = usize::try_from(cmd_id - ID_FILE_RECENT_FIRST).ok()?;
+    RECENT_FILES.lock().ok()?.get(index).cloned()
+}
+
+// Helper function to rebuild File > Recent Files from RECENT_FILES
+fn update_recent_menu() {
+    let Some(hmenu_isize) = RECENT_MENU_HANDLE.lock().ok().and_then(|handle| *handle) else {
+        return;
+    };
+    let hmenu = HMENU(hmenu_isize as *mut core::ffi::c_void);
+    let recent_files = RECENT_FILES
+        .lock()
+        .map(|recent_files| recent_files.clone())
+        .unwrap_or_default();
+    unsafe {
+        while GetMenuItemCount(Some(hmenu)) > 0 {
+            let _ = DeleteMenu(hmenu, 0, MENU_ITEM_FLAGS(0x00000400)); // MF_BYPOSITION
+        }
+
+        if recent_files.is_empty() {
+            let text = format!("{}\0", get_string("MENU_RECENT_EMPTY"));
+            let text_utf16: Vec<u16> = text.encode_utf16().collect();
+            let _ = AppendMenuW(
+                hmenu,
+                MENU_ITEM_FLAGS(0x00000001), // MF_GRAYED
+                0,
+                PCWSTR(text_utf16.as_ptr()),
+            );
+        }
+        for (index, path) in recent_files.iter().enumerate() {
+            // A lone & would underline the next letter
+            let text = format!(
+                "&{} {}\0",
+                index + 1,
+                path.display().to_string().replace('&', "&&")
+            );
+            let text_utf16: Vec<u16> = text.encode_utf16().collect();
+            let _ = AppendMenuW(
+                hmenu,
+                MENU_ITEM_FLAGS(0x00000000),
+                (ID_FILE_RECENT_FIRST + index as i32) as usize,
+                PCWSTR(text_utf16.as_ptr()),
+            );
+        }
+    }
+}
+
 // Helper function to update status bar menu check state
 fn update_statusbar_menu_check() {
     if let Ok(menu_handle) = MENU_HANDLE.lock() {
@@ -303,8 +1492,8 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
 
         match msg {
             WM_UAHDRAWMENU => {
-                // Only handle if dark mode is enabled
-                if !should_use_dark_mode() {
+                // Windows draws the menu bar itself in the built-in light theme
+                if theme::uses_system_colors() {
                     return DefWindowProcW(hwnd, msg, wparam, lparam);
                 }
 
@@ -318,7 +1507,7 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
 
                 let menu_data = lparam.0 as *const UAHMENU;
                 if !menu_data.is_null() {
-                    let brush = CreateSolidBrush(DARK_MENU_BG);
+                    let brush = CreateSolidBrush(COLORREF(theme::active_theme().menu_bg));
                     let mut rect = RECT::default();
                     let _ = GetWindowRect(hwnd, &mut rect);
                     rect.bottom = rect.top + 24; // Menu bar height
@@ -331,8 +1520,8 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                 return LRESULT(0);
             }
             WM_UAHDRAWMENUITEM => {
-                // Only handle if dark mode is enabled
-                if !should_use_dark_mode() {
+                // Windows draws the menu bar itself in the built-in light theme
+                if theme::uses_system_colors() {
                     return DefWindowProcW(hwnd, msg, wparam, lparam);
                 }
                 // Draw individual menu items with white text
@@ -368,22 +1557,23 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                     const ODS_SELECTED: u32 = 0x0001;
                     const ODS_INACTIVE: u32 = 0x0080;
 
+                    let theme = theme::active_theme();
                     let bg_color = if (dis.itemState.0 & ODS_HOTLIGHT) != 0
                         || (dis.itemState.0 & ODS_SELECTED) != 0
                     {
-                        DARK_MENU_HOVER
+                        theme.menu_hover
                     } else {
-                        DARK_MENU_BG
+                        theme.menu_bg
                     };
 
                     let text_color = if (dis.itemState.0 & ODS_INACTIVE) != 0 {
-                        DARK_MENU_TEXT_DISABLED
+                        theme.menu_text_disabled
                     } else {
-                        DARK_MENU_TEXT
+                        theme.menu_text
                     };
 
                     // Draw background
-                    let brush = CreateSolidBrush(bg_color);
+                    let brush = CreateSolidBrush(COLORREF(bg_color));
                     FillRect(um.hdc, &dis.rcItem, brush);
                     let _ = DeleteObject(brush.into());
 
@@ -409,7 +1599,7 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                         };
 
                         SetBkMode(um.hdc, TRANSPARENT);
-                        SetTextColor(um.hdc, text_color);
+                        SetTextColor(um.hdc, COLORREF(text_color));
 
                         let mut rect = dis.rcItem;
                         DrawTextW(
@@ -439,7 +1629,14 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                     WINDOW_EX_STYLE(0),
                     PCWSTR(richedit_class.as_ptr()),
                     PCWSTR::null(),
-                    WINDOW_STYLE(0x40000000 | 0x10000000 | 0x00200000 | 0x00100000 | ES_MULTILINE), // WS_CHILD | WS_VISIBLE | WS_VSCROLL | WS_HSCROLL
+                    WINDOW_STYLE(
+                        0x40000000
+                            | 0x10000000
+                            | 0x00200000
+                            | 0x00100000
+                            | ES_MULTILINE
+                            | ES_NOHIDESEL,
+                    ), // WS_CHILD | WS_VISIBLE | WS_VSCROLL | WS_HSCROLL
                     0,
                     0,
                     0,
@@ -465,6 +1662,17 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                     Some(LPARAM(u64::MAX as isize)),
                 );
 
+                // Send EN_CHANGE and EN_SELCHANGE so the status bar can follow edits
+                const EM_SETEVENTMASK: u32 = 0x0445;
+                const ENM_CHANGE: isize = 0x00000001;
+                const ENM_SELCHANGE: isize = 0x00080000;
+                SendMessageW(
+                    edit_hwnd,
+                    EM_SETEVENTMASK,
+                    Some(WPARAM(0)),
+                    Some(LPARAM(ENM_CHANGE | ENM_SELCHANGE)),
+                );
+
                 // Enable word wrap by default
                 SendMessageW(
                     edit_hwnd,
@@ -491,51 +1699,13 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                     Some(LPARAM(margin as isize)),
                 );
 
-                // Set default font
-                let font_name = "MS Gothic";
-                let hfont_edit = CreateFontW(
-                    -16,                      // cHeight
-                    0,                        // cWidth
-                    0,                        // cEscapement
-                    0,                        // cOrientation
-                    400,                      // cWeight (FW_NORMAL)
-                    0,                        // bItalic
-                    0,                        // bUnderline
-                    0,                        // bStrikeOut
-                    FONT_CHARSET(0),          // iCharSet
-                    FONT_OUTPUT_PRECISION(0), // iOutPrecision
-                    FONT_CLIP_PRECISION(0),   // iClipPrecision
-                    FONT_QUALITY(0),          // iQuality
-                    0,                        // iPitchAndFamily
-                    windows::core::PCWSTR(
-                        font_name
-                            .encode_utf16()
-                            .chain(Some(0))
-                            .collect::<Vec<_>>()
-                            .as_ptr(),
-                    ),
-                );
-                SendMessageW(
-                    edit_hwnd,
-                    WM_SETFONT,
-                    Some(WPARAM(hfont_edit.0 as usize)),
-                    Some(LPARAM(1)),
-                );
-
-                // Disable auto font
-                let lang_options = SendMessageW(
-                    edit_hwnd,
-                    EM_GETLANGOPTIONS,
-                    Some(WPARAM(0)),
-                    Some(LPARAM(0)),
-                );
-                let new_options = LRESULT(lang_options.0 & !(IMF_AUTOFONT | IMF_DUALFONT) as isize);
-                SendMessageW(
-                    edit_hwnd,
-                    EM_SETLANGOPTIONS,
-                    Some(WPARAM(0)),
-                    Some(LPARAM(new_options.0)),
-                );
+                // Set the editor font and disable auto font
+                let font = EDITOR_FONT
+                    .lock()
+                    .ok()
+                    .and_then(|font| font.clone())
+                    .unwrap_or_default();
+                set_editor_font(edit_hwnd, &font);
 
                 // Set reduced line spacing using PARAFORMAT2
                 #[repr(C)]
@@ -638,6 +1808,45 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                     ID_FILE_SAVEAS as usize,
                     PCWSTR(saveas_text_utf16.as_ptr()),
                 );
+                // Add separator
+                let _ = AppendMenuW(
+                    hmenu_file,
+                    MENU_ITEM_FLAGS(0x00000800), // MF_SEPARATOR
+                    0,
+                    PCWSTR::null(),
+                );
+                let backup_text = format!("{}\0", get_string("MENU_BACKUP"));
+                let backup_text_utf16: Vec<u16> = backup_text.encode_utf16().collect();
+                let _ = AppendMenuW(
+                    hmenu_file,
+                    MENU_ITEM_FLAGS(0x00000000),
+                    ID_FILE_BACKUP as usize,
+                    PCWSTR(backup_text_utf16.as_ptr()),
+                );
+                // Add separator
+                let _ = AppendMenuW(
+                    hmenu_file,
+                    MENU_ITEM_FLAGS(0x00000800), // MF_SEPARATOR
+                    0,
+                    PCWSTR::null(),
+                );
+                // Filled in by update_recent_menu
+                let hmenu_recent = CreateMenu().unwrap_or_default();
+                let recent_text = format!("{}\0", get_string("MENU_RECENT"));
+                let recent_text_utf16: Vec<u16> = recent_text.encode_utf16().collect();
+                let _ = AppendMenuW(
+                    hmenu_file,
+                    MENU_ITEM_FLAGS(0x00000010), // MF_POPUP
+                    hmenu_recent.0 as usize,
+                    PCWSTR(recent_text_utf16.as_ptr()),
+                );
+                // Add separator
+                let _ = AppendMenuW(
+                    hmenu_file,
+                    MENU_ITEM_FLAGS(0x00000800), // MF_SEPARATOR
+                    0,
+                    PCWSTR::null(),
+                );
                 let exit_text = format!("{}\0", get_string("MENU_EXIT"));
                 let exit_text_utf16: Vec<u16> = exit_text.encode_utf16().collect();
                 let _ = AppendMenuW(
@@ -719,6 +1928,29 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                     0,
                     PCWSTR::null(),
                 );
+                for (id, key) in [
+                    (ID_EDIT_FIND, "MENU_FIND"),
+                    (ID_EDIT_FIND_NEXT, "MENU_FIND_NEXT"),
+                    (ID_EDIT_FIND_PREV, "MENU_FIND_PREV"),
+                    (ID_EDIT_REPLACE, "MENU_REPLACE"),
+                    (ID_EDIT_GOTO, "MENU_GOTO"),
+                ] {
+                    let find_text = format!("{}\0", get_string(key));
+                    let find_text_utf16: Vec<u16> = find_text.encode_utf16().collect();
+                    let _ = AppendMenuW(
+                        hmenu_edit,
+                        MENU_ITEM_FLAGS(0x00000000),
+                        id as usize,
+                        PCWSTR(find_text_utf16.as_ptr()),
+                    );
+                }
+                // Add separator
+                let _ = AppendMenuW(
+                    hmenu_edit,
+                    MENU_ITEM_FLAGS(0x00000800), // MF_SEPARATOR
+                    0,
+                    PCWSTR::null(),
+                );
                 let selectall_text = format!("{}\0", get_string("MENU_SELECTALL"));
                 let selectall_text_utf16: Vec<u16> = selectall_text.encode_utf16().collect();
                 let _ = AppendMenuW(
@@ -727,6 +1959,43 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                     ID_EDIT_SELECTALL as usize,
                     PCWSTR(selectall_text_utf16.as_ptr()),
                 );
+                // Add separator
+                let _ = AppendMenuW(
+                    hmenu_edit,
+                    MENU_ITEM_FLAGS(0x00000800), // MF_SEPARATOR
+                    0,
+                    PCWSTR::null(),
+                );
+                let hmenu_lineending = CreateMenu().unwrap_or_default();
+                let crlf_text_utf16: Vec<u16> = "Windows (CRLF)\0".encode_utf16().collect();
+                let _ = AppendMenuW(
+                    hmenu_lineending,
+                    MENU_ITEM_FLAGS(0x00000000),
+                    ID_EDIT_LINEENDING_CRLF as usize,
+                    PCWSTR(crlf_text_utf16.as_ptr()),
+                );
+                let lf_text_utf16: Vec<u16> = "Unix (LF)\0".encode_utf16().collect();
+                let _ = AppendMenuW(
+                    hmenu_lineending,
+                    MENU_ITEM_FLAGS(0x00000000),
+                    ID_EDIT_LINEENDING_LF as usize,
+                    PCWSTR(lf_text_utf16.as_ptr()),
+                );
+                let cr_text_utf16: Vec<u16> = "Macintosh (CR)\0".encode_utf16().collect();
+                let _ = AppendMenuW(
+                    hmenu_lineending,
+                    MENU_ITEM_FLAGS(0x00000000),
+                    ID_EDIT_LINEENDING_CR as usize,
+                    PCWSTR(cr_text_utf16.as_ptr()),
+                );
+                let lineending_text = format!("{}\0", get_string("MENU_LINEENDING"));
+                let lineending_text_utf16: Vec<u16> = lineending_text.encode_utf16().collect();
+                let _ = AppendMenuW(
+                    hmenu_edit,
+                    MENU_ITEM_FLAGS(0x00000010), // MF_POPUP
+                    hmenu_lineending.0 as usize,
+                    PCWSTR(lineending_text_utf16.as_ptr()),
+                );
                 let edit_text = format!("{}\0", get_string("MENU_EDIT"));
                 let edit_text_utf16: Vec<u16> = edit_text.encode_utf16().collect();
                 let _ = AppendMenuW(
@@ -736,8 +2005,50 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                     PCWSTR(edit_text_utf16.as_ptr()),
                 );
 
+                // Create Format menu
+                let hmenu_format = CreateMenu().unwrap_or_default();
+                let font_text = format!("{}\0", get_string("MENU_FONT"));
+                let font_text_utf16: Vec<u16> = font_text.encode_utf16().collect();
+                let _ = AppendMenuW(
+                    hmenu_format,
+                    MENU_ITEM_FLAGS(0x00000000),
+                    ID_FORMAT_FONT as usize,
+                    PCWSTR(font_text_utf16.as_ptr()),
+                );
+                let format_text = format!("{}\0", get_string("MENU_FORMAT"));
+                let format_text_utf16: Vec<u16> = format_text.encode_utf16().collect();
+                let _ = AppendMenuW(
+                    hmenu,
+                    MENU_ITEM_FLAGS(0x00000010),
+                    hmenu_format.0 as usize,
+                    PCWSTR(format_text_utf16.as_ptr()),
+                );
+
                 // Create View menu
                 let hmenu_view = CreateMenu().unwrap_or_default();
+                let hmenu_zoom = CreateMenu().unwrap_or_default();
+                for (id, key) in [
+                    (ID_VIEW_ZOOMIN, "MENU_ZOOMIN"),
+                    (ID_VIEW_ZOOMOUT, "MENU_ZOOMOUT"),
+                    (ID_VIEW_ZOOMRESET, "MENU_ZOOMRESET"),
+                ] {
+                    let text = format!("{}\0", get_string(key));
+                    let text_utf16: Vec<u16> = text.encode_utf16().collect();
+                    let _ = AppendMenuW(
+                        hmenu_zoom,
+                        MENU_ITEM_FLAGS(0x00000000),
+                        id as usize,
+                        PCWSTR(text_utf16.as_ptr()),
+                    );
+                }
+                let zoom_text = format!("{}\0", get_string("MENU_ZOOM"));
+                let zoom_text_utf16: Vec<u16> = zoom_text.encode_utf16().collect();
+                let _ = AppendMenuW(
+                    hmenu_view,
+                    MENU_ITEM_FLAGS(0x00000010),
+                    hmenu_zoom.0 as usize,
+                    PCWSTR(zoom_text_utf16.as_ptr()),
+                );
                 let wordwrap_text = format!("{}\0", get_string("MENU_WORDWRAP"));
                 let wordwrap_text_utf16: Vec<u16> = wordwrap_text.encode_utf16().collect();
                 let _ = AppendMenuW(
@@ -754,13 +2065,39 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                     ID_VIEW_STATUSBAR as usize,
                     PCWSTR(statusbar_text_utf16.as_ptr()),
                 );
-                let darkmode_text = format!("{}\0", get_string("MENU_DARKMODE"));
-                let darkmode_text_utf16: Vec<u16> = darkmode_text.encode_utf16().collect();
+                let hmenu_theme = CreateMenu().unwrap_or_default();
+                for (id, mode) in menu_themes() {
+                    let label = match mode {
+                        ThemeMode::System => get_string("MENU_THEME_SYSTEM"),
+                        ThemeMode::Light => get_string("MENU_THEME_LIGHT"),
+                        ThemeMode::Dark => get_string("MENU_THEME_DARK"),
+                        // A lone & would underline the next letter
+                        ThemeMode::User(name) => name.replace('&', "&&"),
+                    };
+                    if id == ID_VIEW_THEME_USER_FIRST {
+                        let _ = AppendMenuW(
+                            hmenu_theme,
+                            MENU_ITEM_FLAGS(0x00000800), // MF_SEPARATOR
+                            0,
+                            PCWSTR::null(),
+                        );
+                    }
+                    let text = format!("{}\0", label);
+                    let text_utf16: Vec<u16> = text.encode_utf16().collect();
+                    let _ = AppendMenuW(
+                        hmenu_theme,
+                        MENU_ITEM_FLAGS(0x00000000),
+                        id as usize,
+                        PCWSTR(text_utf16.as_ptr()),
+                    );
+                }
+                let theme_text = format!("{}\0", get_string("MENU_THEME"));
+                let theme_text_utf16: Vec<u16> = theme_text.encode_utf16().collect();
                 let _ = AppendMenuW(
                     hmenu_view,
-                    MENU_ITEM_FLAGS(0x00000000),
-                    ID_VIEW_DARKMODE as usize,
-                    PCWSTR(darkmode_text_utf16.as_ptr()),
+                    MENU_ITEM_FLAGS(0x00000010),
+                    hmenu_theme.0 as usize,
+                    PCWSTR(theme_text_utf16.as_ptr()),
                 );
 
                 let view_text = format!("{}\0", get_string("MENU_VIEW"));
@@ -772,6 +2109,62 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                     PCWSTR(view_text_utf16.as_ptr()),
                 );
 
+                // Create Encoding menu
+                let hmenu_encoding = CreateMenu().unwrap_or_default();
+                let hmenu_reopen = CreateMenu().unwrap_or_default();
+                let hmenu_save_encoding = CreateMenu().unwrap_or_default();
+                for (index, &encoding) in MENU_ENCODINGS.iter().enumerate() {
+                    let label = format!("{}\0", status_bar::encoding_name(encoding));
+                    let label_utf16: Vec<u16> = label.encode_utf16().collect();
+                    let _ = AppendMenuW(
+                        hmenu_reopen,
+                        MENU_ITEM_FLAGS(0x00000000),
+                        (ID_ENCODING_REOPEN_FIRST + index as i32) as usize,
+                        PCWSTR(label_utf16.as_ptr()),
+                    );
+                    if encoding == FileEncoding::Auto {
+                        // Add separator
+                        let _ = AppendMenuW(
+                            hmenu_reopen,
+                            MENU_ITEM_FLAGS(0x00000800), // MF_SEPARATOR
+                            0,
+                            PCWSTR::null(),
+                        );
+                    } else {
+                        let _ = AppendMenuW(
+                            hmenu_save_encoding,
+                            MENU_ITEM_FLAGS(0x00000000),
+                            (ID_ENCODING_SAVE_FIRST + index as i32) as usize,
+                            PCWSTR(label_utf16.as_ptr()),
+                        );
+                    }
+                }
+                let reopen_text = format!("{}\0", get_string("MENU_REOPEN_ENCODING"));
+                let reopen_text_utf16: Vec<u16> = reopen_text.encode_utf16().collect();
+                let _ = AppendMenuW(
+                    hmenu_encoding,
+                    MENU_ITEM_FLAGS(0x00000010), // MF_POPUP
+                    hmenu_reopen.0 as usize,
+                    PCWSTR(reopen_text_utf16.as_ptr()),
+                );
+                let save_encoding_text = format!("{}\0", get_string("MENU_SAVE_ENCODING"));
+                let save_encoding_text_utf16: Vec<u16> =
+                    save_encoding_text.encode_utf16().collect();
+                let _ = AppendMenuW(
+                    hmenu_encoding,
+                    MENU_ITEM_FLAGS(0x00000010), // MF_POPUP
+                    hmenu_save_encoding.0 as usize,
+                    PCWSTR(save_encoding_text_utf16.as_ptr()),
+                );
+                let encoding_text = format!("{}\0", get_string("MENU_ENCODING"));
+                let encoding_text_utf16: Vec<u16> = encoding_text.encode_utf16().collect();
+                let _ = AppendMenuW(
+                    hmenu,
+                    MENU_ITEM_FLAGS(0x00000010),
+                    hmenu_encoding.0 as usize,
+                    PCWSTR(encoding_text_utf16.as_ptr()),
+                );
+
                 // Set menu
                 let _ = SetMenu(hwnd, Some(hmenu));
 
@@ -806,13 +2199,24 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                     flush_menu_themes();
                 }
 
-                // Store menu handle
+                // Store menu handles
                 if let Ok(mut menu_handle) = MENU_HANDLE.lock() {
                     *menu_handle = Some(hmenu.0 as *mut core::ffi::c_void as isize);
                 }
+                if let Ok(mut menu_handle) = ENCODING_MENU_HANDLE.lock() {
+                    *menu_handle = Some(hmenu_encoding.0 as isize);
+                }
+                if let Ok(mut menu_handle) = LINEENDING_MENU_HANDLE.lock() {
+                    *menu_handle = Some(hmenu_lineending.0 as isize);
+                }
+                if let Ok(mut menu_handle) = RECENT_MENU_HANDLE.lock() {
+                    *menu_handle = Some(hmenu_recent.0 as isize);
+                }
 
                 update_wordwrap_menu_check();
                 update_statusbar_menu_check();
+                update_backup_menu_check();
+                update_recent_menu();
 
                 // Create status bar components
                 let separator_class = "SeparatorClass\0".encode_utf16().collect::<Vec<_>>();
@@ -865,7 +2269,7 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                     0,
                     20,
                     Some(hwnd),
-                    None,
+                    Some(HMENU(ID_STATUS_POSITION as isize as *mut core::ffi::c_void)),
                     Some(HINSTANCE(hinstance.0)),
                     None,
                 )
@@ -881,7 +2285,7 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                     0,
                     20,
                     Some(hwnd),
-                    None,
+                    Some(HMENU(ID_STATUS_ENCODING as isize as *mut core::ffi::c_void)),
                     Some(HINSTANCE(hinstance.0)),
                     None,
                 )
@@ -994,7 +2398,7 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                     32,
                     20,
                     Some(hwnd),
-                    None,
+                    Some(HMENU(ID_STATUS_ZOOM as isize as *mut core::ffi::c_void)),
                     Some(HINSTANCE(hinstance.0)),
                     None,
                 )
@@ -1037,7 +2441,9 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                     102,
                     20,
                     Some(hwnd),
-                    None,
+                    Some(HMENU(
+                        ID_STATUS_LINEENDING as isize as *mut core::ffi::c_void,
+                    )),
                     Some(HINSTANCE(hinstance.0)),
                     None,
                 )
@@ -1050,9 +2456,6 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                     Some(LPARAM(1)),
                 );
 
-                let linebreak_text = "Windows (CRLF)\0".encode_utf16().collect::<Vec<_>>();
-                let _ = SetWindowTextW(linebreak_hwnd, PCWSTR(linebreak_text.as_ptr()));
-
                 // Store handles
                 SetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(8), char_hwnd.0 as isize);
                 SetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(24), sep1_hwnd.0 as isize);
@@ -1064,6 +2467,8 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                 SetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(72), sep4_hwnd.0 as isize);
                 SetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(80), linebreak_hwnd.0 as isize);
 
+                set_line_ending(hwnd, current_line_ending());
+
                 // Set window icon
                 if let Ok(hicon) = LoadIconW(Some(HINSTANCE(hinstance.0)), PCWSTR(1 as *const u16))
                 {
@@ -1093,7 +2498,7 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                     Some(LPARAM(0)),
                 );
 
-                // Set colors for RichEdit based on dark mode preference
+                // Set colors for RichEdit from the active theme
                 const EM_SETBKGNDCOLOR: u32 = 0x0443; // WM_USER + 67
                 const EM_SETCHARFORMAT: u32 = 0x0444; // WM_USER + 68
                 const SCF_ALL: usize = 0x0004;
@@ -1124,29 +2529,8 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                     bUnderlineType: u8,
                 }
 
-                if should_use_dark_mode() {
-                    // Dark mode colors
-                    SendMessageW(
-                        edit_hwnd,
-                        EM_SETBKGNDCOLOR,
-                        Some(WPARAM(0)),
-                        Some(LPARAM(DARK_EDITOR_BG.0 as isize)),
-                    );
-
-                    let mut cf = CHARFORMAT2W {
-                        cbsize: std::mem::size_of::<CHARFORMAT2W>() as u32,
-                        dwmask: CFM_COLOR,
-                        crtext: DARK_EDITOR_TEXT,
-                        ..std::mem::zeroed()
-                    };
-
-                    SendMessageW(
-                        edit_hwnd,
-                        EM_SETCHARFORMAT,
-                        Some(WPARAM(SCF_ALL)),
-                        Some(LPARAM(&mut cf as *mut _ as isize)),
-                    );
-                } else {
+                let theme = theme::active_theme();
+                if theme::uses_system_colors() {
                     // Light mode colors (system defaults)
                     SendMessageW(
                         edit_hwnd,
@@ -1154,22 +2538,29 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                         Some(WPARAM(1)), // Use system color
                         Some(LPARAM(0)),
                     );
-
-                    let mut cf = CHARFORMAT2W {
-                        cbsize: std::mem::size_of::<CHARFORMAT2W>() as u32,
-                        dwmask: CFM_COLOR,
-                        crtext: LIGHT_EDITOR_TEXT,
-                        ..std::mem::zeroed()
-                    };
-
+                } else {
                     SendMessageW(
                         edit_hwnd,
-                        EM_SETCHARFORMAT,
-                        Some(WPARAM(SCF_ALL)),
-                        Some(LPARAM(&mut cf as *mut _ as isize)),
+                        EM_SETBKGNDCOLOR,
+                        Some(WPARAM(0)),
+                        Some(LPARAM(theme.editor_bg as isize)),
                     );
                 }
 
+                let mut cf = CHARFORMAT2W {
+                    cbsize: std::mem::size_of::<CHARFORMAT2W>() as u32,
+                    dwmask: CFM_COLOR,
+                    crtext: COLORREF(theme.editor_text),
+                    ..std::mem::zeroed()
+                };
+
+                SendMessageW(
+                    edit_hwnd,
+                    EM_SETCHARFORMAT,
+                    Some(WPARAM(SCF_ALL)),
+                    Some(LPARAM(&mut cf as *mut _ as isize)),
+                );
+
                 LRESULT(0)
             }
             WM_GETMINMAXINFO => {
@@ -1455,7 +2846,7 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                     let _ = InvalidateRect(Some(hwnd), None, true);
 
                     // Directly paint menu bar area after resize using window DC
-                    if should_use_dark_mode() {
+                    if !theme::uses_system_colors() {
                         if wparam.0 != 1 {
                             // Skip SIZE_MINIMIZED (1)
                             use windows::Win32::Graphics::Gdi::{GetWindowDC, ReleaseDC};
@@ -1463,6 +2854,8 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                                 GetSystemMetrics, SYSTEM_METRICS_INDEX,
                             };
 
+                            let theme = theme::active_theme();
+
                             let hdc = GetWindowDC(Some(hwnd));
                             let caption_height = GetSystemMetrics(SYSTEM_METRICS_INDEX(4)); // SM_CYCAPTION
                             let menu_height = GetSystemMetrics(SYSTEM_METRICS_INDEX(15)); // SM_CYMENU
@@ -1479,7 +2872,7 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                                 bottom: caption_height + border_height + menu_height + 10,
                             };
 
-                            let brush = CreateSolidBrush(DARK_MENU_BG);
+                            let brush = CreateSolidBrush(COLORREF(theme.menu_bg));
                             FillRect(hdc, &menu_rect, brush);
                             let _ = DeleteObject(brush.into());
                             let _ = ReleaseDC(Some(hwnd), hdc);
@@ -1495,7 +2888,7 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                                 right: window_width,
                                 bottom: caption_height + border_height + menu_height + 5,
                             };
-                            let line_brush = CreateSolidBrush(DARK_MENU_BORDER);
+                            let line_brush = CreateSolidBrush(COLORREF(theme.menu_border));
                             FillRect(hdc2, &line_rect, line_brush);
                             let _ = DeleteObject(line_brush.into());
                             let _ = ReleaseDC(Some(hwnd), hdc2);
@@ -1512,13 +2905,20 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
             }
             WM_NOTIFY => {
                 let edit_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(0)) as _);
-                remove_ole_objects(edit_hwnd);
+                let header = &*(lparam.0 as *const NMHDR);
+                const EN_SELCHANGE: u32 = 0x0702;
+                if header.hwndFrom == edit_hwnd && header.code == EN_SELCHANGE {
+                    // Caret moves only update the line index; the text is unchanged
+                    status_bar::selection_changed(edit_hwnd);
+                } else {
+                    remove_ole_objects(edit_hwnd);
+                }
                 LRESULT(0)
             }
             WM_PASTE => {
                 let edit_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(0)) as _);
 
-                if OpenClipboard(Some(hwnd)).is_ok() {
+                if !is_read_only(edit_hwnd) && OpenClipboard(Some(hwnd)).is_ok() {
                     let hdata = GetClipboardData(13); // CF_UNICODETEXT
                     if let Ok(data) = hdata {
                         if !data.0.is_null() {
@@ -1729,14 +3129,18 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                         let _ = EnableMenuItem(edit_menu, ID_EDIT_PASTE as u32, paste_flags);
                     }
 
-                    // Update dark mode menu checkmark
-                    let dark_mode_enabled = should_use_dark_mode();
-                    let check_state = if dark_mode_enabled {
-                        MENU_ITEM_FLAGS(0x00000008) // MF_CHECKED
-                    } else {
-                        MENU_ITEM_FLAGS(0x00000000) // MF_UNCHECKED
-                    };
-                    let _ = CheckMenuItem(hmenu, ID_VIEW_DARKMODE as u32, check_state.0);
+                    // Update theme menu checkmarks
+                    let current_mode = theme::theme_mode();
+                    for (id, mode) in menu_themes() {
+                        let check_state = if mode == current_mode {
+                            MENU_ITEM_FLAGS(0x00000008) // MF_CHECKED
+                        } else {
+                            MENU_ITEM_FLAGS(0x00000000) // MF_UNCHECKED
+                        };
+                        let _ = CheckMenuItem(hmenu, id as u32, check_state.0);
+                    }
+
+                    update_encoding_menu(hmenu);
                 }
                 LRESULT(0)
             }
@@ -1744,9 +3148,47 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                 let edit_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(0)) as _);
                 let cmd_id = wparam.0 as i32;
 
+                const EN_CHANGE: usize = 0x0300;
+                if lparam.0 == edit_hwnd.0 as isize && (wparam.0 >> 16) & 0xFFFF == EN_CHANGE {
+                    if let Ok(mut cache) = MODIFIED_CACHE.lock() {
+                        *cache = None;
+                    }
+                    status_bar::text_changed(edit_hwnd);
+                    return LRESULT(0);
+                }
+
+                if let Some(encoding) = menu_encoding(ID_ENCODING_REOPEN_FIRST, cmd_id) {
+                    reopen_with_encoding(hwnd, edit_hwnd, encoding);
+                    return LRESULT(0);
+                }
+                if let Some(encoding) = menu_encoding(ID_ENCODING_SAVE_FIRST, cmd_id) {
+                    set_current_encoding(hwnd, edit_hwnd, encoding);
+                    return LRESULT(0);
+                }
+                if let Some(path) = recent_file(cmd_id) {
+                    // Drop entries for files that have been deleted or moved
+                    if confirm_save_changes(hwnd, edit_hwnd)
+                        && !open_file(hwnd, edit_hwnd, &path, FileEncoding::Auto)
+                        && !path.exists()
+                    {
+                        remove_recent_file(&path);
+                    }
+                    return LRESULT(0);
+                }
+                if let Some((_, mode)) = menu_themes().into_iter().find(|(id, _)| *id == cmd_id) {
+                    theme::set_theme_mode(mode);
+                    apply_theme(hwnd);
+                    return LRESULT(0);
+                }
+
                 match cmd_id {
                     ID_FILE_NEW => {
+                        if !confirm_save_changes(hwnd, edit_hwnd) {
+                            return LRESULT(0);
+                        }
+
                         let empty = "\0".encode_utf16().collect::<Vec<_>>();
+                        status_bar::reset_line_index();
                         SendMessageW(
                             edit_hwnd,
                             0x000C,
@@ -1759,6 +3201,12 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                         if let Ok(mut current_file) = CURRENT_FILE.lock() {
                             *current_file = Some(untitled_path);
                         }
+                        if let Ok(mut saved) = SAVED_CONTENT.lock() {
+                            *saved = SavedText::EMPTY;
+                        }
+                        status_bar::set_encoding_ambiguous(false);
+                        set_line_ending(hwnd, LineEnding::Crlf);
+                        set_read_only(edit_hwnd, false);
 
                         SendMessageW(edit_hwnd, EM_SETMODIFY, Some(WPARAM(0)), Some(LPARAM(0)));
 
@@ -1774,83 +3222,26 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                         LRESULT(0)
                     }
                     ID_FILE_OPEN => {
-                        if let Some((path, selected_encoding)) = file_io::open_file_dialog() {
-                            if let Ok((content, detected_encoding)) =
-                                file_io::load_file(&path, selected_encoding)
-                            {
-                                let utf16: Vec<u16> =
-                                    content.encode_utf16().chain(std::iter::once(0)).collect();
-
-                                SendMessageW(
-                                    edit_hwnd,
-                                    0x000C,
-                                    Some(WPARAM(0)),
-                                    Some(LPARAM(utf16.as_ptr() as isize)),
-                                );
-
-                                if let Ok(mut current_file) = CURRENT_FILE.lock() {
-                                    *current_file = Some(path.clone());
-                                }
-
-                                if let Ok(mut current_encoding) = CURRENT_ENCODING.lock() {
-                                    *current_encoding = detected_encoding;
-                                }
-
-                                if let Ok(mut saved) = SAVED_CONTENT.lock() {
-                                    *saved = content;
-                                }
-
-                                SendMessageW(
-                                    edit_hwnd,
-                                    EM_SETMODIFY,
-                                    Some(WPARAM(0)),
-                                    Some(LPARAM(0)),
-                                );
-
-                                // Clear undo buffer
-                                const EM_EMPTYUNDOBUFFER: u32 = 0x00CD;
-                                SendMessageW(
-                                    edit_hwnd,
-                                    EM_EMPTYUNDOBUFFER,
-                                    Some(WPARAM(0)),
-                                    Some(LPARAM(0)),
-                                );
-
-                                if let Some(filename) = path.file_name() {
-                                    if let Some(filename_str) = filename.to_str() {
-                                        let app_name = get_string("WINDOW_TITLE");
-                                        let title = format!("{} - {}\0", filename_str, app_name);
-                                        let title_utf16: Vec<u16> = title.encode_utf16().collect();
-                                        let _ = SetWindowTextW(hwnd, PCWSTR(title_utf16.as_ptr()));
-                                    }
-                                }
+                        if !confirm_save_changes(hwnd, edit_hwnd) {
+                            return LRESULT(0);
+                        }
 
-                                let char_hwnd =
-                                    HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(8)) as _);
-                                let pos_hwnd =
-                                    HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(32)) as _);
-                                let encoding_hwnd =
-                                    HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(48)) as _);
-                                let zoom_hwnd =
-                                    HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(64)) as _);
-                                let current_encoding = if let Ok(enc) = CURRENT_ENCODING.lock() {
-                                    *enc
-                                } else {
-                                    FileEncoding::Utf8
-                                };
-                                update_status_bar(
-                                    edit_hwnd,
-                                    char_hwnd,
-                                    pos_hwnd,
-                                    encoding_hwnd,
-                                    zoom_hwnd,
-                                    current_encoding,
-                                );
-                            }
+                        if let Some((path, selected_encoding)) = file_io::open_file_dialog(None) {
+                            open_file(hwnd, edit_hwnd, &path, selected_encoding);
                         }
                         LRESULT(0)
                     }
                     ID_FILE_SAVE => {
+                        // Saving over the original would replace its undecodable bytes
+                        if is_read_only(edit_hwnd) {
+                            SendMessageW(
+                                hwnd,
+                                WM_COMMAND,
+                                Some(WPARAM(ID_FILE_SAVEAS as usize)),
+                                Some(LPARAM(0)),
+                            );
+                            return LRESULT(0);
+                        }
                         if let Ok(current_file) = CURRENT_FILE.lock() {
                             if let Some(path) = current_file.as_ref() {
                                 if is_untitled_file(path) {
@@ -1888,14 +3279,20 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                                             String::new()
                                         };
 
-                                        let _ = file_io::save_file(&new_path, &text, encoding);
+                                        let Some(encoding) =
+                                            save_document(hwnd, &new_path, &text, encoding)
+                                        else {
+                                            return LRESULT(0);
+                                        };
                                         *CURRENT_FILE.lock().unwrap() = Some(new_path.clone());
                                         if let Ok(mut current_encoding) = CURRENT_ENCODING.lock() {
                                             *current_encoding = encoding;
                                         }
+                                        status_bar::set_encoding_ambiguous(false);
                                         if let Ok(mut saved) = SAVED_CONTENT.lock() {
-                                            *saved = text;
+                                            *saved = SavedText::new(&text);
                                         }
+                                        add_recent_file(&new_path);
                                         SendMessageW(
                                             edit_hwnd,
                                             EM_SETMODIFY,
@@ -1975,10 +3372,21 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                                         String::new()
                                     };
 
-                                    let _ = file_io::save_file(path, &text, encoding);
+                                    // Release CURRENT_FILE while a message box may be open
+                                    let path = path.clone();
+                                    drop(current_file);
+                                    let Some(saved_encoding) =
+                                        save_document(hwnd, &path, &text, encoding)
+                                    else {
+                                        return LRESULT(0);
+                                    };
+                                    if saved_encoding != encoding {
+                                        set_current_encoding(hwnd, edit_hwnd, saved_encoding);
+                                    }
                                     if let Ok(mut saved) = SAVED_CONTENT.lock() {
-                                        *saved = text;
+                                        *saved = SavedText::new(&text);
                                     }
+                                    add_recent_file(&path);
                                     SendMessageW(
                                         edit_hwnd,
                                         EM_SETMODIFY,
@@ -2031,14 +3439,21 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                                 String::new()
                             };
 
-                            let _ = file_io::save_file(&new_path, &text, encoding);
+                            let Some(encoding) = save_document(hwnd, &new_path, &text, encoding)
+                            else {
+                                return LRESULT(0);
+                            };
+                            // The new file holds exactly the text shown, so it can be edited
+                            set_read_only(edit_hwnd, false);
                             *CURRENT_FILE.lock().unwrap() = Some(new_path.clone());
                             if let Ok(mut current_encoding) = CURRENT_ENCODING.lock() {
                                 *current_encoding = encoding;
                             }
+                            status_bar::set_encoding_ambiguous(false);
                             if let Ok(mut saved) = SAVED_CONTENT.lock() {
-                                *saved = text.clone();
+                                *saved = SavedText::new(&text);
                             }
+                            add_recent_file(&new_path);
                             SendMessageW(edit_hwnd, EM_SETMODIFY, Some(WPARAM(0)), Some(LPARAM(0)));
                             if let Some(filename) = new_path.file_name() {
                                 if let Some(filename_str) = filename.to_str() {
@@ -2067,8 +3482,14 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                         }
                         LRESULT(0)
                     }
+                    ID_FILE_BACKUP => {
+                        file_io::set_backup_on_save(!file_io::backup_on_save());
+                        update_backup_menu_check();
+                        LRESULT(0)
+                    }
                     ID_FILE_EXIT => {
-                        PostQuitMessage(0);
+                        // Go through WM_CLOSE so unsaved changes are not lost
+                        SendMessageW(hwnd, WM_CLOSE, Some(WPARAM(0)), Some(LPARAM(0)));
                         LRESULT(0)
                     }
                     ID_EDIT_UNDO => {
@@ -2094,6 +3515,9 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                         LRESULT(0)
                     }
                     ID_EDIT_DELETE => {
+                        if is_read_only(edit_hwnd) {
+                            return LRESULT(0);
+                        }
                         const EM_REPLACESEL: u32 = 0x00C2;
                         let empty_str = "\0".encode_utf16().collect::<Vec<_>>();
                         SendMessageW(
@@ -2113,6 +3537,10 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                         toggle_word_wrap(edit_hwnd);
                         LRESULT(0)
                     }
+                    ID_FORMAT_FONT => {
+                        choose_editor_font(hwnd, edit_hwnd);
+                        LRESULT(0)
+                    }
                     ID_VIEW_STATUSBAR => {
                         let new_visibility = if let Ok(mut visible) = STATUSBAR_VISIBLE.lock() {
                             *visible = !*visible;
@@ -2188,148 +3616,60 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
 
                         LRESULT(0)
                     }
-                    ID_VIEW_DARKMODE => {
-                        // Toggle dark mode
-                        if let Ok(mut dark_mode) = DARK_MODE_ENABLED.lock() {
-                            *dark_mode = !*dark_mode;
-                        }
-
-                        // Apply dark mode settings immediately
-                        let use_dark = should_use_dark_mode();
-
-                        // Update title bar
-                        let dark_mode_value: i32 = if use_dark { 1 } else { 0 };
-                        let _ = DwmSetWindowAttribute(
-                            hwnd,
-                            DWMWA_USE_IMMERSIVE_DARK_MODE,
-                            &dark_mode_value as *const _ as *const _,
-                            std::mem::size_of::<i32>() as u32,
-                        );
-
-                        // Update menu dark mode
-                        set_preferred_app_mode(if use_dark { 1 } else { 0 });
-                        allow_dark_mode_for_window(hwnd, use_dark);
-                        flush_menu_themes();
-
-                        // Update RichEdit colors
-                        const EM_SETBKGNDCOLOR: u32 = 0x0443;
-                        const EM_SETCHARFORMAT: u32 = 0x0444;
-                        const SCF_ALL: usize = 0x0004;
-                        const CFM_COLOR: u32 = 0x40000000;
-
-                        #[repr(C)]
-                        #[allow(non_snake_case)]
-                        struct CHARFORMAT2W {
-                            cbsize: u32,
-                            dwmask: u32,
-                            dweffects: u32,
-                            yheight: i32,
-                            yoffset: i32,
-                            crtext: windows::Win32::Foundation::COLORREF,
-                            bcharset: u8,
-                            bpitchandfamily: u8,
-                            szfacename: [u16; 32],
-                            wweight: u16,
-                            sSpacing: i16,
-                            crbackcolor: windows::Win32::Foundation::COLORREF,
-                            lcid: u32,
-                            dwcookie: u32,
-                            sstyle: i16,
-                            wkerning: u16,
-                            bunits: u8,
-                            bAnimation: u8,
-                            bRevAuthor: u8,
-                            bUnderlineType: u8,
-                        }
-
-                        if use_dark {
-                            SendMessageW(
-                                edit_hwnd,
-                                EM_SETBKGNDCOLOR,
-                                Some(WPARAM(0)),
-                                Some(LPARAM(DARK_EDITOR_BG.0 as isize)),
-                            );
-
-                            let mut cf = CHARFORMAT2W {
-                                cbsize: std::mem::size_of::<CHARFORMAT2W>() as u32,
-                                dwmask: CFM_COLOR,
-                                crtext: DARK_EDITOR_TEXT,
-                                ..std::mem::zeroed()
-                            };
-
-                            SendMessageW(
-                                edit_hwnd,
-                                EM_SETCHARFORMAT,
-                                Some(WPARAM(SCF_ALL)),
-                                Some(LPARAM(&mut cf as *mut _ as isize)),
-                            );
-                        } else {
-                            SendMessageW(
-                                edit_hwnd,
-                                EM_SETBKGNDCOLOR,
-                                Some(WPARAM(1)),
-                                Some(LPARAM(0)),
-                            );
-
-                            let mut cf = CHARFORMAT2W {
-                                cbsize: std::mem::size_of::<CHARFORMAT2W>() as u32,
-                                dwmask: CFM_COLOR,
-                                crtext: LIGHT_EDITOR_TEXT,
-                                ..std::mem::zeroed()
-                            };
-
-                            SendMessageW(
-                                edit_hwnd,
-                                EM_SETCHARFORMAT,
-                                Some(WPARAM(SCF_ALL)),
-                                Some(LPARAM(&mut cf as *mut _ as isize)),
-                            );
-                        }
-
-                        // Redraw status bar and menu bar
-                        use windows::Win32::UI::WindowsAndMessaging::DrawMenuBar;
-                        let _ = DrawMenuBar(hwnd);
-                        let _ = InvalidateRect(Some(hwnd), None, true);
-
-                        // Redraw the menu bottom line
-                        draw_menu_nc_bottom_line(hwnd);
-
-                        // Update status bar components
-                        let char_hwnd =
-                            HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(8)) as _);
-                        let sep1_hwnd =
-                            HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(24)) as _);
-                        let pos_hwnd =
-                            HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(32)) as _);
-                        let sep2_hwnd =
-                            HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(40)) as _);
-                        let encoding_hwnd =
-                            HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(48)) as _);
-                        let sep3_hwnd =
-                            HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(56)) as _);
-                        let zoom_hwnd =
-                            HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(64)) as _);
-                        let sep4_hwnd =
-                            HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(72)) as _);
-                        let linebreak_hwnd =
-                            HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(80)) as _);
-                        let separator_hwnd =
-                            HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(16)) as _);
-
-                        let _ = InvalidateRect(Some(char_hwnd), None, true);
-                        let _ = InvalidateRect(Some(sep1_hwnd), None, true);
-                        let _ = InvalidateRect(Some(pos_hwnd), None, true);
-                        let _ = InvalidateRect(Some(sep2_hwnd), None, true);
-                        let _ = InvalidateRect(Some(encoding_hwnd), None, true);
-                        let _ = InvalidateRect(Some(sep3_hwnd), None, true);
-                        let _ = InvalidateRect(Some(zoom_hwnd), None, true);
-                        let _ = InvalidateRect(Some(sep4_hwnd), None, true);
-                        let _ = InvalidateRect(Some(linebreak_hwnd), None, true);
-                        let _ = InvalidateRect(Some(separator_hwnd), None, true);
-
-                        // Update scrollbar theme
-                        set_window_theme(edit_hwnd, use_dark);
-
+                    ID_STATUS_POSITION => {
+                        goto_dialog::show_goto_dialog(hwnd);
+                        LRESULT(0)
+                    }
+                    ID_STATUS_ENCODING => {
+                        show_status_menu(hwnd, &ENCODING_MENU_HANDLE, HWND(lparam.0 as _));
+                        LRESULT(0)
+                    }
+                    ID_STATUS_ZOOM | ID_VIEW_ZOOMRESET => {
+                        set_zoom(hwnd, edit_hwnd, 100);
+                        LRESULT(0)
+                    }
+                    ID_VIEW_ZOOMIN => {
+                        step_zoom(hwnd, edit_hwnd, true);
+                        LRESULT(0)
+                    }
+                    ID_VIEW_ZOOMOUT => {
+                        step_zoom(hwnd, edit_hwnd, false);
+                        LRESULT(0)
+                    }
+                    ID_STATUS_LINEENDING => {
+                        show_status_menu(hwnd, &LINEENDING_MENU_HANDLE, HWND(lparam.0 as _));
+                        LRESULT(0)
+                    }
+                    ID_EDIT_FIND => {
+                        find_dialog::show_find_dialog(hwnd, false);
+                        LRESULT(0)
+                    }
+                    ID_EDIT_GOTO => {
+                        goto_dialog::show_goto_dialog(hwnd);
+                        LRESULT(0)
+                    }
+                    ID_EDIT_REPLACE => {
+                        find_dialog::show_find_dialog(hwnd, true);
+                        LRESULT(0)
+                    }
+                    ID_EDIT_FIND_NEXT => {
+                        find_dialog::find_next(hwnd, true);
+                        LRESULT(0)
+                    }
+                    ID_EDIT_FIND_PREV => {
+                        find_dialog::find_next(hwnd, false);
+                        LRESULT(0)
+                    }
+                    ID_EDIT_LINEENDING_CRLF => {
+                        set_line_ending(hwnd, LineEnding::Crlf);
+                        LRESULT(0)
+                    }
+                    ID_EDIT_LINEENDING_LF => {
+                        set_line_ending(hwnd, LineEnding::Lf);
+                        LRESULT(0)
+                    }
+                    ID_EDIT_LINEENDING_CR => {
+                        set_line_ending(hwnd, LineEnding::Cr);
                         LRESULT(0)
                     }
                     _ => DefWindowProcW(hwnd, msg, wparam, lparam),
@@ -2366,27 +3706,32 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                 }
                 DefWindowProcW(hwnd, msg, wparam, lparam)
             }
+            0x001A => {
+                // WM_SETTINGCHANGE - Follow the Windows light/dark app mode when it changes
+                if lparam.0 != 0
+                    && PCWSTR(lparam.0 as *const u16)
+                        .to_string()
+                        .is_ok_and(|area| area == "ImmersiveColorSet")
+                    && theme::refresh_system_dark_mode()
+                {
+                    apply_theme(hwnd);
+                }
+                DefWindowProcW(hwnd, msg, wparam, lparam)
+            }
             0x0138 => {
                 // WM_CTLCOLORSTATIC - Color status bar controls
                 use windows::Win32::Graphics::Gdi::HDC;
 
                 let hdc = HDC(wparam.0 as isize as *mut core::ffi::c_void);
 
-                if should_use_dark_mode() {
-                    // Dark mode colors
-                    SetTextColor(hdc, DARK_EDITOR_TEXT);
-                    SetBkColor(hdc, DARK_EDITOR_BG);
-
-                    // Return dark brush for background
-                    if let Ok(mut brush) = DARK_BRUSH.lock() {
-                        if brush.is_none() {
-                            let hbrush = CreateSolidBrush(DARK_EDITOR_BG);
-                            *brush = Some(hbrush.0 as isize);
-                        }
-                        LRESULT(brush.unwrap())
-                    } else {
-                        DefWindowProcW(hwnd, msg, wparam, lparam)
-                    }
+                if !theme::uses_system_colors() {
+                    // Theme colors
+                    let theme = theme::active_theme();
+                    SetTextColor(hdc, COLORREF(theme.editor_text));
+                    SetBkColor(hdc, COLORREF(theme.editor_bg));
+
+                    // Return the theme brush for background
+                    LRESULT(find_dialog::brush_for(COLORREF(theme.editor_bg)).0 as isize)
                 } else {
                     // Use default system colors for light mode
                     DefWindowProcW(hwnd, msg, wparam, lparam)
@@ -2397,11 +3742,7 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                 use windows::Win32::Graphics::Gdi::HDC;
                 let hdc = HDC(wparam.0 as isize as *mut core::ffi::c_void);
 
-                let bg_color = if should_use_dark_mode() {
-                    DARK_MENU_BG
-                } else {
-                    LIGHT_MENU_BG
-                };
+                let bg_color = COLORREF(theme::active_theme().menu_bg);
 
                 let mut rect = RECT::default();
                 let _ = GetClientRect(hwnd, &mut rect);
@@ -2433,12 +3774,14 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
             }
             0x0232 => {
                 // WM_EXITSIZEMOVE - User has finished resizing/moving window
-                if should_use_dark_mode() {
+                if !theme::uses_system_colors() {
                     use windows::Win32::Graphics::Gdi::{GetWindowDC, ReleaseDC};
                     use windows::Win32::UI::WindowsAndMessaging::{
                         GetSystemMetrics, SYSTEM_METRICS_INDEX,
                     };
 
+                    let theme = theme::active_theme();
+
                     let hdc = GetWindowDC(Some(hwnd));
                     let caption_height = GetSystemMetrics(SYSTEM_METRICS_INDEX(4)); // SM_CYCAPTION
                     let menu_height = GetSystemMetrics(SYSTEM_METRICS_INDEX(15)); // SM_CYMENU
@@ -2455,7 +3798,7 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                         bottom: caption_height + border_height + menu_height + 0,
                     };
 
-                    let brush = CreateSolidBrush(DARK_MENU_BG);
+                    let brush = CreateSolidBrush(COLORREF(theme.menu_bg));
                     FillRect(hdc, &menu_rect, brush);
                     let _ = DeleteObject(brush.into());
                     let _ = ReleaseDC(Some(hwnd), hdc);
@@ -2471,7 +3814,7 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                         right: window_width,
                         bottom: caption_height + border_height + menu_height + 5,
                     };
-                    let line_brush = CreateSolidBrush(DARK_MENU_BORDER);
+                    let line_brush = CreateSolidBrush(COLORREF(theme.menu_border));
                     FillRect(hdc2, &line_rect, line_brush);
                     let _ = DeleteObject(line_brush.into());
                     let _ = ReleaseDC(Some(hwnd), hdc2);
@@ -2500,10 +3843,19 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
                 DefWindowProcW(hwnd, msg, wparam, lparam)
             }
             WM_CLOSE => {
-                let _ = DestroyWindow(hwnd);
+                let edit_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(0)) as _);
+                if confirm_save_changes(hwnd, edit_hwnd) {
+                    let _ = DestroyWindow(hwnd);
+                }
                 LRESULT(0)
             }
+            WM_QUERYENDSESSION => {
+                // Returning FALSE asks Windows to keep the session alive
+                let edit_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(0)) as _);
+                LRESULT(confirm_save_changes(hwnd, edit_hwnd) as isize)
+            }
             WM_DESTROY => {
+                settings_store::save_settings(&current_settings(hwnd));
                 PostQuitMessage(0);
                 LRESULT(0)
             }
@@ -2515,6 +3867,16 @@ extern "system" fn window_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPA
 fn main() {
     init_language();
 
+    let settings = settings_store::load_settings();
+    file_io::set_backup_on_save(settings.backup_on_save);
+    theme::set_theme_mode(settings.theme.clone());
+    if let Ok(mut font) = EDITOR_FONT.lock() {
+        *font = Some(settings.font.clone());
+    }
+    if let Ok(mut recent_files) = RECENT_FILES.lock() {
+        *recent_files = settings.recent_files.clone();
+    }
+
     let default_filename = get_string("FILE_UNTITLED");
     let untitled_path = PathBuf::from(default_filename);
     *CURRENT_FILE.lock().unwrap() = Some(untitled_path);
@@ -2542,21 +3904,27 @@ fn main() {
         let _ = RegisterClassW(&wnd_class);
 
         status_bar::register_status_bar_classes();
+        find_dialog::register_find_dialog_class();
+        goto_dialog::register_goto_dialog_class();
 
         let window_title_str = format!("{}\0", get_string("WINDOW_TITLE"));
         let window_title = window_title_str.encode_utf16().collect::<Vec<_>>();
 
         const WS_THICKFRAME: u32 = 0x00040000;
         const CW_USEDEFAULT: i32 = i32::MIN;
+        let (x, y, width, height) = match settings.window {
+            Some(window) => (window.x, window.y, window.width, window.height),
+            None => (CW_USEDEFAULT, CW_USEDEFAULT, 800, 600),
+        };
         let hwnd = CreateWindowExW(
             WINDOW_EX_STYLE(0),
             PCWSTR(class_name.as_ptr()),
             PCWSTR(window_title.as_ptr()),
             WINDOW_STYLE(0x00CF0000 | 0x10000000 | WS_THICKFRAME), // WS_OVERLAPPEDWINDOW | WS_VISIBLE
-            CW_USEDEFAULT,
-            CW_USEDEFAULT,
-            800,
-            600,
+            x,
+            y,
+            width,
+            height,
             None,
             None,
             Some(HINSTANCE(hinstance.0)),
@@ -2570,8 +3938,58 @@ fn main() {
         let initial_title_utf16: Vec<u16> = initial_title.encode_utf16().collect();
         let _ = SetWindowTextW(hwnd, PCWSTR(initial_title_utf16.as_ptr()));
 
+        apply_window_settings(hwnd, &settings);
+
+        match cli::parse(std::env::args_os().skip(1)) {
+            Ok(command_line) => apply_command_line(hwnd, &command_line),
+            Err(error) => show_command_line_error(hwnd, &error),
+        }
+
         let mut msg = MSG::default();
         while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            // Ctrl+F opens Find, Ctrl+H opens Replace, Ctrl+G opens Go To, F3 and Shift+F3
+            // search again in either direction. Ctrl+Plus, Ctrl+Minus and Ctrl+0 (main
+            // keyboard or numeric keypad) zoom in, zoom out and restore the default zoom.
+            if msg.message == WM_KEYDOWN {
+                // AltGr arrives as Ctrl+Alt, so AltGr+0 and the like are typed characters
+                let ctrl_pressed = (GetKeyState(0x11) as u16 & 0x8000) != 0
+                    && (GetKeyState(0x12) as u16 & 0x8000) == 0;
+                let shift_pressed = (GetKeyState(0x10) as u16 & 0x8000) != 0;
+                let shortcut_command = match msg.wParam.0 {
+                    0x46 if ctrl_pressed => Some(ID_EDIT_FIND),
+                    0x48 if ctrl_pressed => Some(ID_EDIT_REPLACE),
+                    0x47 if ctrl_pressed => Some(ID_EDIT_GOTO),
+                    0x72 if shift_pressed => Some(ID_EDIT_FIND_PREV),
+                    0x72 => Some(ID_EDIT_FIND_NEXT),
+                    // VK_OEM_PLUS, VK_ADD
+                    0xBB | 0x6B if ctrl_pressed => Some(ID_VIEW_ZOOMIN),
+                    // VK_OEM_MINUS, VK_SUBTRACT
+                    0xBD | 0x6D if ctrl_pressed => Some(ID_VIEW_ZOOMOUT),
+                    // 0, VK_NUMPAD0
+                    0x30 | 0x60 if ctrl_pressed => Some(ID_VIEW_ZOOMRESET),
+                    _ => None,
+                };
+                if let Some(cmd_id) = shortcut_command {
+                    SendMessageW(
+                        hwnd,
+                        WM_COMMAND,
+                        Some(WPARAM(cmd_id as usize)),
+                        Some(LPARAM(0)),
+                    );
+                    continue;
+                }
+            }
+
+            // Let the Find dialog handle its own keyboard input (Tab, Enter, Esc)
+            if let Some(find_hwnd) = find_dialog::dialog_hwnd()
+                && (msg.hwnd == find_hwnd || IsChild(find_hwnd, msg.hwnd).as_bool())
+                && IsDialogMessageW(find_hwnd, &msg).as_bool()
+            {
+                // Replacing from the dialog changes the document
+                update_status_and_title(hwnd);
+                continue;
+            }
+
             if msg.message == WM_KEYDOWN
                 && (msg.wParam.0 as i32 == 0x45 || msg.wParam.0 as i32 == 0x52)
             {
@@ -2585,6 +4003,16 @@ fn main() {
                 let ctrl_pressed = (GetKeyState(0x11) as u16 & 0x8000) != 0;
                 if ctrl_pressed {
                     let edit_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(0)) as _);
+                    // Saving over the original would replace its undecodable bytes
+                    if is_read_only(edit_hwnd) {
+                        SendMessageW(
+                            hwnd,
+                            WM_COMMAND,
+                            Some(WPARAM(ID_FILE_SAVEAS as usize)),
+                            Some(LPARAM(0)),
+                        );
+                        continue;
+                    }
                     if let Ok(current_file) = CURRENT_FILE.lock() {
                         if let Some(path) = current_file.as_ref() {
                             if is_untitled_file(path) {
@@ -2618,14 +4046,20 @@ fn main() {
                                         String::new()
                                     };
 
-                                    let _ = file_io::save_file(&new_path, &text, encoding);
+                                    let Some(encoding) =
+                                        save_document(hwnd, &new_path, &text, encoding)
+                                    else {
+                                        continue;
+                                    };
                                     *CURRENT_FILE.lock().unwrap() = Some(new_path.clone());
                                     if let Ok(mut current_encoding) = CURRENT_ENCODING.lock() {
                                         *current_encoding = encoding;
                                     }
+                                    status_bar::set_encoding_ambiguous(false);
                                     if let Ok(mut saved) = SAVED_CONTENT.lock() {
-                                        *saved = text.clone();
+                                        *saved = SavedText::new(&text);
                                     }
+                                    add_recent_file(&new_path);
                                     SendMessageW(
                                         edit_hwnd,
                                         EM_SETMODIFY,
@@ -2696,10 +4130,21 @@ fn main() {
                                 } else {
                                     FileEncoding::Utf8
                                 };
-                                let _ = file_io::save_file(path, &text, current_encoding);
+                                // Release CURRENT_FILE while a message box may be open
+                                let path = path.clone();
+                                drop(current_file);
+                                let Some(saved_encoding) =
+                                    save_document(hwnd, &path, &text, current_encoding)
+                                else {
+                                    continue;
+                                };
+                                if saved_encoding != current_encoding {
+                                    set_current_encoding(hwnd, edit_hwnd, saved_encoding);
+                                }
                                 if let Ok(mut saved) = SAVED_CONTENT.lock() {
-                                    *saved = text.clone();
+                                    *saved = SavedText::new(&text);
                                 }
+                                add_recent_file(&path);
                                 SendMessageW(
                                     edit_hwnd,
                                     EM_SETMODIFY,
@@ -2726,7 +4171,7 @@ fn main() {
                 if ctrl_pressed {
                     let edit_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(0)) as _);
 
-                    if OpenClipboard(Some(hwnd)).is_ok() {
+                    if !is_read_only(edit_hwnd) && OpenClipboard(Some(hwnd)).is_ok() {
                         let hdata = GetClipboardData(13);
                         if let Ok(data) = hdata {
                             if !data.0.is_null() {
@@ -2763,32 +4208,7 @@ fn main() {
             let _ = TranslateMessage(&msg);
             let _ = DispatchMessageW(&msg);
 
-            let edit_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(0)) as _);
-
-            if let Ok(visible) = STATUSBAR_VISIBLE.lock() {
-                if *visible {
-                    let char_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(8)) as _);
-                    let pos_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(32)) as _);
-                    let encoding_hwnd =
-                        HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(48)) as _);
-                    let zoom_hwnd = HWND(GetWindowLongPtrW(hwnd, WINDOW_LONG_PTR_INDEX(64)) as _);
-                    let current_encoding = if let Ok(enc) = CURRENT_ENCODING.lock() {
-                        *enc
-                    } else {
-                        FileEncoding::Utf8
-                    };
-                    status_bar::update_status_bar(
-                        edit_hwnd,
-                        char_hwnd,
-                        pos_hwnd,
-                        encoding_hwnd,
-                        zoom_hwnd,
-                        current_encoding,
-                    );
-                }
-            }
-
-            update_title_if_needed(hwnd, edit_hwnd);
+            update_status_and_title(hwnd);
         }
     }
 }
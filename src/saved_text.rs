@@ -0,0 +1,65 @@
+// Fingerprint of the text as it was last saved
+//
+// Keeping a length and a hash instead of a copy of the text saves memory on large files, and
+// the length alone settles most checks without reading the editor text at all.
+
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SavedText {
+    // Length in UTF-16 code units
+    len: usize,
+    hash: u64,
+}
+
+impl SavedText {
+    /// An empty document
+    pub const EMPTY: SavedText = SavedText { len: 0, hash: 0 };
+
+    pub fn new(text: &str) -> Self {
+        let len = text.encode_utf16().count();
+        if len == 0 {
+            return Self::EMPTY;
+        }
+        SavedText {
+            len,
+            hash: hash_text(text),
+        }
+    }
+
+    /// Whether a text of `len` UTF-16 code units is the saved text.
+    /// `text` is only called when the lengths are equal.
+    pub fn matches(&self, len: usize, text: impl FnOnce() -> String) -> bool {
+        if len != self.len {
+            return false;
+        }
+        len == 0 || hash_text(&text()) == self.hash
+    }
+}
+
+fn hash_text(text: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    text.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        let saved = SavedText::new("abc\r\n😀");
+        assert!(saved.matches(7, || "abc\r\n😀".to_string()));
+        assert!(!saved.matches(7, || "abd\r\n😀".to_string()));
+        assert!(!saved.matches(6, || "abc\r\n😀".to_string()));
+    }
+
+    #[test]
+    fn test_length_check_skips_text() {
+        let saved = SavedText::new("abc");
+        assert!(!saved.matches(4, || unreachable!()));
+        assert!(SavedText::EMPTY.matches(0, || unreachable!()));
+        assert_eq!(SavedText::new(""), SavedText::EMPTY);
+    }
+}
@@ -0,0 +1,689 @@
+// Plain-text and regular-expression search over the editor text
+//
+// The text and pattern are UTF-16 and every position is a UTF-16 offset. After
+// `collapse_crlf` the offsets are RichEdit character positions, so matches can be selected
+// with EM_SETSEL directly.
+//
+// A regular expression sees each CR line break as CRLF, the way the document is saved on
+// Windows, so `\r\n` matches a line break and `^` and `$` match at the start and end of
+// every line without including the break.
+
+use regex::{Regex, RegexBuilder};
+use std::borrow::Cow;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct SearchOptions {
+    pub match_case: bool,
+    pub whole_word: bool,
+    /// Continue from the other end of the text when nothing is found
+    pub wrap_around: bool,
+    /// Treat the pattern as a regular expression
+    pub regex: bool,
+}
+
+/// A search pattern prepared for a set of options
+pub struct Query {
+    pattern: Vec<u16>,
+    options: SearchOptions,
+    regex: Option<Regex>,
+}
+
+impl Query {
+    /// Prepare a pattern, compiling it when the regex option is set.
+    /// An invalid regular expression is returned as a one-line message.
+    pub fn new(pattern: &[u16], options: SearchOptions) -> Result<Query, String> {
+        let regex = if options.regex {
+            let source = String::from_utf16_lossy(pattern);
+            let regex = RegexBuilder::new(&source)
+                .case_insensitive(!options.match_case)
+                .multi_line(true)
+                .crlf(true)
+                .build()
+                .map_err(|error| {
+                    // Syntax errors show the pattern with a caret above an "error:" line
+                    let message = error.to_string();
+                    message
+                        .lines()
+                        .find_map(|line| line.strip_prefix("error: "))
+                        .unwrap_or(&message)
+                        .to_string()
+                })?;
+            Some(regex)
+        } else {
+            None
+        };
+        Ok(Query {
+            pattern: pattern.to_vec(),
+            options,
+            regex,
+        })
+    }
+}
+
+/// The edit that replaces every match at once
+#[derive(Debug, PartialEq)]
+pub struct Replacement {
+    /// Start of the first match
+    pub start: usize,
+    /// End of the last match
+    pub end: usize,
+    /// Text for the span from `start` to `end`
+    pub text: Vec<u16>,
+    /// Number of matches replaced
+    pub count: usize,
+}
+
+/// Collapse each CRLF pair into a single CR, the way RichEdit counts line breaks
+pub fn collapse_crlf(text: &[u16]) -> Vec<u16> {
+    let mut result = Vec::with_capacity(text.len());
+    for (index, &unit) in text.iter().enumerate() {
+        if unit == LF && index > 0 && text[index - 1] == CR {
+            continue;
+        }
+        result.push(unit);
+    }
+    result
+}
+
+/// Find the next match, returning its start and end offsets
+///
+/// Searching forward finds the first match starting at or after `from`. Searching backward
+/// finds the last match ending at or before `from`. Empty regex matches are skipped, since
+/// they cannot be selected.
+pub fn find(text: &[u16], query: &Query, from: usize, forward: bool) -> Option<(usize, usize)> {
+    match &query.regex {
+        Some(regex) => find_regex(text, regex, query.options, from, forward),
+        None => find_text(text, &query.pattern, from, forward, query.options),
+    }
+}
+
+/// Find every match from the start of the text, without overlaps
+pub fn find_all(text: &[u16], query: &Query) -> Vec<(usize, usize)> {
+    match &query.regex {
+        Some(regex) => {
+            let haystack = haystack(text);
+            regex_matches(text, &haystack, regex, query.options, 0).collect()
+        }
+        None => {
+            // Fold the text once rather than once per match
+            let (text, pattern) = prepare_text(text, &query.pattern, query.options);
+            let options = SearchOptions {
+                wrap_around: false,
+                ..query.options
+            };
+            let mut matches = Vec::new();
+            let mut from = 0;
+            while let Some((start, end)) = scan_text(&text, &pattern, from, true, options) {
+                matches.push((start, end));
+                from = end;
+            }
+            matches
+        }
+    }
+}
+
+/// The text that replaces the match at `range`, or None when `range` is not a match.
+/// Regex replacements expand `$1`, `${name}` and `$$`.
+pub fn replace_match(
+    text: &[u16],
+    query: &Query,
+    range: (usize, usize),
+    replacement: &[u16],
+) -> Option<Vec<u16>> {
+    let (start, end) = range;
+    if start == end {
+        return None;
+    }
+    match &query.regex {
+        Some(regex) => {
+            let haystack = haystack(text);
+            let mut offsets = Offsets::new(&haystack);
+            let from = offsets.byte_at(start);
+            let captures = regex.captures_at(&haystack, from)?;
+            let found = captures.get(0)?;
+            let found = (
+                offsets.start_at(found.start()),
+                offsets.position_at(found.end()),
+            );
+            if found != range || (query.options.whole_word && !is_whole_word(text, start, end)) {
+                return None;
+            }
+            Some(expand(&captures, replacement))
+        }
+        None => {
+            let options = SearchOptions {
+                wrap_around: false,
+                ..query.options
+            };
+            (find_text(text, &query.pattern, start, true, options) == Some(range))
+                .then(|| replacement.to_vec())
+        }
+    }
+}
+
+/// Replace every match with a single edit, or None when there is no match
+///
+/// The returned text covers the span from the start of the first match to the end of the
+/// last, so the whole replacement can be applied (and undone) as one step.
+pub fn replace_all(text: &[u16], query: &Query, replacement: &[u16]) -> Option<Replacement> {
+    let replaced: Vec<((usize, usize), Vec<u16>)> = match &query.regex {
+        Some(regex) => {
+            let haystack = haystack(text);
+            let mut offsets = Offsets::new(&haystack);
+            regex
+                .captures_iter(&haystack)
+                .filter_map(|captures| {
+                    let found = captures.get(0)?;
+                    let range = (
+                        offsets.start_at(found.start()),
+                        offsets.position_at(found.end()),
+                    );
+                    (!query.options.whole_word || is_whole_word(text, range.0, range.1))
+                        .then(|| (range, expand(&captures, replacement)))
+                })
+                .collect()
+        }
+        None => find_all(text, query)
+            .into_iter()
+            .map(|range| (range, replacement.to_vec()))
+            .collect(),
+    };
+
+    let (&((start, _), _), &((_, end), _)) = (replaced.first()?, replaced.last()?);
+    let mut result = Vec::new();
+    let mut copied = start;
+    for ((match_start, match_end), replacement) in &replaced {
+        result.extend_from_slice(&text[copied..*match_start]);
+        result.extend_from_slice(replacement);
+        copied = *match_end;
+    }
+    Some(Replacement {
+        start,
+        end,
+        text: result,
+        count: replaced.len(),
+    })
+}
+
+const CR: u16 = 0x000D;
+const LF: u16 = 0x000A;
+
+fn find_text(
+    text: &[u16],
+    pattern: &[u16],
+    from: usize,
+    forward: bool,
+    options: SearchOptions,
+) -> Option<(usize, usize)> {
+    let (text, pattern) = prepare_text(text, pattern, options);
+    scan_text(&text, &pattern, from, forward, options)
+}
+
+// The text and pattern as they are compared: borrowed as they are with Match case, or
+// case-folded copies without it
+fn prepare_text<'a>(
+    text: &'a [u16],
+    pattern: &'a [u16],
+    options: SearchOptions,
+) -> (Cow<'a, [u16]>, Cow<'a, [u16]>) {
+    if options.match_case {
+        (Cow::Borrowed(text), Cow::Borrowed(pattern))
+    } else {
+        (Cow::Owned(fold_case(text)), Cow::Owned(fold_case(pattern)))
+    }
+}
+
+// Search text already prepared by `prepare_text`
+fn scan_text(
+    text: &[u16],
+    pattern: &[u16],
+    from: usize,
+    forward: bool,
+    options: SearchOptions,
+) -> Option<(usize, usize)> {
+    if pattern.is_empty() || pattern.len() > text.len() {
+        return None;
+    }
+
+    let from = from.min(text.len());
+    let last_start = text.len() - pattern.len();
+    let is_match = |start: usize| {
+        text[start..start + pattern.len()] == *pattern
+            && (!options.whole_word || is_whole_word(text, start, start + pattern.len()))
+    };
+
+    let found = if forward {
+        (from..=last_start)
+            .find(|&start| is_match(start))
+            .or_else(|| {
+                if options.wrap_around {
+                    (0..from.min(last_start + 1)).find(|&start| is_match(start))
+                } else {
+                    None
+                }
+            })
+    } else {
+        let end = from.checked_sub(pattern.len()).map(|start| start + 1);
+        (0..end.unwrap_or(0))
+            .rev()
+            .find(|&start| is_match(start))
+            .or_else(|| {
+                if options.wrap_around {
+                    (end.unwrap_or(0)..=last_start)
+                        .rev()
+                        .find(|&start| is_match(start))
+                } else {
+                    None
+                }
+            })
+    };
+
+    found.map(|start| (start, start + pattern.len()))
+}
+
+fn find_regex(
+    text: &[u16],
+    regex: &Regex,
+    options: SearchOptions,
+    from: usize,
+    forward: bool,
+) -> Option<(usize, usize)> {
+    let haystack = haystack(text);
+    let matches = |from| {
+        regex_matches(text, &haystack, regex, options, from).filter(|(start, end)| start < end)
+    };
+
+    if forward {
+        matches(from)
+            .next()
+            .or_else(|| options.wrap_around.then(|| matches(0).next()).flatten())
+    } else {
+        let mut before = None;
+        let mut last = None;
+        for (start, end) in matches(0) {
+            if end <= from {
+                before = Some((start, end));
+            }
+            last = Some((start, end));
+        }
+        before.or(if options.wrap_around { last } else { None })
+    }
+}
+
+// Matches at or after the UTF-16 offset `from`, converted back to UTF-16 offsets
+fn regex_matches<'a>(
+    text: &'a [u16],
+    haystack: &'a str,
+    regex: &'a Regex,
+    options: SearchOptions,
+    from: usize,
+) -> impl Iterator<Item = (usize, usize)> + 'a {
+    let mut offsets = Offsets::new(haystack);
+    let mut byte = offsets.byte_at(from);
+    let mut last_end = None;
+    std::iter::from_fn(move || {
+        while byte <= haystack.len() {
+            let found = regex.find_at(haystack, byte)?;
+            // Step past an empty match so the search keeps moving
+            byte = match haystack[found.end()..].chars().next() {
+                Some(next) if found.is_empty() => found.end() + next.len_utf8(),
+                None if found.is_empty() => found.end() + 1,
+                _ => found.end(),
+            };
+            // Like `Regex::find_iter`, an empty match where the last match ended is skipped
+            if found.is_empty() && last_end == Some(found.start()) {
+                continue;
+            }
+            last_end = Some(found.end());
+            let range = (
+                offsets.start_at(found.start()),
+                offsets.position_at(found.end()),
+            );
+            if !options.whole_word || is_whole_word(text, range.0, range.1) {
+                return Some(range);
+            }
+        }
+        None
+    })
+}
+
+// The text as the regex sees it, with each CR written as CRLF
+fn haystack(text: &[u16]) -> String {
+    String::from_utf16_lossy(text).replace('\r', "\r\n")
+}
+
+// Expand the capture references in a replacement
+fn expand(captures: &regex::Captures, replacement: &[u16]) -> Vec<u16> {
+    let mut expanded = String::new();
+    captures.expand(&String::from_utf16_lossy(replacement), &mut expanded);
+    expanded.encode_utf16().collect()
+}
+
+// Converts between byte offsets in the haystack and UTF-16 offsets in the text. The offsets
+// asked for must not decrease, so a whole search walks the text only once.
+struct Offsets<'a> {
+    haystack: &'a str,
+    byte: usize,
+    position: usize,
+}
+
+impl<'a> Offsets<'a> {
+    fn new(haystack: &'a str) -> Self {
+        Offsets {
+            haystack,
+            byte: 0,
+            position: 0,
+        }
+    }
+
+    // Byte offset of a UTF-16 offset. A line start maps past the whole CRLF.
+    fn byte_at(&mut self, position: usize) -> usize {
+        while self.byte < self.haystack.len() && (self.position < position || self.at_added_lf()) {
+            self.step();
+        }
+        self.byte
+    }
+
+    // UTF-16 offset of a match end. Ending inside a CRLF includes the line break.
+    fn position_at(&mut self, byte: usize) -> usize {
+        while self.byte < byte {
+            self.step();
+        }
+        self.position
+    }
+
+    // UTF-16 offset of a match start. Starting inside a CRLF includes the line break.
+    fn start_at(&mut self, byte: usize) -> usize {
+        let position = self.position_at(byte);
+        if self.at_added_lf() {
+            position - 1
+        } else {
+            position
+        }
+    }
+
+    // The LF that `haystack` added after a CR has no UTF-16 offset of its own
+    fn at_added_lf(&self) -> bool {
+        let bytes = self.haystack.as_bytes();
+        self.byte > 0 && bytes[self.byte - 1] == b'\r' && bytes.get(self.byte) == Some(&b'\n')
+    }
+
+    fn step(&mut self) {
+        let Some(ch) = self.haystack[self.byte..].chars().next() else {
+            return;
+        };
+        if !self.at_added_lf() {
+            self.position += ch.len_utf16();
+        }
+        self.byte += ch.len_utf8();
+    }
+}
+
+// Lowercase each BMP code unit that has a single-unit lowercase form, keeping offsets intact
+fn fold_case(text: &[u16]) -> Vec<u16> {
+    text.iter()
+        .map(|&unit| {
+            let Some(ch) = char::from_u32(unit as u32) else {
+                return unit;
+            };
+            let mut lower = ch.to_lowercase();
+            match (lower.next(), lower.next()) {
+                (Some(folded), None) if folded.len_utf16() == 1 => folded as u16,
+                _ => unit,
+            }
+        })
+        .collect()
+}
+
+// Letters, digits and underscores make up words. Surrogates belong to supplementary
+// characters, which are treated as letters.
+fn is_word_unit(unit: u16) -> bool {
+    match char::from_u32(unit as u32) {
+        Some(ch) => ch.is_alphanumeric() || ch == '_',
+        None => true,
+    }
+}
+
+fn is_whole_word(text: &[u16], start: usize, end: usize) -> bool {
+    let before = start == 0 || !is_word_unit(text[start - 1]);
+    let after = end == text.len() || !is_word_unit(text[end]);
+    before && after
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utf16(text: &str) -> Vec<u16> {
+        text.encode_utf16().collect()
+    }
+
+    fn query(pattern: &str, options: SearchOptions) -> Query {
+        Query::new(&utf16(pattern), options).unwrap()
+    }
+
+    fn find_str(
+        text: &str,
+        pattern: &str,
+        from: usize,
+        forward: bool,
+        options: SearchOptions,
+    ) -> Option<(usize, usize)> {
+        find(&utf16(text), &query(pattern, options), from, forward)
+    }
+
+    fn regex_options() -> SearchOptions {
+        SearchOptions {
+            regex: true,
+            ..Default::default()
+        }
+    }
+
+    fn replace_all_str(text: &str, pattern: &str, options: SearchOptions, with: &str) -> String {
+        let text = utf16(text);
+        let replaced = replace_all(&text, &query(pattern, options), &utf16(with)).unwrap();
+        let mut result = text[..replaced.start].to_vec();
+        result.extend_from_slice(&replaced.text);
+        result.extend_from_slice(&text[replaced.end..]);
+        String::from_utf16(&result).unwrap()
+    }
+
+    #[test]
+    fn test_forward_and_backward() {
+        let options = SearchOptions::default();
+        assert_eq!(find_str("abc abc", "abc", 0, true, options), Some((0, 3)));
+        assert_eq!(find_str("abc abc", "abc", 1, true, options), Some((4, 7)));
+        assert_eq!(find_str("abc abc", "abc", 7, false, options), Some((4, 7)));
+        assert_eq!(find_str("abc abc", "abc", 6, false, options), Some((0, 3)));
+        assert_eq!(find_str("abc abc", "abc", 5, true, options), None);
+    }
+
+    #[test]
+    fn test_match_case() {
+        let mut options = SearchOptions::default();
+        assert_eq!(
+            find_str("Hello ÄÖ", "hello äö", 0, true, options),
+            Some((0, 8))
+        );
+        options.match_case = true;
+        assert_eq!(find_str("Hello", "hello", 0, true, options), None);
+    }
+
+    #[test]
+    fn test_whole_word() {
+        let options = SearchOptions {
+            whole_word: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            find_str("cat concat cat_ cat.", "cat", 1, true, options),
+            Some((16, 19))
+        );
+        assert_eq!(find_str("日本語 語", "語", 0, true, options), Some((4, 5)));
+    }
+
+    #[test]
+    fn test_wrap_around() {
+        let options = SearchOptions {
+            wrap_around: true,
+            ..Default::default()
+        };
+        assert_eq!(find_str("abc abc", "abc", 5, true, options), Some((0, 3)));
+        assert_eq!(find_str("abc abc", "abc", 2, false, options), Some((4, 7)));
+        assert_eq!(find_str("abc", "x", 1, true, options), None);
+    }
+
+    #[test]
+    fn test_surrogate_pairs_keep_offsets() {
+        let options = SearchOptions::default();
+        assert_eq!(find_str("😀A😀a", "a", 0, true, options), Some((2, 3)));
+        assert_eq!(find_str("😀A😀a", "a", 3, true, options), Some((5, 6)));
+        assert_eq!(find_str("😀A😀a", "😀", 1, true, options), Some((3, 5)));
+    }
+
+    #[test]
+    fn test_find_all_and_replace_all() {
+        let options = SearchOptions::default();
+        let text = utf16("aa-aaa-b");
+        let query = query("aa", options);
+        assert_eq!(find_all(&text, &query), vec![(0, 2), (3, 5)]);
+        assert_eq!(
+            replace_all(&text, &query, &utf16("x")),
+            Some(Replacement {
+                start: 0,
+                end: 5,
+                text: utf16("x-x"),
+                count: 2,
+            })
+        );
+        assert_eq!(replace_all(&text, &self::query("z", options), &[]), None);
+    }
+
+    #[test]
+    fn test_replace_all_many_matches() {
+        // A 4 MB log with one match per line: a search that copies or folds the text for
+        // every match would take hours here
+        let line = "2026-10-16 12:00:00 INFO request done\r";
+        let text = utf16(&line.repeat(100_000));
+        let started = std::time::Instant::now();
+        for options in [
+            SearchOptions::default(),
+            SearchOptions {
+                match_case: true,
+                ..Default::default()
+            },
+        ] {
+            let replaced = replace_all(&text, &query("info", options), &utf16("WARN"));
+            let expected = if options.match_case { 0 } else { 100_000 };
+            assert_eq!(replaced.map_or(0, |replaced| replaced.count), expected);
+        }
+        assert!(started.elapsed() < std::time::Duration::from_secs(30));
+    }
+
+    #[test]
+    fn test_regex_offsets_and_anchors() {
+        let options = regex_options();
+        // Offsets are RichEdit positions: each line break counts once
+        assert_eq!(
+            find_str("ab\rcd\r😀e", "e$", 0, true, options),
+            Some((8, 9))
+        );
+        assert_eq!(find_str("ab\rcd", "^c", 1, true, options), Some((3, 4)));
+        assert_eq!(find_str("ab\rcd", "b$", 0, true, options), Some((1, 2)));
+        // A line break matches \r\n, \r or \n and is selected as one position
+        assert_eq!(
+            find_str("ab\rcd", r"b\r\nc", 0, true, options),
+            Some((1, 4))
+        );
+        assert_eq!(find_str("ab\rcd", r"\n", 0, true, options), Some((2, 3)));
+        assert_eq!(find_str("ab\rcd", r"\r", 0, true, options), Some((2, 3)));
+        // . does not match line breaks
+        assert_eq!(find_str("ab\rcd", "b.c", 0, true, options), None);
+    }
+
+    #[test]
+    fn test_regex_find_direction_and_options() {
+        let options = regex_options();
+        assert_eq!(
+            find_str("a1 b22 c3", r"\d+", 2, true, options),
+            Some((4, 6))
+        );
+        assert_eq!(
+            find_str("a1 b22 c3", r"\d+", 6, false, options),
+            Some((4, 6))
+        );
+        assert_eq!(find_str("a1 b22 c3", r"\d+", 9, true, options), None);
+        // Empty matches cannot be selected
+        assert_eq!(find_str("ab", "x*", 0, true, options), None);
+
+        let wrap = SearchOptions {
+            wrap_around: true,
+            ..options
+        };
+        assert_eq!(find_str("a1 b22 c3", r"\d+", 9, true, wrap), Some((1, 2)));
+        assert_eq!(find_str("a1 b22 c3", r"\d+", 1, false, wrap), Some((8, 9)));
+
+        assert_eq!(find_str("ÄB", "äb", 0, true, options), Some((0, 2)));
+        let match_case = SearchOptions {
+            match_case: true,
+            ..options
+        };
+        assert_eq!(find_str("ÄB", "äb", 0, true, match_case), None);
+
+        let whole_word = SearchOptions {
+            whole_word: true,
+            ..options
+        };
+        assert_eq!(
+            find_str("err errors err", "err\\w*", 0, true, whole_word),
+            Some((0, 3))
+        );
+        assert_eq!(
+            find_str("xerr err", "err", 0, true, whole_word),
+            Some((5, 8))
+        );
+    }
+
+    #[test]
+    fn test_regex_replace() {
+        let options = regex_options();
+        assert_eq!(
+            replace_all_str("id=1\rid=22", r"^id=(\d+)$", options, "[$1]"),
+            "[1]\r[22]"
+        );
+        assert_eq!(
+            replace_all_str("ab cd", r"(?<first>\w)(\w)", options, "${2}${first}$$"),
+            "ba$ dc$"
+        );
+        // Empty matches are replaced too, once per position
+        assert_eq!(replace_all_str("ab\rc", "^", options, "> "), "> ab\r> c");
+        assert_eq!(replace_all_str("abc", "x*", options, "-"), "-a-b-c-");
+        // Line breaks inside a match are kept by $0
+        assert_eq!(
+            replace_all_str("a\rb", r"a\r\n", options, "$0$0"),
+            "a\r\na\r\nb"
+        );
+
+        let text = utf16("k=v k=w");
+        let query = query(r"(\w)=(\w)", options);
+        assert_eq!(
+            replace_match(&text, &query, (4, 7), &utf16("$2=$1")),
+            Some(utf16("w=k"))
+        );
+        assert_eq!(replace_match(&text, &query, (3, 7), &utf16("x")), None);
+    }
+
+    #[test]
+    fn test_regex_errors() {
+        assert_eq!(
+            Query::new(&utf16("(ab"), regex_options()).err(),
+            Some("unclosed group".to_string())
+        );
+        assert!(Query::new(&utf16("(ab"), SearchOptions::default()).is_ok());
+    }
+
+    #[test]
+    fn test_collapse_crlf() {
+        assert_eq!(collapse_crlf(&utf16("a\r\nb\rc\nd")), utf16("a\rb\rc\nd"));
+    }
+}
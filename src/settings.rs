@@ -0,0 +1,403 @@
+// User settings that last between sessions
+//
+// Settings are stored as key/value strings so that any backend (see `settings_store`) can
+// keep them. Missing keys and values that do not parse fall back to the defaults.
+
+use std::path::{Path, PathBuf};
+
+/// Keys of the recent files list, most recent first
+const RECENT_FILE_KEYS: [&str; 9] = [
+    "Recent1", "Recent2", "Recent3", "Recent4", "Recent5", "Recent6", "Recent7", "Recent8",
+    "Recent9",
+];
+
+/// How many files the File > Recent Files menu remembers
+pub const MAX_RECENT_FILES: usize = RECENT_FILE_KEYS.len();
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub word_wrap: bool,
+    pub status_bar: bool,
+    pub theme: ThemeMode,
+    pub font: FontSettings,
+    /// Editor zoom in percent
+    pub zoom: u32,
+    /// Position and size of the main window, None for the system default
+    pub window: Option<WindowPlacement>,
+    pub backup_on_save: bool,
+    /// Recently opened or saved files, most recent first
+    pub recent_files: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontSettings {
+    pub face: String,
+    /// Size in points, such as 10.5
+    pub size: f32,
+    /// Weight from 100 (thin) to 900 (black); 400 is normal and 700 is bold
+    pub weight: u32,
+}
+
+/// Which colors the window uses
+#[derive(Debug, Clone, PartialEq, Default)]
+pub enum ThemeMode {
+    /// Follow the Windows app mode setting, even when it changes while running
+    #[default]
+    System,
+    Light,
+    Dark,
+    /// A theme file in the themes folder, by file name without the extension
+    User(String),
+}
+
+/// The restored (not maximized) window rectangle
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowPlacement {
+    pub x: i32,
+    pub y: i32,
+    pub width: i32,
+    pub height: i32,
+    pub maximized: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            word_wrap: true,
+            status_bar: true,
+            theme: ThemeMode::System,
+            font: FontSettings::default(),
+            zoom: 100,
+            window: None,
+            backup_on_save: false,
+            recent_files: Vec::new(),
+        }
+    }
+}
+
+impl Default for FontSettings {
+    fn default() -> Self {
+        FontSettings {
+            face: "MS Gothic".to_string(),
+            size: 12.0,
+            weight: 400,
+        }
+    }
+}
+
+impl ThemeMode {
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "" => None,
+            "system" => Some(ThemeMode::System),
+            "light" => Some(ThemeMode::Light),
+            "dark" => Some(ThemeMode::Dark),
+            _ => Some(ThemeMode::User(value.to_string())),
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            ThemeMode::System => "System",
+            ThemeMode::Light => "Light",
+            ThemeMode::Dark => "Dark",
+            ThemeMode::User(name) => name,
+        }
+    }
+}
+
+impl Settings {
+    /// Read settings from key/value pairs. Unknown keys are ignored.
+    pub fn from_entries<'a>(entries: impl IntoIterator<Item = (&'a str, &'a str)>) -> Self {
+        let mut settings = Settings::default();
+        let mut window = [None; 4];
+        let mut maximized = false;
+        // Older versions saved DarkMode=0/1 instead of Theme
+        let mut theme = None;
+        let mut dark_mode = None;
+        let mut recent_files = [const { None }; MAX_RECENT_FILES];
+
+        for (key, value) in entries {
+            let key = key.trim();
+            let value = value.trim();
+            match key {
+                "WordWrap" => set(&mut settings.word_wrap, parse_bool(value)),
+                "StatusBar" => set(&mut settings.status_bar, parse_bool(value)),
+                "Theme" => theme = ThemeMode::parse(value),
+                "DarkMode" => dark_mode = parse_bool(value),
+                "FontFace" if !value.is_empty() => settings.font.face = value.to_string(),
+                "FontSize" => set(
+                    &mut settings.font.size,
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|size| (1.0..=1638.0).contains(size)),
+                ),
+                "FontWeight" => set(
+                    &mut settings.font.weight,
+                    value
+                        .parse()
+                        .ok()
+                        .filter(|weight| (1..=1000).contains(weight)),
+                ),
+                "Zoom" => set(
+                    &mut settings.zoom,
+                    value.parse().ok().filter(|zoom| *zoom > 0),
+                ),
+                "WindowX" => window[0] = value.parse().ok(),
+                "WindowY" => window[1] = value.parse().ok(),
+                "WindowWidth" => window[2] = value.parse().ok().filter(|width| *width > 0),
+                "WindowHeight" => window[3] = value.parse().ok().filter(|height| *height > 0),
+                "WindowMaximized" => maximized = parse_bool(value).unwrap_or(false),
+                "BackupOnSave" => set(&mut settings.backup_on_save, parse_bool(value)),
+                _ => {
+                    if let Some(index) = RECENT_FILE_KEYS.iter().position(|name| *name == key)
+                        && !value.is_empty()
+                    {
+                        recent_files[index] = Some(PathBuf::from(value));
+                    }
+                }
+            }
+        }
+        // Gaps left by missing or empty keys close up
+        for path in recent_files.into_iter().flatten() {
+            add_recent_file_last(&mut settings.recent_files, path);
+        }
+
+        let legacy_theme = dark_mode.map(|dark| {
+            if dark {
+                ThemeMode::Dark
+            } else {
+                ThemeMode::Light
+            }
+        });
+        set(&mut settings.theme, theme.or(legacy_theme));
+
+        // The window position is only used when all of it is there
+        if let [Some(x), Some(y), Some(width), Some(height)] = window {
+            settings.window = Some(WindowPlacement {
+                x,
+                y,
+                width,
+                height,
+                maximized,
+            });
+        }
+        settings
+    }
+
+    /// Key/value pairs for every setting, in a fixed order
+    pub fn to_entries(&self) -> Vec<(&'static str, String)> {
+        let mut entries = vec![
+            ("WordWrap", format_bool(self.word_wrap)),
+            ("StatusBar", format_bool(self.status_bar)),
+            ("Theme", self.theme.name().to_string()),
+            ("FontFace", self.font.face.clone()),
+            ("FontSize", self.font.size.to_string()),
+            ("FontWeight", self.font.weight.to_string()),
+            ("Zoom", self.zoom.to_string()),
+        ];
+        if let Some(window) = self.window {
+            entries.extend([
+                ("WindowX", window.x.to_string()),
+                ("WindowY", window.y.to_string()),
+                ("WindowWidth", window.width.to_string()),
+                ("WindowHeight", window.height.to_string()),
+                ("WindowMaximized", format_bool(window.maximized)),
+            ]);
+        }
+        entries.push(("BackupOnSave", format_bool(self.backup_on_save)));
+        // Unused keys are written empty so a shorter list replaces a longer one
+        entries.extend(RECENT_FILE_KEYS.iter().enumerate().map(|(index, key)| {
+            let path = self.recent_files.get(index);
+            (
+                *key,
+                path.map_or(String::new(), |path| path.display().to_string()),
+            )
+        }));
+        entries
+    }
+}
+
+/// Put a file at the top of the recent files list, removing any earlier entry for it and
+/// the oldest entries past `MAX_RECENT_FILES`
+pub fn add_recent_file(recent_files: &mut Vec<PathBuf>, path: &Path) {
+    recent_files.retain(|recent| !same_path(recent, path));
+    recent_files.insert(0, path.to_path_buf());
+    recent_files.truncate(MAX_RECENT_FILES);
+}
+
+// Append a file read from the settings unless it is already listed
+fn add_recent_file_last(recent_files: &mut Vec<PathBuf>, path: PathBuf) {
+    if !recent_files.iter().any(|recent| same_path(recent, &path)) {
+        recent_files.push(path);
+    }
+}
+
+// Windows file names ignore case
+fn same_path(a: &Path, b: &Path) -> bool {
+    a.as_os_str().eq_ignore_ascii_case(b.as_os_str())
+}
+
+fn set<T>(setting: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *setting = value;
+    }
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    match value.to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+fn format_bool(value: bool) -> String {
+    if value { "1" } else { "0" }.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(settings: &Settings) -> Settings {
+        let entries = settings.to_entries();
+        Settings::from_entries(entries.iter().map(|(key, value)| (*key, value.as_str())))
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let settings = Settings::default();
+        assert_eq!(round_trip(&settings), settings);
+
+        let settings = Settings {
+            word_wrap: false,
+            status_bar: false,
+            theme: ThemeMode::Dark,
+            font: FontSettings {
+                face: "BIZ UDゴシック".to_string(),
+                size: 10.5,
+                weight: 700,
+            },
+            zoom: 150,
+            window: Some(WindowPlacement {
+                x: -1200,
+                y: 40,
+                width: 1000,
+                height: 700,
+                maximized: true,
+            }),
+            backup_on_save: true,
+            recent_files: vec![
+                PathBuf::from("C:\\Users\\ryu\\メモ.txt"),
+                PathBuf::from("D:\\notes = todo.md"),
+            ],
+        };
+        assert_eq!(round_trip(&settings), settings);
+    }
+
+    #[test]
+    fn test_recent_files() {
+        let settings = Settings::from_entries([
+            ("Recent1", "C:\\a.txt"),
+            ("Recent2", ""),
+            ("Recent3", "C:\\b.txt"),
+            ("Recent4", "c:\\A.TXT"),
+            ("Recent10", "C:\\c.txt"),
+        ]);
+        assert_eq!(
+            settings.recent_files,
+            vec![PathBuf::from("C:\\a.txt"), PathBuf::from("C:\\b.txt")]
+        );
+
+        // Every key is written, so entries dropped from the list are cleared
+        let entries = settings.to_entries();
+        let recent: Vec<_> = entries
+            .iter()
+            .filter(|(key, _)| key.starts_with("Recent"))
+            .collect();
+        assert_eq!(recent.len(), MAX_RECENT_FILES);
+        assert_eq!(recent[2], &("Recent3", String::new()));
+    }
+
+    #[test]
+    fn test_add_recent_file() {
+        let mut recent_files = Vec::new();
+        for index in 0..12 {
+            add_recent_file(&mut recent_files, Path::new(&format!("C:\\{}.txt", index)));
+        }
+        assert_eq!(recent_files.len(), MAX_RECENT_FILES);
+        assert_eq!(recent_files[0], PathBuf::from("C:\\11.txt"));
+        assert_eq!(recent_files[8], PathBuf::from("C:\\3.txt"));
+
+        // Opening a listed file again moves it to the top
+        add_recent_file(&mut recent_files, Path::new("c:\\5.TXT"));
+        assert_eq!(recent_files.len(), MAX_RECENT_FILES);
+        assert_eq!(recent_files[0], PathBuf::from("c:\\5.TXT"));
+        assert_eq!(recent_files[1], PathBuf::from("C:\\11.txt"));
+    }
+
+    #[test]
+    fn test_missing_and_invalid_values() {
+        let settings = Settings::from_entries([
+            ("WordWrap", "maybe"),
+            ("StatusBar", " off "),
+            ("FontSize", "0"),
+            ("FontSize", "NaN"),
+            ("Zoom", "-5"),
+            ("FontFace", ""),
+            ("Unknown", "1"),
+        ]);
+        assert_eq!(
+            settings,
+            Settings {
+                status_bar: false,
+                ..Settings::default()
+            }
+        );
+    }
+
+    #[test]
+    fn test_theme() {
+        let theme = |entries: &[(&'static str, &'static str)]| {
+            Settings::from_entries(entries.iter().copied()).theme
+        };
+        assert_eq!(theme(&[("Theme", "light")]), ThemeMode::Light);
+        assert_eq!(
+            theme(&[("Theme", "Solarized Dark")]),
+            ThemeMode::User("Solarized Dark".to_string())
+        );
+        assert_eq!(theme(&[("Theme", "")]), ThemeMode::System);
+        // The old DarkMode key is read when there is no Theme
+        assert_eq!(theme(&[("DarkMode", "1")]), ThemeMode::Dark);
+        assert_eq!(theme(&[("DarkMode", "0")]), ThemeMode::Light);
+        assert_eq!(
+            theme(&[("DarkMode", "1"), ("Theme", "System")]),
+            ThemeMode::System
+        );
+    }
+
+    #[test]
+    fn test_window_needs_every_value() {
+        let settings = Settings::from_entries([("WindowX", "10"), ("WindowY", "20")]);
+        assert_eq!(settings.window, None);
+
+        let settings = Settings::from_entries([
+            ("WindowX", "10"),
+            ("WindowY", "20"),
+            ("WindowWidth", "300"),
+            ("WindowHeight", "200"),
+        ]);
+        assert_eq!(
+            settings.window,
+            Some(WindowPlacement {
+                x: 10,
+                y: 20,
+                width: 300,
+                height: 200,
+                maximized: false,
+            })
+        );
+    }
+}
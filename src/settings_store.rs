@@ -0,0 +1,160 @@
+// Where settings are kept between sessions
+//
+// Settings go to the registry, unless an nn.ini file sits next to the executable. Then
+// they are kept in that file (portable mode). A `SettingsStore` only moves key/value
+// strings; `Settings` decides what they mean.
+
+use crate::ini;
+use crate::settings::Settings;
+use std::path::PathBuf;
+use windows::Win32::Foundation::ERROR_SUCCESS;
+use windows::Win32::System::Registry::{
+    HKEY, HKEY_CURRENT_USER, KEY_READ, REG_SZ, REG_VALUE_TYPE, RegCloseKey, RegCreateKeyW,
+    RegEnumValueW, RegOpenKeyExW, RegSetValueExW,
+};
+use windows::core::{HSTRING, PWSTR};
+
+pub trait SettingsStore {
+    /// Stored key/value pairs, or None if nothing has been saved yet
+    fn load(&self) -> Option<Vec<(String, String)>>;
+    /// Write the values, keeping any others. Returns false if they could not be written.
+    fn save(&self, entries: &[(&str, String)]) -> bool;
+}
+
+/// String values under HKEY_CURRENT_USER\Software\Normal Notepad
+pub struct RegistryStore;
+
+const REGISTRY_KEY: &str = "Software\\Normal Notepad";
+
+impl SettingsStore for RegistryStore {
+    fn load(&self) -> Option<Vec<(String, String)>> {
+        unsafe {
+            let mut key = HKEY::default();
+            if RegOpenKeyExW(
+                HKEY_CURRENT_USER,
+                &HSTRING::from(REGISTRY_KEY),
+                None,
+                KEY_READ,
+                &mut key,
+            ) != ERROR_SUCCESS
+            {
+                return None;
+            }
+
+            let mut entries = Vec::new();
+            let mut name = vec![0u16; 256];
+            let mut data = vec![0u16; 1024];
+            for index in 0.. {
+                let mut name_len = name.len() as u32;
+                let mut data_size = (data.len() * 2) as u32;
+                let mut value_type = 0u32;
+                let result = RegEnumValueW(
+                    key,
+                    index,
+                    Some(PWSTR(name.as_mut_ptr())),
+                    &mut name_len,
+                    None,
+                    Some(&mut value_type),
+                    Some(data.as_mut_ptr() as *mut u8),
+                    Some(&mut data_size),
+                );
+                if result != ERROR_SUCCESS {
+                    // ERROR_NO_MORE_ITEMS, or a value too long to be one of ours
+                    break;
+                }
+                if REG_VALUE_TYPE(value_type) != REG_SZ {
+                    continue;
+                }
+                // The data may or may not include the terminating null
+                let data_len = (data_size as usize / 2).min(data.len());
+                let value = &data[..data_len];
+                let value = value.strip_suffix(&[0]).unwrap_or(value);
+                entries.push((
+                    String::from_utf16_lossy(&name[..name_len as usize]),
+                    String::from_utf16_lossy(value),
+                ));
+            }
+            let _ = RegCloseKey(key);
+            Some(entries)
+        }
+    }
+
+    fn save(&self, entries: &[(&str, String)]) -> bool {
+        unsafe {
+            let mut key = HKEY::default();
+            if RegCreateKeyW(HKEY_CURRENT_USER, &HSTRING::from(REGISTRY_KEY), &mut key)
+                != ERROR_SUCCESS
+            {
+                return false;
+            }
+
+            let mut saved = true;
+            for (name, value) in entries {
+                let data: Vec<u8> = value
+                    .encode_utf16()
+                    .chain(Some(0))
+                    .flat_map(u16::to_le_bytes)
+                    .collect();
+                saved &= RegSetValueExW(key, &HSTRING::from(*name), None, REG_SZ, Some(&data))
+                    == ERROR_SUCCESS;
+            }
+            let _ = RegCloseKey(key);
+            saved
+        }
+    }
+}
+
+/// The [Settings] section of an INI file (see `ini` for the format)
+pub struct IniFileStore {
+    path: PathBuf,
+}
+
+impl IniFileStore {
+    pub fn new(path: PathBuf) -> Self {
+        IniFileStore { path }
+    }
+
+    fn read(&self) -> Option<String> {
+        let bytes = std::fs::read(&self.path).ok()?;
+        let text = String::from_utf8_lossy(&bytes);
+        Some(text.strip_prefix('\u{FEFF}').unwrap_or(&text).to_string())
+    }
+}
+
+impl SettingsStore for IniFileStore {
+    fn load(&self) -> Option<Vec<(String, String)>> {
+        self.read().map(|text| ini::parse(&text))
+    }
+
+    fn save(&self, entries: &[(&str, String)]) -> bool {
+        let existing = self.read().unwrap_or_default();
+        std::fs::write(&self.path, ini::update(&existing, entries)).is_ok()
+    }
+}
+
+/// nn.ini next to the executable, if it exists
+fn portable_settings_path() -> Option<PathBuf> {
+    let path = std::env::current_exe().ok()?.with_file_name("nn.ini");
+    path.is_file().then_some(path)
+}
+
+fn settings_store() -> Box<dyn SettingsStore> {
+    match portable_settings_path() {
+        Some(path) => Box::new(IniFileStore::new(path)),
+        None => Box::new(RegistryStore),
+    }
+}
+
+/// Load the saved settings, or the defaults if there are none
+pub fn load_settings() -> Settings {
+    let entries = settings_store().load().unwrap_or_default();
+    Settings::from_entries(
+        entries
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str())),
+    )
+}
+
+pub fn save_settings(settings: &Settings) -> bool {
+    settings_store().save(&settings.to_entries())
+}
@@ -1,27 +1,29 @@
 use crate::file_io::FileEncoding;
+use crate::find_dialog::selection;
 use crate::i18n::get_string;
 use crate::line_column::calculate_line_column;
-use crate::theme::{
-    DARK_SEPARATOR, DARK_STATUSBAR_BG, DARK_STATUSBAR_TEXT, LIGHT_SEPARATOR, LIGHT_STATUSBAR_BG,
-    LIGHT_STATUSBAR_TEXT, should_use_dark_mode,
-};
+use crate::line_ending::LineEnding;
+use crate::line_index::{LineIndex, infer_edit};
+use crate::theme::active_theme;
 use std::sync::Mutex;
 use std::sync::atomic::{AtomicBool, Ordering};
-use windows::Win32::Foundation::{HWND, LPARAM, LRESULT, RECT, WPARAM};
+use windows::Win32::Foundation::{COLORREF, HWND, LPARAM, LRESULT, RECT, WPARAM};
 use windows::Win32::Graphics::Gdi::{
     BeginPaint, CreateFontW, CreatePen, DRAW_TEXT_FORMAT, DeleteObject, DrawTextW, EndPaint,
     FONT_CHARSET, FONT_CLIP_PRECISION, FONT_OUTPUT_PRECISION, FONT_QUALITY, HBRUSH, InvalidateRect,
     LineTo, MoveToEx, PAINTSTRUCT, PS_SOLID, SelectObject,
 };
 use windows::Win32::System::LibraryLoader::GetModuleHandleW;
+use windows::Win32::UI::Controls::WM_MOUSELEAVE;
+use windows::Win32::UI::Input::KeyboardAndMouse::{TME_LEAVE, TRACKMOUSEEVENT, TrackMouseEvent};
 use windows::Win32::UI::WindowsAndMessaging::{
-    DefWindowProcW, GetClientRect, GetWindowLongPtrW, IDC_ARROW, LoadCursorW, SendMessageW,
-    SetCursor, SetWindowTextW, WINDOW_LONG_PTR_INDEX, WM_GETTEXT, WM_GETTEXTLENGTH, WM_PAINT,
-    WM_SETCURSOR, WNDCLASS_STYLES, WNDCLASSW,
+    DefWindowProcW, GWLP_USERDATA, GetClientRect, GetDlgCtrlID, GetParent, GetWindowLongPtrW,
+    IDC_ARROW, IDC_HAND, LoadCursorW, SendMessageW, SetCursor, SetWindowLongPtrW, SetWindowTextW,
+    WINDOW_LONG_PTR_INDEX, WM_COMMAND, WM_GETTEXT, WM_GETTEXTLENGTH, WM_LBUTTONUP, WM_MOUSEMOVE,
+    WM_PAINT, WM_SETCURSOR, WNDCLASS_STYLES, WNDCLASSW,
 };
 use windows::core::PCWSTR;
 
-pub const EM_GETSEL: u32 = 0x00B0;
 pub const EM_GETZOOM: u32 = 0x04E0;
 
 // Helper function to convert raw SendMessageW result to i32
@@ -40,7 +42,219 @@ fn msg_as_usize(result: LRESULT) -> usize {
 static COUNT_NEWLINE_AS_ONE: AtomicBool = AtomicBool::new(true);
 
 // Cache for previous status bar values
-static LAST_STATUS: Mutex<Option<(i32, i32, i32, i32, FileEncoding)>> = Mutex::new(None);
+static LAST_STATUS: Mutex<Option<(i32, i32, i32, i32, FileEncoding, bool)>> = Mutex::new(None);
+
+// Line index of the editor text, kept up to date from edit notifications so the status
+// bar does not copy the whole text after every message
+struct TextTracking {
+    index: Option<LineIndex>,
+    // Selection before the next edit, used to work out what the edit changed
+    selection: (usize, usize),
+    // Selection before the latest EN_SELCHANGE, for an edit that keeps the text length:
+    // its EN_SELCHANGE may arrive first and cannot tell that the text changed
+    previous_selection: (usize, usize),
+    // Set when EN_SELCHANGE arrived first and already applied the edit for EN_CHANGE
+    edit_applied: bool,
+    // Line and column of the caret until the text or selection changes
+    position: Option<(i32, i32)>,
+}
+
+static TEXT_TRACKING: Mutex<TextTracking> = Mutex::new(TextTracking {
+    index: None,
+    selection: (0, 0),
+    previous_selection: (0, 0),
+    edit_applied: false,
+    position: None,
+});
+
+// RichEdit messages and structures for reading the text without copying all of it
+const EM_GETTEXTRANGE: u32 = 0x044B;
+const EM_GETTEXTLENGTHEX: u32 = 0x045F;
+const GTL_PRECISE: u32 = 2;
+const GTL_NUMCHARS: u32 = 8;
+const CP_UNICODE: u32 = 1200;
+
+#[repr(C)]
+struct GetTextLengthEx {
+    flags: u32,
+    codepage: u32,
+}
+
+#[repr(C)]
+struct CharRange {
+    cp_min: i32,
+    cp_max: i32,
+}
+
+#[repr(C)]
+struct TextRangeW {
+    chrg: CharRange,
+    lpstr_text: *mut u16,
+}
+
+// Whether the current encoding was guessed by the detector without much confidence
+static ENCODING_AMBIGUOUS: AtomicBool = AtomicBool::new(false);
+
+/// Mark the displayed encoding as an uncertain guess (or clear the mark)
+pub fn set_encoding_ambiguous(ambiguous: bool) {
+    ENCODING_AMBIGUOUS.store(ambiguous, Ordering::SeqCst);
+}
+
+/// Forget the line index before the whole text is replaced; the next status bar update
+/// rebuilds it
+pub fn reset_line_index() {
+    if let Ok(mut tracking) = TEXT_TRACKING.lock() {
+        tracking.index = None;
+        tracking.edit_applied = false;
+        tracking.position = None;
+    }
+}
+
+/// Update the line index for an EN_CHANGE from the editor
+pub fn text_changed(edit_hwnd: HWND) {
+    let Ok(mut tracking) = TEXT_TRACKING.lock() else {
+        return;
+    };
+    tracking.position = None;
+    let Some(index_len) = tracking.index.as_ref().map(LineIndex::len) else {
+        return;
+    };
+    let len = text_length(edit_hwnd);
+    let selection = selection(edit_hwnd);
+    if len != index_len {
+        apply_typed_edit(edit_hwnd, &mut tracking, selection, len);
+    } else if !tracking.edit_applied {
+        // Overtype, or typing over a selection of the same length. If EN_SELCHANGE came
+        // first it already replaced the selection before the edit.
+        if selection == tracking.selection {
+            tracking.selection = tracking.previous_selection;
+        }
+        if selection == tracking.selection {
+            // Nothing shows where the text changed
+            tracking.index = None;
+        }
+        apply_typed_edit(edit_hwnd, &mut tracking, selection, len);
+    }
+    tracking.edit_applied = false;
+}
+
+/// Remember the selection for an EN_SELCHANGE from the editor. When the text has
+/// already changed, the edit is applied here and EN_CHANGE has nothing left to do.
+pub fn selection_changed(edit_hwnd: HWND) {
+    let Ok(mut tracking) = TEXT_TRACKING.lock() else {
+        return;
+    };
+    tracking.position = None;
+    let selection = selection(edit_hwnd);
+    if let Some(index_len) = tracking.index.as_ref().map(LineIndex::len) {
+        let len = text_length(edit_hwnd);
+        if len != index_len {
+            apply_typed_edit(edit_hwnd, &mut tracking, selection, len);
+            tracking.edit_applied = true;
+        }
+    }
+    tracking.previous_selection = tracking.selection;
+    tracking.selection = selection;
+}
+
+// Apply the edit that turned the remembered selection into `selection`, or drop the
+// index when it cannot be worked out
+fn apply_typed_edit(
+    edit_hwnd: HWND,
+    tracking: &mut TextTracking,
+    selection: (usize, usize),
+    len: usize,
+) {
+    let old_selection = tracking.selection;
+    tracking.selection = selection;
+    let Some(index) = tracking.index.as_mut() else {
+        return;
+    };
+    let applied = infer_edit(old_selection, index.len(), selection, len).is_some_and(
+        |(start, removed, inserted)| {
+            let (range_start, range_end) = index.edit_range(start, removed, inserted);
+            let text = text_range(edit_hwnd, range_start, range_end);
+            index.apply_edit(start, removed, inserted, &text)
+        },
+    );
+    if !applied {
+        tracking.index = None;
+    }
+}
+
+// Length of the editor text in RichEdit positions, with each line break counted once
+fn text_length(edit_hwnd: HWND) -> usize {
+    let length = GetTextLengthEx {
+        flags: GTL_NUMCHARS | GTL_PRECISE,
+        codepage: CP_UNICODE,
+    };
+    unsafe {
+        msg_as_usize(SendMessageW(
+            edit_hwnd,
+            EM_GETTEXTLENGTHEX,
+            Some(WPARAM(&length as *const GetTextLengthEx as usize)),
+            Some(LPARAM(0)),
+        ))
+    }
+}
+
+// Text between two RichEdit positions
+fn text_range(edit_hwnd: HWND, start: usize, end: usize) -> Vec<u16> {
+    if end <= start {
+        return Vec::new();
+    }
+    let mut buffer = vec![0u16; end - start + 1];
+    let mut range = TextRangeW {
+        chrg: CharRange {
+            cp_min: start as i32,
+            cp_max: end as i32,
+        },
+        lpstr_text: buffer.as_mut_ptr(),
+    };
+    let copied = unsafe {
+        msg_as_usize(SendMessageW(
+            edit_hwnd,
+            EM_GETTEXTRANGE,
+            Some(WPARAM(0)),
+            Some(LPARAM(&mut range as *mut TextRangeW as isize)),
+        ))
+    };
+    buffer.truncate(copied.min(end - start));
+    buffer
+}
+
+// Line, column and character count from the line index, rebuilding it if needed
+fn text_status(edit_hwnd: HWND) -> (i32, i32, usize) {
+    let Ok(mut tracking) = TEXT_TRACKING.lock() else {
+        return (1, 1, 0);
+    };
+    let selection = selection(edit_hwnd);
+    if tracking.index.is_none() {
+        let text = text_range(edit_hwnd, 0, text_length(edit_hwnd));
+        tracking.index = Some(LineIndex::new(&text));
+        tracking.selection = selection;
+        tracking.edit_applied = false;
+        tracking.position = None;
+    }
+    let Some(index) = tracking.index.as_ref() else {
+        return (1, 1, 0);
+    };
+    let char_count = index.char_count();
+
+    let position = match tracking.position {
+        Some(position) => position,
+        None => {
+            let caret = selection.0.min(index.len());
+            let line = index.line_of(caret);
+            let prefix = text_range(edit_hwnd, index.line_start(line), caret);
+            let prefix_len = prefix.len() as i32;
+            let (_, col) = calculate_line_column(&String::from_utf16_lossy(&prefix), prefix_len);
+            (line as i32 + 1, col)
+        }
+    };
+    tracking.position = Some(position);
+    (position.0, position.1, char_count)
+}
 
 // Separator window procedure for thin light gray lines (vertical or horizontal)
 pub extern "system" fn separator_proc(
@@ -65,12 +279,8 @@ pub extern "system" fn separator_proc(
                 let mut rect = RECT::default();
                 let _ = GetClientRect(hwnd, &mut rect);
 
-                // Separator color based on dark mode
-                let separator_color = if should_use_dark_mode() {
-                    DARK_SEPARATOR
-                } else {
-                    LIGHT_SEPARATOR
-                };
+                // Separator color from the theme
+                let separator_color = COLORREF(active_theme().separator);
                 let pen = CreatePen(PS_SOLID, 1, separator_color);
                 if !pen.is_invalid() {
                     let old_pen = SelectObject(hdc, pen.into());
@@ -112,12 +322,51 @@ pub extern "system" fn status_text_proc(
     unsafe {
         match msg {
             WM_SETCURSOR => {
-                // Set cursor to default arrow
-                if let Ok(cursor) = LoadCursorW(None, IDC_ARROW) {
+                // Clickable segments show a hand, the rest the default arrow
+                let cursor_id = if is_clickable(hwnd) {
+                    IDC_HAND
+                } else {
+                    IDC_ARROW
+                };
+                if let Ok(cursor) = LoadCursorW(None, cursor_id) {
                     SetCursor(Some(cursor));
                 }
                 LRESULT(1)
             }
+            WM_MOUSEMOVE => {
+                // Highlight until the mouse leaves the segment
+                if is_clickable(hwnd) && GetWindowLongPtrW(hwnd, GWLP_USERDATA) == 0 {
+                    SetWindowLongPtrW(hwnd, GWLP_USERDATA, 1);
+                    let mut track = TRACKMOUSEEVENT {
+                        cbSize: std::mem::size_of::<TRACKMOUSEEVENT>() as u32,
+                        dwFlags: TME_LEAVE,
+                        hwndTrack: hwnd,
+                        dwHoverTime: 0,
+                    };
+                    let _ = TrackMouseEvent(&mut track);
+                    let _ = InvalidateRect(Some(hwnd), None, true);
+                }
+                LRESULT(0)
+            }
+            WM_MOUSELEAVE => {
+                SetWindowLongPtrW(hwnd, GWLP_USERDATA, 0);
+                let _ = InvalidateRect(Some(hwnd), None, true);
+                LRESULT(0)
+            }
+            WM_LBUTTONUP => {
+                // Let the main window handle the click like a menu command
+                if is_clickable(hwnd)
+                    && let Ok(parent) = GetParent(hwnd)
+                {
+                    SendMessageW(
+                        parent,
+                        WM_COMMAND,
+                        Some(WPARAM(GetDlgCtrlID(hwnd) as usize)),
+                        Some(LPARAM(hwnd.0 as isize)),
+                    );
+                }
+                LRESULT(0)
+            }
             WM_PAINT => {
                 // Paint text using STATIC control behavior
                 use windows::Win32::Graphics::Gdi::{
@@ -131,13 +380,16 @@ pub extern "system" fn status_text_proc(
                 let mut rect = RECT::default();
                 let _ = GetClientRect(hwnd, &mut rect);
 
-                // Fill background based on dark mode
+                // Fill background with the theme colors
                 use windows::Win32::Graphics::Gdi::CreateSolidBrush;
-                let (bg_color, text_color) = if should_use_dark_mode() {
-                    (DARK_STATUSBAR_BG, DARK_STATUSBAR_TEXT)
+                let hovered = GetWindowLongPtrW(hwnd, GWLP_USERDATA) != 0;
+                let theme = active_theme();
+                let bg_color = COLORREF(if hovered {
+                    theme.statusbar_hover
                 } else {
-                    (LIGHT_STATUSBAR_BG, LIGHT_STATUSBAR_TEXT)
-                };
+                    theme.statusbar_bg
+                });
+                let text_color = COLORREF(theme.statusbar_text);
                 let brush = CreateSolidBrush(bg_color);
                 FillRect(hdc, &rect, brush);
                 let _ = DeleteObject(brush.into());
@@ -213,6 +465,11 @@ pub extern "system" fn status_text_proc(
     }
 }
 
+// Status text windows created with a control ID act as buttons
+fn is_clickable(hwnd: HWND) -> bool {
+    unsafe { GetDlgCtrlID(hwnd) != 0 }
+}
+
 // Register status bar window classes
 pub unsafe fn register_status_bar_classes() {
     unsafe {
@@ -252,6 +509,22 @@ pub unsafe fn register_status_bar_classes() {
     }
 }
 
+/// Display name of an encoding
+pub fn encoding_name(encoding: FileEncoding) -> String {
+    match encoding {
+        FileEncoding::Utf8 => "UTF-8".to_string(),
+        FileEncoding::Utf8Bom => "UTF-8 (BOM)".to_string(),
+        FileEncoding::Utf16Le => "UTF-16 LE".to_string(),
+        FileEncoding::Utf16Be => "UTF-16 BE".to_string(),
+        FileEncoding::Utf32Le => "UTF-32 LE".to_string(),
+        FileEncoding::Utf32Be => "UTF-32 BE".to_string(),
+        FileEncoding::ShiftJis => "Shift-JIS".to_string(),
+        FileEncoding::EucJp => "EUC-JP".to_string(),
+        FileEncoding::Iso2022Jp => "ISO-2022-JP".to_string(),
+        FileEncoding::Auto => get_string("ENCODING_AUTO"),
+    }
+}
+
 pub fn update_status_bar(
     edit_hwnd: HWND,
     char_hwnd: HWND,
@@ -262,82 +535,18 @@ pub fn update_status_bar(
 ) {
     unsafe {
         if edit_hwnd != HWND::default() {
-            // Get cursor position using EM_GETSEL
-            let mut start_pos: i32 = 0;
-            let mut end_pos: i32 = 0;
-            SendMessageW(
-                edit_hwnd,
-                EM_GETSEL,
-                Some(WPARAM(&mut start_pos as *mut i32 as usize)),
-                Some(LPARAM(&mut end_pos as *mut i32 as isize)),
-            );
-
-            // Get text length
-            let text_length = msg_as_i32(SendMessageW(
-                edit_hwnd,
-                WM_GETTEXTLENGTH,
-                Some(WPARAM(0)),
-                Some(LPARAM(0)),
-            ));
-
-            let text_str = if text_length > 0 {
-                // Allocate buffer and get text using WM_GETTEXT
-                let mut buffer = vec![0u16; (text_length + 1) as usize];
-                let actual_len = msg_as_usize(SendMessageW(
-                    edit_hwnd,
-                    WM_GETTEXT,
-                    Some(WPARAM((text_length + 1) as usize)),
-                    Some(LPARAM(buffer.as_mut_ptr() as isize)),
-                ));
-
-                if actual_len > 0 {
-                    String::from_utf16_lossy(&buffer[..actual_len.min(text_length as usize)])
-                        .to_string()
-                } else {
-                    String::new()
-                }
-            } else {
-                String::new()
-            };
-
-            // Convert char position to UTF-16 code unit position
-            let mut utf16_pos = 0i32;
-            let mut char_idx = 0i32;
-            let mut chars_iter = text_str.chars().peekable();
-
-            while let Some(ch) = chars_iter.next() {
-                if char_idx >= start_pos {
-                    break;
-                }
-
-                if ch == '\r' && chars_iter.peek() == Some(&'\n') {
-                    chars_iter.next();
-                    utf16_pos += 2;
-                } else {
-                    utf16_pos += if ch > '\u{FFFF}' { 2 } else { 1 };
-                }
-
-                char_idx += 1;
-            }
-
-            // Calculate line and column
-            let (display_line, display_col) = calculate_line_column(&text_str, utf16_pos);
+            let (display_line, display_col, index_char_count) = text_status(edit_hwnd);
 
             // Get total character count
             let char_count = if COUNT_NEWLINE_AS_ONE.load(Ordering::SeqCst) {
-                let mut count = 0i32;
-                let mut chars = text_str.chars().peekable();
-                while let Some(ch) = chars.next() {
-                    if ch == '\r' && chars.peek() == Some(&'\n') {
-                        chars.next();
-                        count += 1;
-                    } else if ch != '\0' {
-                        count += 1;
-                    }
-                }
-                count
+                index_char_count as i32
             } else {
-                text_length
+                msg_as_i32(SendMessageW(
+                    edit_hwnd,
+                    WM_GETTEXTLENGTH,
+                    Some(WPARAM(0)),
+                    Some(LPARAM(0)),
+                ))
             };
 
             // Get zoom level
@@ -356,6 +565,8 @@ pub fn update_status_bar(
                 100
             };
 
+            let encoding_ambiguous = ENCODING_AMBIGUOUS.load(Ordering::SeqCst);
+
             // Check if values changed
             let current_status = (
                 display_line,
@@ -363,6 +574,7 @@ pub fn update_status_bar(
                 char_count,
                 zoom_percent,
                 current_encoding,
+                encoding_ambiguous,
             );
             let mut last = LAST_STATUS.lock().unwrap();
 
@@ -392,13 +604,15 @@ pub fn update_status_bar(
                 let _ = InvalidateRect(Some(pos_hwnd), None, true);
 
                 // Update encoding display
-                let encoding_text = match current_encoding {
-                    FileEncoding::Utf8 => "UTF-8\0".to_string(),
-                    FileEncoding::Utf8Bom => "UTF-8 (BOM)\0".to_string(),
-                    FileEncoding::Utf16Le => "UTF-16 LE\0".to_string(),
-                    FileEncoding::Utf16Be => "UTF-16 BE\0".to_string(),
-                    FileEncoding::ShiftJis => format!("{}\0", get_string("ENCODING_ANSI")),
-                    FileEncoding::Auto => format!("{}\0", get_string("ENCODING_AUTO")),
+                let encoding_name = encoding_name(current_encoding);
+                let encoding_text = if encoding_ambiguous {
+                    format!(
+                        "{}\0",
+                        get_string("STATUS_ENCODING_AMBIGUOUS")
+                            .replace("{encoding}", &encoding_name)
+                    )
+                } else {
+                    format!("{}\0", encoding_name)
                 };
                 let encoding_utf16: Vec<u16> = encoding_text.encode_utf16().collect();
                 let _ = SetWindowTextW(encoding_hwnd, PCWSTR(encoding_utf16.as_ptr()));
@@ -413,3 +627,18 @@ pub fn update_status_bar(
         }
     }
 }
+
+/// Show the line ending style in the status bar
+pub fn set_line_ending_text(linebreak_hwnd: HWND, line_ending: LineEnding) {
+    let linebreak_text = match line_ending {
+        LineEnding::Crlf => "Windows (CRLF)\0".to_string(),
+        LineEnding::Lf => "Unix (LF)\0".to_string(),
+        LineEnding::Cr => "Macintosh (CR)\0".to_string(),
+        LineEnding::Mixed => format!("{}\0", get_string("STATUS_LINEENDING_MIXED")),
+    };
+    let linebreak_utf16: Vec<u16> = linebreak_text.encode_utf16().collect();
+    unsafe {
+        let _ = SetWindowTextW(linebreak_hwnd, PCWSTR(linebreak_utf16.as_ptr()));
+        let _ = InvalidateRect(Some(linebreak_hwnd), None, true);
+    }
+}
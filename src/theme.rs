@@ -1,33 +1,19 @@
+use crate::color_theme::Theme;
+use crate::settings::ThemeMode;
+use std::path::PathBuf;
 use std::sync::{Mutex, Once, OnceLock};
 use windows::Win32::Foundation::HMODULE;
-use windows::Win32::Foundation::{COLORREF, HWND};
+use windows::Win32::Foundation::HWND;
 use windows::Win32::System::LibraryLoader::{GetProcAddress, LoadLibraryW};
 use windows::core::PCWSTR;
 
-// Dark mode colors
-pub const DARK_MENU_BG: COLORREF = COLORREF(0x00202020);
-pub const DARK_MENU_HOVER: COLORREF = COLORREF(0x00404040);
-pub const DARK_MENU_TEXT: COLORREF = COLORREF(0x00FFFFFF);
-pub const DARK_MENU_TEXT_DISABLED: COLORREF = COLORREF(0x00808080);
-pub const DARK_MENU_BORDER: COLORREF = COLORREF(0x00262624);
-pub const DARK_EDITOR_BG: COLORREF = COLORREF(0x001E1E1E);
-pub const DARK_EDITOR_TEXT: COLORREF = COLORREF(0x00E0E0E0);
-pub const DARK_STATUSBAR_BG: COLORREF = COLORREF(0x00202020);
-pub const DARK_STATUSBAR_TEXT: COLORREF = COLORREF(0x00E0E0E0);
-pub const DARK_SEPARATOR: COLORREF = COLORREF(0x00404040);
-
-// Light mode colors
-#[allow(dead_code)]
-pub const LIGHT_EDITOR_BG: COLORREF = COLORREF(0x00FFFFFF);
-pub const LIGHT_EDITOR_TEXT: COLORREF = COLORREF(0x00000000);
-pub const LIGHT_STATUSBAR_BG: COLORREF = COLORREF(0x00F0F0F0);
-pub const LIGHT_STATUSBAR_TEXT: COLORREF = COLORREF(0x00000000);
-pub const LIGHT_SEPARATOR: COLORREF = COLORREF(0x00D0D0D0);
-pub const LIGHT_MENU_BG: COLORREF = COLORREF(0x00F0F0F0);
-
-// Dark mode state
-pub static DARK_MODE_ENABLED: Mutex<bool> = Mutex::new(false);
+// Theme state
+static ACTIVE_THEME: Mutex<Theme> = Mutex::new(Theme::LIGHT);
 pub static DARK_MODE_INIT: Once = Once::new();
+// Light, dark, a user theme, or following the system
+static THEME_MODE: Mutex<ThemeMode> = Mutex::new(ThemeMode::System);
+// User theme names, read from the themes folder once
+static USER_THEMES: OnceLock<Vec<String>> = OnceLock::new();
 
 // UxTheme DLL handle (loaded once and reused)
 // Stored as isize to allow static sharing (HMODULE is not Send/Sync)
@@ -61,23 +47,129 @@ pub fn is_system_dark_mode() -> bool {
     }
 }
 
+fn system_theme() -> Theme {
+    if is_system_dark_mode() {
+        Theme::DARK
+    } else {
+        Theme::LIGHT
+    }
+}
+
 // Initialize dark mode from system settings
 pub fn init_dark_mode() {
     DARK_MODE_INIT.call_once(|| {
-        let system_dark = is_system_dark_mode();
-        if let Ok(mut dark_mode) = DARK_MODE_ENABLED.lock() {
-            *dark_mode = system_dark;
+        let theme = system_theme();
+        if let Ok(mut active_theme) = ACTIVE_THEME.lock() {
+            *active_theme = theme;
         }
     });
 }
 
+// Switch to a theme mode and make its colors the active theme. A user theme whose file
+// cannot be read falls back to the system setting.
+pub fn set_theme_mode(mode: ThemeMode) {
+    DARK_MODE_INIT.call_once(|| {});
+    let theme = match &mode {
+        ThemeMode::System => system_theme(),
+        ThemeMode::Light => Theme::LIGHT,
+        ThemeMode::Dark => Theme::DARK,
+        ThemeMode::User(name) => load_user_theme(name).unwrap_or_else(system_theme),
+    };
+    if let Ok(mut theme_mode) = THEME_MODE.lock() {
+        *theme_mode = mode;
+    }
+    if let Ok(mut active_theme) = ACTIVE_THEME.lock() {
+        *active_theme = theme;
+    }
+}
+
+pub fn theme_mode() -> ThemeMode {
+    THEME_MODE
+        .lock()
+        .map(|mode| mode.clone())
+        .unwrap_or(ThemeMode::System)
+}
+
+// The colors every paint path draws with
+pub fn active_theme() -> Theme {
+    init_dark_mode();
+    ACTIVE_THEME
+        .lock()
+        .map(|theme| *theme)
+        .unwrap_or(Theme::LIGHT)
+}
+
+// The built-in light theme leaves the menu bar and static controls to Windows
+pub fn uses_system_colors() -> bool {
+    active_theme() == Theme::LIGHT
+}
+
+// The themes folder next to the executable
+fn themes_dir() -> Option<PathBuf> {
+    Some(std::env::current_exe().ok()?.with_file_name("themes"))
+}
+
+// Names of the theme files in the themes folder, sorted. Names of the built-in themes are
+// left out, since a saved Theme setting with those names means the built-in one.
+pub fn user_themes() -> &'static [String] {
+    USER_THEMES.get_or_init(|| {
+        let Some(entries) = themes_dir().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+            return Vec::new();
+        };
+        let mut names: Vec<String> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension.eq_ignore_ascii_case("ini"))
+            })
+            .filter_map(|path| Some(path.file_stem()?.to_str()?.to_string()))
+            .filter(|name| {
+                !["System", "Light", "Dark"]
+                    .iter()
+                    .any(|builtin| name.eq_ignore_ascii_case(builtin))
+            })
+            .collect();
+        names.sort_by_key(|name| name.to_lowercase());
+        names
+    })
+}
+
+fn load_user_theme(name: &str) -> Option<Theme> {
+    let path = themes_dir()?.join(format!("{}.ini", name));
+    let bytes = std::fs::read(path).ok()?;
+    let text = String::from_utf8_lossy(&bytes);
+    Some(Theme::parse(text.strip_prefix('\u{FEFF}').unwrap_or(&text)))
+}
+
+// Read the system setting again when following it. Returns true if dark mode changed.
+pub fn refresh_system_dark_mode() -> bool {
+    if theme_mode() != ThemeMode::System {
+        return false;
+    }
+    let was_dark = should_use_dark_mode();
+    refresh_immersive_color_policy_state();
+    set_theme_mode(ThemeMode::System);
+    should_use_dark_mode() != was_dark
+}
+
 // Check if dark mode is enabled
 pub fn should_use_dark_mode() -> bool {
-    init_dark_mode();
-    if let Ok(enabled) = DARK_MODE_ENABLED.lock() {
-        *enabled
-    } else {
-        false
+    active_theme().dark
+}
+
+// Make ShouldAppsUseDarkMode see a system setting changed since it was first called
+fn refresh_immersive_color_policy_state() {
+    unsafe {
+        if let Some(huxtheme) = get_uxtheme_handle() {
+            // Ordinal 104 = RefreshImmersiveColorPolicyState (undocumented API)
+            if let Some(func_addr) =
+                GetProcAddress(huxtheme, windows::core::PCSTR(104 as *const u8))
+            {
+                type RefreshImmersiveColorPolicyState = unsafe extern "system" fn();
+                let func: RefreshImmersiveColorPolicyState = std::mem::transmute(func_addr);
+                func();
+            }
+        }
     }
 }
 
@@ -0,0 +1,143 @@
+// UTF-8, UTF-16 and UTF-32 decoders that report invalid sequences
+//
+// Like the legacy decoders, each one replaces invalid input with U+FFFD and also
+// returns the byte offset of every replacement. Byte order marks are not stripped here.
+
+/// Decode UTF-8, returning the text and the byte offset of every invalid sequence
+pub fn decode_utf8(bytes: &[u8]) -> (String, Vec<usize>) {
+    let mut text = String::with_capacity(bytes.len());
+    let mut errors = Vec::new();
+    let mut offset = 0;
+
+    for chunk in bytes.utf8_chunks() {
+        text.push_str(chunk.valid());
+        offset += chunk.valid().len();
+        if !chunk.invalid().is_empty() {
+            text.push('\u{FFFD}');
+            errors.push(offset);
+            offset += chunk.invalid().len();
+        }
+    }
+
+    (text, errors)
+}
+
+/// Decode UTF-16, returning the text and the byte offset of every invalid sequence
+///
+/// Unpaired surrogates and a trailing odd byte are reported as errors.
+pub fn decode_utf16(bytes: &[u8], big_endian: bool) -> (String, Vec<usize>) {
+    let units = bytes.chunks_exact(2).map(|chunk| {
+        if big_endian {
+            u16::from_be_bytes([chunk[0], chunk[1]])
+        } else {
+            u16::from_le_bytes([chunk[0], chunk[1]])
+        }
+    });
+
+    let mut text = String::with_capacity(bytes.len() / 2);
+    let mut errors = Vec::new();
+    let mut offset = 0;
+
+    for decoded in char::decode_utf16(units) {
+        match decoded {
+            Ok(ch) => {
+                text.push(ch);
+                offset += ch.len_utf16() * 2;
+            }
+            Err(_) => {
+                text.push('\u{FFFD}');
+                errors.push(offset);
+                offset += 2;
+            }
+        }
+    }
+
+    if !bytes.len().is_multiple_of(2) {
+        text.push('\u{FFFD}');
+        errors.push(bytes.len() - 1);
+    }
+
+    (text, errors)
+}
+
+/// Decode UTF-32, returning the text and the byte offset of every invalid sequence
+///
+/// Values that are not Unicode scalar values and trailing bytes are reported as errors.
+pub fn decode_utf32(bytes: &[u8], big_endian: bool) -> (String, Vec<usize>) {
+    let mut text = String::with_capacity(bytes.len() / 4);
+    let mut errors = Vec::new();
+
+    for (index, chunk) in bytes.chunks_exact(4).enumerate() {
+        let bytes = [chunk[0], chunk[1], chunk[2], chunk[3]];
+        let code = if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        };
+        match char::from_u32(code) {
+            Some(ch) => text.push(ch),
+            None => {
+                text.push('\u{FFFD}');
+                errors.push(index * 4);
+            }
+        }
+    }
+
+    if !bytes.len().is_multiple_of(4) {
+        text.push('\u{FFFD}');
+        errors.push(bytes.len() - bytes.len() % 4);
+    }
+
+    (text, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_utf8_valid() {
+        assert_eq!(
+            decode_utf8("aあ😀".as_bytes()),
+            ("aあ😀".to_string(), vec![])
+        );
+    }
+
+    #[test]
+    fn test_utf8_errors() {
+        // Lone continuation byte, then a truncated three-byte sequence at the end
+        let (text, errors) = decode_utf8(&[0x61, 0x80, 0x62, 0xE3, 0x81]);
+        assert_eq!(text, "a\u{FFFD}b\u{FFFD}");
+        assert_eq!(errors, vec![1, 3]);
+    }
+
+    #[test]
+    fn test_utf16_surrogate_pairs() {
+        let bytes: Vec<u8> = "a😀"
+            .encode_utf16()
+            .flat_map(|unit| unit.to_be_bytes())
+            .collect();
+        assert_eq!(decode_utf16(&bytes, true), ("a😀".to_string(), vec![]));
+    }
+
+    #[test]
+    fn test_utf16_errors() {
+        // Unpaired high surrogate, "a", then an odd trailing byte
+        let (text, errors) = decode_utf16(&[0x3D, 0xD8, 0x61, 0x00, 0x62], false);
+        assert_eq!(text, "\u{FFFD}a\u{FFFD}");
+        assert_eq!(errors, vec![0, 4]);
+    }
+
+    #[test]
+    fn test_utf32_errors() {
+        let bytes = [
+            0x00, 0x00, 0x00, 0x61, // a
+            0x00, 0x00, 0xD8, 0x00, // surrogate
+            0x00, 0x11, 0x00, 0x00, // above U+10FFFF
+            0x00, 0x00, // truncated
+        ];
+        let (text, errors) = decode_utf32(&bytes, true);
+        assert_eq!(text, "a\u{FFFD}\u{FFFD}\u{FFFD}");
+        assert_eq!(errors, vec![4, 8, 12]);
+    }
+}
@@ -0,0 +1,122 @@
+// Find characters that an encoding cannot represent
+//
+// The Japanese legacy encoders write `?` in place of such characters, so the text is
+// checked before saving and the user can choose what to do.
+
+use crate::cp932;
+use crate::euc_jp;
+use crate::file_io::FileEncoding;
+use crate::iso_2022_jp;
+use crate::line_column::calculate_line_column;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UnmappableChar {
+    pub ch: char,
+    /// 1-indexed line, as shown in the status bar
+    pub line: i32,
+    /// 1-indexed column, as shown in the status bar
+    pub col: i32,
+}
+
+/// Returns true if the encoding can represent the character
+///
+/// The Unicode encodings can represent everything.
+pub fn can_encode(ch: char, encoding: FileEncoding) -> bool {
+    match encoding {
+        FileEncoding::ShiftJis => cp932::can_encode(ch),
+        FileEncoding::EucJp => euc_jp::can_encode(ch),
+        FileEncoding::Iso2022Jp => iso_2022_jp::can_encode(ch),
+        _ => true,
+    }
+}
+
+/// List every character in the text that the encoding cannot represent, in text order
+pub fn find(text: &str, encoding: FileEncoding) -> Vec<UnmappableChar> {
+    let mut result = Vec::new();
+
+    // Each lookup starts from the previous hit, so only the text in between is scanned
+    let mut base_byte = 0;
+    let mut base_utf16 = 0;
+    let mut base_line = 1;
+    let mut base_col = 1;
+
+    let mut utf16_pos = 0i32;
+    for (byte_pos, ch) in text.char_indices() {
+        if !can_encode(ch, encoding) {
+            let (line, col) = calculate_line_column(&text[base_byte..], utf16_pos - base_utf16);
+            let (line, col) = if line == 1 {
+                (base_line, base_col + col - 1)
+            } else {
+                (base_line + line - 1, col)
+            };
+            result.push(UnmappableChar { ch, line, col });
+
+            base_byte = byte_pos;
+            base_utf16 = utf16_pos;
+            base_line = line;
+            base_col = col;
+        }
+        utf16_pos += ch.len_utf16() as i32;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unicode_encodings_accept_everything() {
+        let text = "あ😀\u{1B}";
+        assert!(find(text, FileEncoding::Utf8).is_empty());
+        assert!(find(text, FileEncoding::Utf16Le).is_empty());
+        assert!(find(text, FileEncoding::Utf32Be).is_empty());
+    }
+
+    #[test]
+    fn test_shift_jis_emoji() {
+        assert_eq!(
+            find("abc\r\nあ😀", FileEncoding::ShiftJis),
+            vec![UnmappableChar {
+                ch: '😀',
+                line: 2,
+                col: 2
+            }]
+        );
+    }
+
+    #[test]
+    fn test_several_on_one_line() {
+        let found = find("😀x😀\r\n😀", FileEncoding::ShiftJis);
+        let positions: Vec<(i32, i32)> = found.iter().map(|c| (c.line, c.col)).collect();
+        assert_eq!(positions, vec![(1, 1), (1, 3), (2, 1)]);
+    }
+
+    #[test]
+    fn test_mappable_text() {
+        assert!(find("漢字ｱ\r\nabc", FileEncoding::ShiftJis).is_empty());
+        assert!(find("漢字ｱ\r\nabc", FileEncoding::EucJp).is_empty());
+        assert!(find("漢字ｱ\r\nabc", FileEncoding::Iso2022Jp).is_empty());
+    }
+
+    #[test]
+    fn test_ibm_extension_outside_jis_rows() {
+        // U+2170 (small roman numeral one) only exists in the IBM extension rows
+        assert!(can_encode('\u{2170}', FileEncoding::ShiftJis));
+        assert!(can_encode('\u{2170}', FileEncoding::EucJp));
+        assert!(!can_encode('\u{1F600}', FileEncoding::EucJp));
+    }
+
+    #[test]
+    fn test_iso_2022_jp_escape() {
+        assert_eq!(
+            find("a\u{1B}", FileEncoding::Iso2022Jp),
+            vec![UnmappableChar {
+                ch: '\u{1B}',
+                line: 1,
+                col: 2
+            }]
+        );
+    }
+}